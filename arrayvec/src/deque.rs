@@ -0,0 +1,180 @@
+use core::{
+    mem::MaybeUninit,
+    ops::{Index, IndexMut},
+    slice,
+};
+
+/// A double-ended queue of at most `N` elements, stored as a ring in place.
+pub struct ArrayDeque<T, const N: usize> {
+    data: [MaybeUninit<T>; N],
+    /// Slot of the front element.
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayDeque<T, N> {
+    pub fn new() -> Self {
+        Self {
+            data: unsafe { MaybeUninit::uninit().assume_init() },
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn push_back(&mut self, obj: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(obj);
+        }
+        let slot = self.slot(self.len);
+        self.data[slot].write(obj);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn push_front(&mut self, obj: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(obj);
+        }
+        self.head = (self.head + N - 1) % N;
+        self.data[self.head].write(obj);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let obj = unsafe { self.data[self.head].as_ptr().read() };
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(obj)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        Some(unsafe { self.data[self.slot(self.len)].as_ptr().read() })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.len.checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    /// The element `index` places from the front.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len).then(|| unsafe { &*self.data[self.slot(index)].as_ptr() })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.len {
+            let slot = self.slot(index);
+            return Some(unsafe { &mut *self.data[slot].as_mut_ptr() });
+        }
+        None
+    }
+
+    /// The elements from the front, in two parts as they wrap around the
+    /// end of the storage. The second part is empty if they don't.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (first, second) = self.ranges();
+        let ptr = self.data.as_ptr() as *const T;
+        unsafe {
+            (
+                slice::from_raw_parts(ptr.add(first.0), first.1),
+                slice::from_raw_parts(ptr.add(second.0), second.1),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (first, second) = self.ranges();
+        let ptr = self.data.as_mut_ptr() as *mut T;
+        // The two ranges never overlap.
+        unsafe {
+            (
+                slice::from_raw_parts_mut(ptr.add(first.0), first.1),
+                slice::from_raw_parts_mut(ptr.add(second.0), second.1),
+            )
+        }
+    }
+
+    /// From front to back.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        let (first, second) = self.as_slices();
+        first.iter().chain(second)
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_back().is_some() {}
+    }
+
+    /// `(start, len)` of the two parts of `as_slices`.
+    fn ranges(&self) -> ((usize, usize), (usize, usize)) {
+        let first = self.len.min(N - self.head);
+        ((self.head, first), (0, self.len - first))
+    }
+
+    /// Only called with an element at or room for `index`, so `N` > 0.
+    fn slot(&self, index: usize) -> usize {
+        (self.head + index) % N
+    }
+}
+
+impl<T, const N: usize> Default for ArrayDeque<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Index<usize> for ArrayDeque<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match self.get(index) {
+            Some(obj) => obj,
+            None => panic!("index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for ArrayDeque<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match self.get_mut(index) {
+            Some(obj) => obj,
+            None => panic!("index out of bounds"),
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayDeque<T, N> {
+    fn drop(&mut self) {
+        let (first, second) = self.as_mut_slices();
+        unsafe {
+            core::ptr::drop_in_place(first);
+            core::ptr::drop_in_place(second);
+        }
+    }
+}
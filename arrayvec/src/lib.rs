@@ -1,8 +1,17 @@
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+mod deque;
+
+pub use deque::ArrayDeque;
+
 use core::{
+    fmt,
     mem::MaybeUninit,
     ops::{Index, IndexMut},
+    slice,
 };
 
 pub struct ArrayVec<T, const N: usize> {
@@ -48,6 +57,99 @@ impl<T, const N: usize> ArrayVec<T, N> {
             None
         }
     }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.data.as_ptr() as *const T, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.data.as_mut_ptr() as *mut T, self.len) }
+    }
+
+    /// Panics if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (&[T], &[T]) {
+        self.as_slice().split_at(mid)
+    }
+
+    /// Panics if `mid > len`.
+    pub fn split_at_mut(&mut self, mid: usize) -> (&mut [T], &mut [T]) {
+        self.as_mut_slice().split_at_mut(mid)
+    }
+
+    /// Drops the elements past the first `len`, if any.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.pop();
+        }
+    }
+
+    /// Like `truncate`, but moves the elements past the first `len` into the
+    /// returned vector instead of dropping them. It is empty if `len` is at
+    /// least the length.
+    pub fn swap_truncate(&mut self, len: usize) -> Self {
+        self.split_off(len.min(self.len))
+    }
+
+    /// Moves the elements from `at` on into a new vector, leaving `[0, at)`.
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        match self.split_off_into(at) {
+            Some(tail) => tail,
+            None => unreachable!("the tail always fits the same capacity"),
+        }
+    }
+
+    /// Like `split_off`, but into a vector of another capacity. Returns `None`
+    /// and keeps `self` as is if the tail doesn't fit.
+    pub fn split_off_into<const M: usize>(&mut self, at: usize) -> Option<ArrayVec<T, M>> {
+        assert!(at <= self.len, "split index out of bounds");
+        if self.len - at > M {
+            return None;
+        }
+        let mut tail = ArrayVec::new();
+        for (slot, elem) in tail.data.iter_mut().zip(&self.data[at..self.len]) {
+            slot.write(unsafe { elem.as_ptr().read() });
+        }
+        tail.len = self.len - at;
+        self.len = at;
+        Some(tail)
+    }
+}
+
+impl<const N: usize> ArrayVec<u8, N> {
+    /// Appends as many bytes from `bytes` as fit, returning how many did.
+    fn append_bytes(&mut self, bytes: &[u8]) -> usize {
+        let count = bytes.len().min(N - self.len);
+        for (slot, byte) in self.data[self.len..].iter_mut().zip(&bytes[..count]) {
+            slot.write(*byte);
+        }
+        self.len += count;
+        count
+    }
+}
+
+/// Fails without writing anything if the string does not fit.
+impl<const N: usize> fmt::Write for ArrayVec<u8, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() > N - self.len {
+            return Err(fmt::Error);
+        }
+        self.append_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Writes as many bytes as fit, so `write_all` fails with `WriteZero` once the
+/// buffer is full.
+#[cfg(feature = "std")]
+impl<const N: usize> std::io::Write for ArrayVec<u8, N> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(self.append_bytes(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 impl<T, const N: usize> Default for ArrayVec<T, N> {
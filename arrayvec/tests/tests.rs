@@ -1,4 +1,4 @@
-use arrayvec::ArrayVec;
+use arrayvec::{ArrayDeque, ArrayVec};
 
 use std::{
     mem::{size_of, size_of_val},
@@ -58,3 +58,158 @@ fn test_drop() {
     drop(v);
     assert_eq!(Rc::strong_count(&obj), 1);
 }
+
+#[test]
+fn test_split() {
+    let mut packet = ArrayVec::<u8, 8>::new();
+    for byte in 1..=6 {
+        packet.push(byte).unwrap();
+    }
+    assert_eq!(packet.split_at(2), (&[1, 2][..], &[3, 4, 5, 6][..]));
+    packet.split_at_mut(2).1[0] = 30;
+
+    assert!(packet.split_off_into::<2>(2).is_none());
+    assert_eq!(packet.len(), 6);
+    let mut body = packet.split_off_into::<4>(2).unwrap();
+    assert_eq!(packet.as_slice(), [1, 2]);
+    assert_eq!(body.as_slice(), [30, 4, 5, 6]);
+    assert_eq!(body.capacity(), 4);
+
+    let trailer = body.split_off(3);
+    assert_eq!(body.as_slice(), [30, 4, 5]);
+    assert_eq!(trailer.as_slice(), [6]);
+    assert_eq!(trailer.capacity(), 4);
+    assert!(body.split_off(3).is_empty());
+
+    let mut header = body.swap_truncate(1);
+    assert_eq!(body.as_slice(), [30]);
+    assert_eq!(header.as_slice(), [4, 5]);
+    assert!(header.swap_truncate(2).is_empty());
+    assert!(header.swap_truncate(5).is_empty());
+    assert_eq!(header.as_slice(), [4, 5]);
+
+    let obj = Rc::new(0);
+    let mut v = ArrayVec::<_, 4>::new();
+    for _ in 0..4 {
+        v.push(obj.clone()).ok();
+    }
+    let tail = v.split_off(1);
+    assert_eq!(Rc::strong_count(&obj), 5);
+    v.truncate(0);
+    assert_eq!(Rc::strong_count(&obj), 4);
+    drop(tail);
+    assert_eq!(Rc::strong_count(&obj), 1);
+}
+
+#[test]
+#[should_panic]
+fn test_split_off_out_of_bounds_panic() {
+    let mut v = ArrayVec::<i32, 4>::new();
+    v.push(1).ok();
+    v.split_off(2);
+}
+
+#[test]
+fn test_fmt_write() {
+    use std::fmt::Write;
+
+    let mut buf = ArrayVec::<u8, 8>::new();
+    assert!(buf.as_slice().is_empty());
+    let (number, word) = (12, "ab");
+    write!(buf, "{number}-{word}").unwrap();
+    assert_eq!(buf.as_slice(), b"12-ab");
+    assert!(buf.write_str("long").is_err());
+    assert_eq!(buf.as_slice(), b"12-ab");
+    buf.write_str("cde").unwrap();
+    assert_eq!(buf.as_slice(), b"12-abcde");
+    assert!(buf.write_char('!').is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_io_write() {
+    use std::io::{ErrorKind, Write};
+
+    let mut buf = ArrayVec::<u8, 4>::new();
+    assert_eq!(buf.write(b"abc").unwrap(), 3);
+    assert_eq!(buf.write(b"def").unwrap(), 1);
+    assert_eq!(buf.write(b"g").unwrap(), 0);
+    assert_eq!(buf.as_slice(), b"abcd");
+    buf.flush().unwrap();
+
+    let mut buf = ArrayVec::<u8, 4>::new();
+    let err = buf.write_all(b"hello").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(buf.as_slice(), b"hell");
+}
+
+#[test]
+fn test_deque() {
+    let mut d = ArrayDeque::<i32, 3>::new();
+    assert_eq!(d.pop_front(), None);
+    assert_eq!(d.pop_back(), None);
+    assert_eq!(d.push_back(2), Ok(()));
+    assert_eq!(d.push_front(1), Ok(()));
+    assert_eq!(d.push_back(3), Ok(()));
+    assert!(d.is_full());
+    assert_eq!(d.push_front(0), Err(0));
+    assert_eq!(d.push_back(4), Err(4));
+    assert_eq!((d.front(), d.back()), (Some(&1), Some(&3)));
+    assert_eq!(d.as_slices(), (&[1][..], &[2, 3][..]));
+    assert_eq!(d.iter().rev().copied().collect::<Vec<_>>(), [3, 2, 1]);
+
+    assert_eq!(d.pop_front(), Some(1));
+    assert_eq!(d.pop_back(), Some(3));
+    d[0] = 20;
+    assert_eq!(d.push_back(30), Ok(()));
+    assert_eq!(d.push_back(40), Ok(()));
+    assert_eq!(d.as_slices(), (&[20, 30, 40][..], &[][..]));
+    d.as_mut_slices().0[2] = 41;
+    assert_eq!((d[0], d[1], d[2]), (20, 30, 41));
+    assert_eq!(d.get(3), None);
+
+    // Around the ring a few times.
+    for i in 0..10 {
+        let front = d[0];
+        assert_eq!(d.pop_front(), Some(front));
+        d.push_back(i).unwrap();
+    }
+    assert_eq!(d.iter().copied().collect::<Vec<_>>(), [7, 8, 9]);
+    d.clear();
+    assert!(d.is_empty());
+    assert_eq!((d.front(), d.back()), (None, None));
+
+    let mut d = ArrayDeque::<i32, 0>::new();
+    assert_eq!(d.push_front(1), Err(1));
+    assert_eq!(d.push_back(1), Err(1));
+    assert_eq!(d.pop_front(), None);
+    assert_eq!(d.as_slices(), (&[][..], &[][..]));
+}
+
+#[test]
+#[should_panic]
+fn test_deque_out_of_bounds_panic() {
+    let mut d = ArrayDeque::<i32, 4>::new();
+    d.push_front(1).ok();
+    let _ = d[1];
+}
+
+#[test]
+fn test_deque_drop() {
+    let obj = Rc::new(50);
+
+    let mut d = ArrayDeque::<_, 4>::new();
+    for _ in 0..3 {
+        d.push_back(obj.clone()).ok();
+    }
+    d.pop_front();
+    d.push_back(obj.clone()).ok();
+    d.push_back(obj.clone()).ok();
+    // Wrapped around the end of the storage.
+    assert_eq!(d.as_slices().1.len(), 1);
+    assert_eq!(Rc::strong_count(&obj), 5);
+    d.pop_back();
+    assert_eq!(Rc::strong_count(&obj), 4);
+    drop(d);
+    assert_eq!(Rc::strong_count(&obj), 1);
+}
@@ -64,7 +64,7 @@ impl Display for Nibble {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Address(u16);
 
 impl Address {
@@ -80,7 +80,7 @@ impl Address {
         Self(value % Self::DOMAIN_SIZE as u16)
     }
 
-    pub fn as_usize(self) -> usize {
+    pub const fn as_usize(self) -> usize {
         self.0 as usize
     }
 }
@@ -14,8 +14,12 @@ pub enum Error {
     StackOverflow,
     #[error("invalid key: {0:#04x}")]
     InvalidKey(Word),
+    #[error("memory access out of bounds: {0:#06x}")]
+    MemoryOutOfBounds(usize),
     #[error("invalid sprite: address {0}, size {1}")]
     InvalidSprite(Address, Nibble),
+    #[error("invalid key map: {0}")]
+    InvalidKeyMap(String),
     #[error("the interpreter has crashed and is now unrecoverable")]
     Crashed,
 }
@@ -1,3 +1,5 @@
+use std::{fs, io, path::Path};
+
 use crate::data::Address;
 
 use thiserror::Error;
@@ -13,8 +15,10 @@ pub trait Image {
 
 #[derive(Error, Debug)]
 pub enum Ch8ImageError {
-    #[error("image is too big")]
-    TooBig,
+    #[error("image is too big: {size} bytes, at most {max_size} fit into memory")]
+    TooBig { size: usize, max_size: usize },
+    #[error("failed to read image: {0}")]
+    Io(#[from] io::Error),
 }
 
 #[derive(Copy, Clone)]
@@ -25,23 +29,35 @@ pub struct Ch8Image<T: AsRef<[u8]>> {
 impl<T: AsRef<[u8]>> Ch8Image<T> {
     const BASE_ADDRESS: Address = Address::new(0x200);
 
+    pub const MAX_SIZE: usize = Address::DOMAIN_SIZE - Self::BASE_ADDRESS.as_usize();
+
     pub fn new(data: T) -> Result<Self, Ch8ImageError> {
-        if data.as_ref().len() > Address::MAX.as_usize() {
-            Err(Ch8ImageError::TooBig)
+        let size = data.as_ref().len();
+        if size > Self::MAX_SIZE {
+            Err(Ch8ImageError::TooBig {
+                size,
+                max_size: Self::MAX_SIZE,
+            })
         } else {
             Ok(Self { data })
         }
     }
+
+    pub fn size(&self) -> usize {
+        self.data.as_ref().len()
+    }
+}
+
+impl Ch8Image<Vec<u8>> {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Ch8ImageError> {
+        Self::new(fs::read(path)?)
+    }
 }
 
 impl<T: AsRef<[u8]>> Image for Ch8Image<T> {
     fn load_into_memory(&self, memory: &mut [u8; Address::DOMAIN_SIZE]) {
-        for (i, &b) in self.data.as_ref().iter().enumerate() {
-            let addr = self.entry_point().as_usize() + i;
-            if addr < Address::DOMAIN_SIZE {
-                memory[addr] = b;
-            }
-        }
+        let start = self.entry_point().as_usize();
+        memory[start..start + self.size()].copy_from_slice(self.data.as_ref());
     }
 
     fn entry_point(&self) -> Address {
@@ -1,21 +1,61 @@
+use std::ops::Range;
+
 use crate::{
     data::{Address, Nibble, OpCode, RegisterIndex, Word},
     image::Image,
+    peripheral::Peripheral,
     platform::{Platform, Point, Sprite},
-    Error, Key, Result, KEYPAD_LAST,
+    Error, IndexIncrement, Key, KeyEventKind, Quirks, Result, KEYPAD_LAST,
 };
 
 ////////////////////////////////////////////////////////////////////////////////
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
 
 ////////////////////////////////////////////////////////////////////////////////
 
 pub const MEM_SIZE: usize = Address::DOMAIN_SIZE;
+pub const FONT_ADDRESS: Address = Address::new(0x050);
+pub const FONT_SPRITE_SIZE: usize = 5;
+
+/// Sprites for the hexadecimal digits `0`..`F`, 4x5 pixels each.
+pub const FONT: [Word; 16 * FONT_SPRITE_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
 pub const REG_SIZE: usize = 16;
 pub const STACK_SIZE: usize = 16;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionState {
+    Running,
+    /// Halted on `Fx0A` until a key event of the kind selected by
+    /// `Quirks::key_wait_event` arrives. `pressed` is the key that went down.
+    WaitingForKey {
+        register: RegisterIndex,
+        pressed: Option<Key>,
+    },
+    /// The program has executed `00FD`.
+    Exited,
+}
+
 struct ProgramCounter(usize);
 impl ProgramCounter {
     const STEP: usize = 2;
@@ -36,10 +76,28 @@ pub struct Interpreter<P: Platform> {
     pc: ProgramCounter,
     sp: usize,
     call_stack: [usize; STACK_SIZE],
+    quirks: Quirks,
+    high_resolution: bool,
+    /// SCHIP "RPL user flags", persisted by `Fx75` and restored by `Fx85`.
+    flags: [Word; REG_SIZE],
+    state: ExecutionState,
+    font_address: Address,
+    peripherals: Vec<Box<dyn Peripheral>>,
 }
 
 impl<P: Platform> Interpreter<P> {
-    pub fn new(image: impl Image, platform: P) -> Self {
+    pub fn new(image: impl Image, platform: P, quirks: Quirks) -> Self {
+        Self::new_with_font_address(image, platform, quirks, FONT_ADDRESS)
+    }
+
+    /// Like `new`, but places the built-in font at `font_address`.
+    /// The image is loaded afterwards and may overwrite it.
+    pub fn new_with_font_address(
+        image: impl Image,
+        platform: P,
+        quirks: Quirks,
+        font_address: Address,
+    ) -> Self {
         let mut interp = Self {
             registers: [0; REG_SIZE],
             platform,
@@ -48,8 +106,17 @@ impl<P: Platform> Interpreter<P> {
             pc: ProgramCounter(image.entry_point().as_usize()),
             sp: 0,
             call_stack: [0; STACK_SIZE],
+            quirks,
+            high_resolution: false,
+            flags: [0; REG_SIZE],
+            state: ExecutionState::Running,
+            font_address,
+            peripherals: Vec::new(),
         };
 
+        let font_start = font_address.as_usize();
+        let font_end = (font_start + FONT.len()).min(MEM_SIZE);
+        interp.memory[font_start..font_end].copy_from_slice(&FONT[..font_end - font_start]);
         image.load_into_memory(&mut interp.memory);
 
         interp
@@ -63,8 +130,83 @@ impl<P: Platform> Interpreter<P> {
         &mut self.platform
     }
 
+    /// Attaches `peripheral` after the ones already registered.
+    pub fn add_peripheral(&mut self, peripheral: impl Peripheral + 'static) {
+        self.peripherals.push(Box::new(peripheral));
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn registers(&self) -> &[Word; REG_SIZE] {
+        &self.registers
+    }
+
+    pub fn index_register(&self) -> usize {
+        self.index_register
+    }
+
+    pub fn registers_mut(&mut self) -> &mut [Word; REG_SIZE] {
+        &mut self.registers
+    }
+
+    pub fn set_index_register(&mut self, addr: Address) {
+        self.index_register = addr.as_usize();
+    }
+
+    pub fn memory(&self) -> &[Word; MEM_SIZE] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [Word; MEM_SIZE] {
+        &mut self.memory
+    }
+
+    pub fn program_counter(&self) -> Address {
+        Address::new(self.pc.0 as u16)
+    }
+
+    pub fn set_program_counter(&mut self, addr: Address) {
+        self.pc.0 = addr.as_usize();
+    }
+
+    pub fn execution_state(&self) -> ExecutionState {
+        self.state
+    }
+
+    /// Whether the program has executed `00FD`. Further instructions are
+    /// not run.
+    pub fn has_exited(&self) -> bool {
+        self.state == ExecutionState::Exited
+    }
+
+    /// Decodes the instruction at the program counter without running it.
+    pub fn next_operation(&self) -> Result<Operation> {
+        Operation::try_from(self.extract_opcode()?)
+    }
+
     pub fn run_next_instruction(&mut self) -> Result<()> {
-        match Operation::try_from(self.extract_opcode()) {
+        match self.state {
+            ExecutionState::Running => {}
+            ExecutionState::WaitingForKey { register, pressed } => {
+                self.poll_key(register, pressed);
+                return Ok(());
+            }
+            ExecutionState::Exited => return Ok(()),
+        }
+        let opcode = self.extract_opcode()?;
+        for peripheral in &mut self.peripherals {
+            if peripheral.execute(opcode, &mut self.registers) {
+                self.pc.next();
+                return Ok(());
+            }
+        }
+        match self.next_operation() {
             Ok(operation) => {
                 match operation {
                     // Test 1: Chip logo
@@ -72,7 +214,7 @@ impl<P: Platform> Interpreter<P> {
                     Operation::Jump(addr) => self.jmp(addr),
                     Operation::SetRegister(vx, nn) => self.set_reg(vx, nn),
                     Operation::SetIndexRegister(addr) => self.set_i(addr),
-                    Operation::Draw(vx, vy, n) => self.draw(vx, vy, n),
+                    Operation::Draw(vx, vy, n) => self.draw(vx, vy, n)?,
                     // Test 2: IBM logo
                     Operation::AddValue(vx, nn) => self.add_value(vx, nn),
                     // Test 3, 4: Corax, Flags
@@ -91,10 +233,10 @@ impl<P: Platform> Interpreter<P> {
                     Operation::SubRegisterReversed(vx, vy) => self.sub_rev(vx, vy),
                     Operation::ShiftRight(vx, vy) => self.shr(vx, vy),
                     Operation::ShiftLeft(vx, vy) => self.shl(vx, vy),
-                    Operation::ReadMemory(vx) => self.read(vx),
-                    Operation::WriteMemory(vx) => self.write(vx),
-                    Operation::ToDecimal(vx) => self.dec(vx),
-                    Operation::IncrementIndexRegister(vx) => self.incr_i(vx),
+                    Operation::ReadMemory(vx) => self.read(vx)?,
+                    Operation::WriteMemory(vx) => self.write(vx)?,
+                    Operation::ToDecimal(vx) => self.dec(vx)?,
+                    Operation::IncrementIndexRegister(vx) => self.incr_i(vx)?,
                     // Test 5: Quirks
                     Operation::SkipIfKeyDown(vx) => self.key_down(vx)?,
                     Operation::SkipIfKeyUp(vx) => self.key_up(vx)?,
@@ -107,15 +249,61 @@ impl<P: Platform> Interpreter<P> {
                     // other
                     Operation::SetToRandom(vx, nn) => self.set_rng(vx, nn),
                     Operation::SetIndexRegisterToSprite(vx) => self.set_sprite(vx),
+                    // SCHIP
+                    Operation::ScrollDown(n) => self.scroll_down(n),
+                    Operation::ScrollRight => self.scroll_right(),
+                    Operation::ScrollLeft => self.scroll_left(),
+                    Operation::Exit => self.exit(),
+                    Operation::LowResolution => self.set_resolution(false),
+                    Operation::HighResolution => self.set_resolution(true),
+                    Operation::SaveFlags(vx) => self.save_flags(vx),
+                    Operation::LoadFlags(vx) => self.load_flags(vx),
                 }
                 Ok(())
             }
+            Err(err @ Error::MemoryOutOfBounds(_)) => Err(err),
             Err(_) => Err(Error::Crashed),
         }
     }
 
-    fn extract_opcode(&self) -> OpCode {
-        OpCode::new((self.memory[self.pc.0] as u16) << 8 | (self.memory[self.pc.0 + 1] as u16))
+    fn extract_opcode(&self) -> Result<OpCode> {
+        let code = &self.memory[self.memory_range(self.pc.0, ProgramCounter::STEP)?];
+        Ok(OpCode::from_bytes(code[0], code[1]))
+    }
+
+    /// Checks that `len` bytes starting at `start` lie within memory.
+    fn memory_range(&self, start: usize, len: usize) -> Result<Range<usize>> {
+        match start.checked_add(len) {
+            Some(end) if end <= MEM_SIZE => Ok(start..end),
+            _ => Err(Error::MemoryOutOfBounds(start)),
+        }
+    }
+
+    /// Reads `range` into `buf`, letting peripherals serve the addresses they
+    /// claim.
+    fn load(&mut self, range: Range<usize>, buf: &mut [Word]) {
+        buf.copy_from_slice(&self.memory[range.clone()]);
+        if self.peripherals.is_empty() {
+            return;
+        }
+        for (addr, value) in range.zip(buf) {
+            let addr = Address::new(addr as u16);
+            if let Some(peripheral) = self.peripherals.iter_mut().find(|p| p.claims(addr)) {
+                *value = peripheral.read(addr);
+            }
+        }
+    }
+
+    /// Writes `data` to `range`, letting peripherals take the addresses they
+    /// claim.
+    fn store(&mut self, range: Range<usize>, data: &[Word]) {
+        for (addr, &value) in range.zip(data) {
+            let address = Address::new(addr as u16);
+            match self.peripherals.iter_mut().find(|p| p.claims(address)) {
+                Some(peripheral) => peripheral.write(address, value),
+                None => self.memory[addr] = value,
+            }
+        }
     }
 }
 
@@ -143,11 +331,21 @@ impl<P: Platform> Interpreter<P> {
         self.pc.0 = nnn.as_usize();
     }
 
-    // Dxyn
-    fn draw(&mut self, x: RegisterIndex, y: RegisterIndex, n: Nibble) {
+    // Dxyn, or Dxy0 for a 16x16 sprite in hi-res mode
+    fn draw(&mut self, x: RegisterIndex, y: RegisterIndex, n: Nibble) -> Result<()> {
+        let wide = n.as_usize() == 0 && self.high_resolution;
+        let len = if wide { 32 } else { n.as_usize() };
+        let mut data = [0; 32];
+        let data = &mut data[..len];
+        self.load(self.memory_range(self.index_register, len)?, data);
+        let sprite = if wide {
+            Sprite::new_wide(data)
+        } else {
+            Sprite::new(data)
+        };
         self.registers[0x0f] = if self.platform.draw_sprite(
             Point(self.registers[x.as_usize()], self.registers[y.as_usize()]),
-            Sprite::new(&self.memory[self.index_register..self.index_register + n.as_usize()]),
+            sprite,
         ) {
             1
         } else {
@@ -155,6 +353,7 @@ impl<P: Platform> Interpreter<P> {
         };
 
         self.pc.next();
+        Ok(())
     }
 
     // 7xnn
@@ -201,8 +400,8 @@ impl<P: Platform> Interpreter<P> {
 
     // 2nnn
     fn call(&mut self, nnn: Address) -> Result<()> {
-        let (res, overflow) = self.sp.overflowing_add(1);
-        if overflow {
+        let res = self.sp + 1;
+        if res > STACK_SIZE {
             Err(Error::StackOverflow)
         } else {
             self.call_stack[self.sp] = self.pc.0 + 2;
@@ -233,21 +432,27 @@ impl<P: Platform> Interpreter<P> {
     // 8xy1
     fn or(&mut self, x: RegisterIndex, y: RegisterIndex) {
         self.registers[x.as_usize()] |= self.registers[y.as_usize()];
-        self.registers[0x0f] = 0;
+        if self.quirks.vf_reset {
+            self.registers[0x0f] = 0;
+        }
         self.pc.next();
     }
 
     // 8xy2
     fn and(&mut self, x: RegisterIndex, y: RegisterIndex) {
         self.registers[x.as_usize()] &= self.registers[y.as_usize()];
-        self.registers[0x0f] = 0;
+        if self.quirks.vf_reset {
+            self.registers[0x0f] = 0;
+        }
         self.pc.next();
     }
 
     // 8xy3
     fn xor(&mut self, x: RegisterIndex, y: RegisterIndex) {
         self.registers[x.as_usize()] ^= self.registers[y.as_usize()];
-        self.registers[0x0f] = 0;
+        if self.quirks.vf_reset {
+            self.registers[0x0f] = 0;
+        }
         self.pc.next();
     }
 
@@ -285,50 +490,69 @@ impl<P: Platform> Interpreter<P> {
 
     // 8xy6
     fn shr(&mut self, x: RegisterIndex, y: RegisterIndex) {
-        let vy = self.registers[y.as_usize()];
-        self.registers[x.as_usize()] = self.registers[y.as_usize()] >> 1;
-        self.registers[0x0f] = vy & 0x1;
+        let src = if self.quirks.shift_in_place { x } else { y };
+        let value = self.registers[src.as_usize()];
+        self.registers[x.as_usize()] = value >> 1;
+        self.registers[0x0f] = value & 0x1;
         self.pc.next();
     }
 
     // 8xyE
     fn shl(&mut self, x: RegisterIndex, y: RegisterIndex) {
-        let vy = self.registers[y.as_usize()];
-        self.registers[x.as_usize()] = self.registers[y.as_usize()] << 1;
-        self.registers[0x0f] = vy >> 7;
+        let src = if self.quirks.shift_in_place { x } else { y };
+        let value = self.registers[src.as_usize()];
+        self.registers[x.as_usize()] = value << 1;
+        self.registers[0x0f] = value >> 7;
         self.pc.next();
     }
 
     // Fx65
-    fn read(&mut self, x: Nibble) {
-        for i in 0..x.as_usize() + 1 {
-            self.registers[i] = self.memory[self.index_register + i];
-        }
-        self.index_register += x.as_usize() + 1;
+    fn read(&mut self, x: Nibble) -> Result<()> {
+        let range = self.memory_range(self.index_register, x.as_usize() + 1)?;
+        let mut values = [0; REG_SIZE];
+        self.load(range, &mut values[..=x.as_usize()]);
+        self.registers[..=x.as_usize()].copy_from_slice(&values[..=x.as_usize()]);
+        self.index_register += match self.quirks.load_store_increment {
+            IndexIncrement::PastLast => x.as_usize() + 1,
+            IndexIncrement::ByX => x.as_usize(),
+            IndexIncrement::Unchanged => 0,
+        };
         self.pc.next();
+        Ok(())
     }
 
     // Fx55
-    fn write(&mut self, x: Nibble) {
-        for i in 0..x.as_usize() + 1 {
-            self.memory[self.index_register + i] = self.registers[i];
-        }
-        self.index_register += x.as_usize() + 1;
+    fn write(&mut self, x: Nibble) -> Result<()> {
+        let range = self.memory_range(self.index_register, x.as_usize() + 1)?;
+        let values = self.registers;
+        self.store(range, &values[..=x.as_usize()]);
+        self.index_register += match self.quirks.load_store_increment {
+            IndexIncrement::PastLast => x.as_usize() + 1,
+            IndexIncrement::ByX => x.as_usize(),
+            IndexIncrement::Unchanged => 0,
+        };
         self.pc.next();
+        Ok(())
     }
 
     // Fx33
-    fn dec(&mut self, x: RegisterIndex) {
-        self.memory[self.index_register] = self.registers[x.as_usize()] / 100;
-        self.memory[self.index_register + 1] = (self.registers[x.as_usize()] % 100) / 10;
-        self.memory[self.index_register + 2] = self.registers[x.as_usize()] % 10;
+    fn dec(&mut self, x: RegisterIndex) -> Result<()> {
+        let range = self.memory_range(self.index_register, 3)?;
+        let value = self.registers[x.as_usize()];
+        self.store(range, &[value / 100, (value % 100) / 10, value % 10]);
         self.pc.next();
+        Ok(())
     }
 
     // Fx1E
-    fn incr_i(&mut self, x: RegisterIndex) {
-        self.index_register += self.registers[x.as_usize()] as usize;
+    fn incr_i(&mut self, x: RegisterIndex) -> Result<()> {
+        let index = self.index_register + self.registers[x.as_usize()] as usize;
+        if index >= MEM_SIZE {
+            return Err(Error::MemoryOutOfBounds(index));
+        }
+        self.index_register = index;
         self.pc.next();
+        Ok(())
     }
 
     // Ex9E
@@ -377,30 +601,50 @@ impl<P: Platform> Interpreter<P> {
 
     // Fx18
     fn set_sound_timer(&mut self, x: Nibble) {
-        self.platform.set_sound_timer(x.as_u8());
+        self.platform.set_sound_timer(self.registers[x.as_usize()]);
         self.pc.next();
     }
 
     // Fx0A
-    fn wait_for_key(&mut self, x: Nibble) {
-        let mut fl = false;
-        for i in 0..16 {
-            if self.platform.is_key_down(Nibble::try_from(i).unwrap()) {
-                self.memory[x.as_usize()] = i;
-                fl = true;
-            }
-        }
+    fn wait_for_key(&mut self, x: RegisterIndex) {
+        // Only keys pressed from now on count.
+        self.platform.consume_key_press();
+        self.state = ExecutionState::WaitingForKey {
+            register: x,
+            pressed: None,
+        };
+    }
 
-        if !fl {
-            return;
-        }
+    fn poll_key(&mut self, x: RegisterIndex, pressed: Option<Key>) {
+        let key = match pressed {
+            Some(key) if self.platform.is_key_down(key) => return,
+            Some(key) => key,
+            None => match self.platform.consume_key_press() {
+                Some(key) if self.quirks.key_wait_event == KeyEventKind::Pressed => key,
+                Some(key) => {
+                    self.state = ExecutionState::WaitingForKey {
+                        register: x,
+                        pressed: Some(key),
+                    };
+                    return;
+                }
+                None => return,
+            },
+        };
 
+        self.registers[x.as_usize()] = key.as_u8();
+        self.state = ExecutionState::Running;
         self.pc.next();
     }
 
     // Bnnn
     fn jmp_v0(&mut self, nnn: Address) {
-        self.pc.0 = (nnn + self.registers[0] as i16).as_usize();
+        let x = if self.quirks.jump_with_vx {
+            Nibble::from(nnn.as_usize() as u16 >> 8)
+        } else {
+            Nibble::default()
+        };
+        self.pc.0 = (nnn + self.registers[x.as_usize()] as i16).as_usize();
     }
 
     // Cxnn
@@ -411,7 +655,50 @@ impl<P: Platform> Interpreter<P> {
 
     // Fx29
     fn set_sprite(&mut self, x: Nibble) {
-        self.index_register = (self.registers[x.as_usize()] as usize) * 5;
+        let digit = (self.registers[x.as_usize()] & 0x0f) as usize;
+        self.index_register = self.font_address.as_usize() + digit * FONT_SPRITE_SIZE;
+        self.pc.next();
+    }
+
+    // 00Cn
+    fn scroll_down(&mut self, n: Nibble) {
+        self.platform.scroll_down(n.as_u8());
+        self.pc.next();
+    }
+
+    // 00FB
+    fn scroll_right(&mut self) {
+        self.platform.scroll_right(4);
+        self.pc.next();
+    }
+
+    // 00FC
+    fn scroll_left(&mut self) {
+        self.platform.scroll_left(4);
+        self.pc.next();
+    }
+
+    // 00FD
+    fn exit(&mut self) {
+        self.state = ExecutionState::Exited;
+    }
+
+    // 00FE, 00FF
+    fn set_resolution(&mut self, high: bool) {
+        self.high_resolution = high;
+        self.platform.set_high_resolution(high);
+        self.pc.next();
+    }
+
+    // Fx75
+    fn save_flags(&mut self, x: Nibble) {
+        self.flags[..=x.as_usize()].copy_from_slice(&self.registers[..=x.as_usize()]);
+        self.pc.next();
+    }
+
+    // Fx85
+    fn load_flags(&mut self, x: Nibble) {
+        self.registers[..=x.as_usize()].copy_from_slice(&self.flags[..=x.as_usize()]);
         self.pc.next();
     }
 }
@@ -454,6 +741,14 @@ pub enum Operation {
     ToDecimal(RegisterIndex),
     WriteMemory(Nibble),
     ReadMemory(Nibble),
+    ScrollDown(Nibble),
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowResolution,
+    HighResolution,
+    SaveFlags(Nibble),
+    LoadFlags(Nibble),
 }
 
 impl TryFrom<OpCode> for Operation {
@@ -537,6 +832,15 @@ impl TryFrom<OpCode> for Operation {
             // other
             [0x0c, x, ..] => Self::SetToRandom(Nibble::try_from(*x)?, nn),
             [0x0f, x, 0x02, 0x09] => Self::SetIndexRegisterToSprite(Nibble::try_from(*x)?),
+            // SCHIP
+            [0x00, 0x00, 0x0c, n] => Self::ScrollDown(Nibble::try_from(*n)?),
+            [0x00, 0x00, 0x0f, 0x0b] => Self::ScrollRight,
+            [0x00, 0x00, 0x0f, 0x0c] => Self::ScrollLeft,
+            [0x00, 0x00, 0x0f, 0x0d] => Self::Exit,
+            [0x00, 0x00, 0x0f, 0x0e] => Self::LowResolution,
+            [0x00, 0x00, 0x0f, 0x0f] => Self::HighResolution,
+            [0x0f, x, 0x07, 0x05] => Self::SaveFlags(Nibble::try_from(*x)?),
+            [0x0f, x, 0x08, 0x05] => Self::LoadFlags(Nibble::try_from(*x)?),
             _ => return Err(Error::UnknownOpCode(code)),
         };
         Ok(op)
@@ -0,0 +1,147 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{Error, Key, Nibble, Result};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Translates a frontend's host key codes to CHIP-8 keys.
+///
+/// Serializes as whitespace-separated `HOST=KEY` pairs with `KEY` a hex digit,
+/// e.g. `x=0 1=1 2=2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyMap<K> {
+    bindings: Vec<(K, Key)>,
+}
+
+impl<K: PartialEq> KeyMap<K> {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds `host` to `key`, returning the key it was bound to before.
+    /// Several host keys may share a CHIP-8 key.
+    pub fn bind(&mut self, host: K, key: Key) -> Option<Key> {
+        match self.bindings.iter_mut().find(|(k, _)| *k == host) {
+            Some((_, bound)) => Some(std::mem::replace(bound, key)),
+            None => {
+                self.bindings.push((host, key));
+                None
+            }
+        }
+    }
+
+    pub fn unbind(&mut self, host: &K) -> Option<Key> {
+        let pos = self.bindings.iter().position(|(k, _)| k == host)?;
+        Some(self.bindings.remove(pos).1)
+    }
+
+    pub fn get(&self, host: &K) -> Option<Key> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| k == host)
+            .map(|(_, key)| *key)
+    }
+
+    /// Bindings in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Key)> {
+        self.bindings.iter().map(|(host, key)| (host, *key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Converts the host keys with `f`, dropping those it maps to `None`.
+    pub fn map_hosts<L: PartialEq>(&self, mut f: impl FnMut(&K) -> Option<L>) -> KeyMap<L> {
+        let mut map = KeyMap::new();
+        for (host, key) in &self.bindings {
+            if let Some(host) = f(host) {
+                map.bind(host, *key);
+            }
+        }
+        map
+    }
+}
+
+impl KeyMap<char> {
+    /// The usual layout putting the keypad on the left of a QWERTY keyboard:
+    ///
+    /// ```text
+    /// 1 2 3 C      1 2 3 4
+    /// 4 5 6 D  ->  Q W E R
+    /// 7 8 9 E      A S D F
+    /// A 0 B F      Z X C V
+    /// ```
+    pub const QWERTY_LAYOUT: &'static str = "x123qweasdzc4rfv";
+
+    /// Maps the `i`-th character of `layout` to key `i`.
+    pub fn from_layout(layout: &str) -> Result<Self> {
+        let chars = layout.chars().collect::<Vec<_>>();
+        if chars.len() != Nibble::DOMAIN_SIZE {
+            return Err(Error::InvalidKeyMap(format!(
+                "layout must have {} keys, got {}",
+                Nibble::DOMAIN_SIZE,
+                chars.len()
+            )));
+        }
+
+        let mut map = Self::new();
+        for (value, host) in chars.into_iter().enumerate() {
+            if map.bind(host, Key::from(value as u16)).is_some() {
+                return Err(Error::InvalidKeyMap(format!(
+                    "{host:?} is bound more than once"
+                )));
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn qwerty() -> Self {
+        Self::from_layout(Self::QWERTY_LAYOUT).unwrap()
+    }
+}
+
+impl<K: PartialEq> Default for KeyMap<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Display> Display for KeyMap<K> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, (host, key)) in self.bindings.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{host}={:X}", key.as_u8())?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: FromStr + PartialEq> FromStr for KeyMap<K> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut map = Self::new();
+        for pair in s.split_whitespace() {
+            let invalid = || Error::InvalidKeyMap(format!("invalid binding: {pair:?}"));
+            let (host, key) = pair.rsplit_once('=').ok_or_else(invalid)?;
+            let key = u8::from_str_radix(key, 16)
+                .ok()
+                .and_then(|key| Key::try_from(key).ok())
+                .ok_or_else(invalid)?;
+            map.bind(host.parse().map_err(|_| invalid())?, key);
+        }
+        Ok(map)
+    }
+}
@@ -4,12 +4,20 @@ mod data;
 mod error;
 mod image;
 mod interpreter;
+mod keymap;
 mod managed_interpreter;
+mod peripheral;
 mod platform;
+mod quirks;
+mod replay;
 
 pub use data::*;
 pub use error::*;
 pub use image::*;
 pub use interpreter::*;
+pub use keymap::*;
 pub use managed_interpreter::*;
+pub use peripheral::*;
 pub use platform::*;
+pub use quirks::*;
+pub use replay::*;
@@ -1,36 +1,135 @@
 use crate::{
-    data::Word,
+    data::{Address, RegisterIndex, Word},
     error::Result,
     image::Image,
-    interpreter::{Interpreter, SCREEN_HEIGHT, SCREEN_WIDTH},
+    interpreter::{
+        ExecutionState, Interpreter, Operation, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, MEM_SIZE,
+        REG_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH,
+    },
+    peripheral::Peripheral,
     platform::{Key, Platform, Point, Sprite},
-    KeyEventKind,
+    replay::{KeyEvent, ReplayLog},
+    KeyEventKind, Quirks,
 };
 
 use core::time::Duration;
-use std::u8;
+use std::collections::{HashSet, VecDeque};
 
 ////////////////////////////////////////////////////////////////////////////////
 
-pub struct FrameBuffer([[bool; SCREEN_WIDTH]; SCREEN_HEIGHT]);
+/// Display contents. Only the top-left `width() x height()` pixels are in use;
+/// the rest is storage for the SCHIP hi-res mode.
+pub struct FrameBuffer {
+    pixels: [[bool; HIRES_SCREEN_WIDTH]; HIRES_SCREEN_HEIGHT],
+    high_resolution: bool,
+    dirty_rows: DirtyRows,
+}
 
 impl Default for FrameBuffer {
     fn default() -> Self {
-        Self([[false; SCREEN_WIDTH]; SCREEN_HEIGHT])
+        Self {
+            pixels: [[false; HIRES_SCREEN_WIDTH]; HIRES_SCREEN_HEIGHT],
+            high_resolution: false,
+            dirty_rows: DirtyRows::ALL,
+        }
     }
 }
 
 impl FrameBuffer {
+    pub fn width(&self) -> usize {
+        if self.high_resolution {
+            HIRES_SCREEN_WIDTH
+        } else {
+            SCREEN_WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.high_resolution {
+            HIRES_SCREEN_HEIGHT
+        } else {
+            SCREEN_HEIGHT
+        }
+    }
+
+    pub fn is_high_resolution(&self) -> bool {
+        self.high_resolution
+    }
+
     pub fn is_in_bounds(&self, x: u8, y: u8) -> bool {
-        (x as usize) < SCREEN_WIDTH && (y as usize) < SCREEN_HEIGHT
+        (x as usize) < self.width() && (y as usize) < self.height()
     }
 
-    pub fn iter_rows(&self) -> impl Iterator<Item = &[bool; SCREEN_WIDTH]> {
-        self.0.iter()
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[bool]> {
+        let width = self.width();
+        self.pixels[..self.height()]
+            .iter()
+            .map(move |r| &r[..width])
     }
 
-    pub fn iter_rows_mut(&mut self) -> impl Iterator<Item = &mut [bool; SCREEN_WIDTH]> {
-        self.0.iter_mut()
+    /// Rows changed since the last `ManagedInterpreter::take_dirty_rows`.
+    pub fn dirty_rows(&self) -> DirtyRows {
+        self.dirty_rows
+    }
+
+    /// Marks every row as dirty.
+    pub fn iter_rows_mut(&mut self) -> impl Iterator<Item = &mut [bool]> {
+        self.dirty_rows = DirtyRows::ALL;
+        let width = self.width();
+        let height = self.height();
+        self.pixels[..height]
+            .iter_mut()
+            .map(move |r| &mut r[..width])
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        let height = self.height();
+        let rows = rows.min(height);
+        self.pixels[..height].rotate_right(rows);
+        self.pixels[..rows].iter_mut().for_each(|r| r.fill(false));
+        self.dirty_rows = DirtyRows::ALL;
+    }
+
+    fn scroll_left(&mut self, columns: usize) {
+        let columns = columns.min(self.width());
+        for row in self.iter_rows_mut() {
+            row.rotate_left(columns);
+            let width = row.len();
+            row[width - columns..].fill(false);
+        }
+    }
+
+    fn scroll_right(&mut self, columns: usize) {
+        let columns = columns.min(self.width());
+        for row in self.iter_rows_mut() {
+            row.rotate_right(columns);
+            row[..columns].fill(false);
+        }
+    }
+}
+
+/// A set of frame buffer rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirtyRows(u64);
+
+impl DirtyRows {
+    const ALL: Self = Self(u64::MAX);
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        row < HIRES_SCREEN_HEIGHT && self.0 & (1 << row) != 0
+    }
+
+    /// Row indices in increasing order, up to `height`.
+    pub fn iter(&self, height: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..height.min(HIRES_SCREEN_HEIGHT)).filter(|&row| self.contains(row))
+    }
+
+    fn insert(&mut self, row: usize) {
+        self.0 |= 1 << row;
     }
 }
 
@@ -53,12 +152,18 @@ struct ManagedPlatform<R: RandomNumberGenerator> {
     sound_timer: Word,
     keypad: [KeyEventKind; KEYPAD_SIZE],
     last_key: Option<Key>,
+    /// Words to return before asking `rand`, see `ManagedInterpreter::replay`.
+    replayed_words: VecDeque<Word>,
+    recorded_words: Option<Vec<Word>>,
 }
 
 impl<R: RandomNumberGenerator> Platform for ManagedPlatform<R> {
     fn draw_sprite(&mut self, pos: Point, sprite: Sprite) -> bool {
         let mut collision = false;
-        let pos = Point(pos.0 % SCREEN_WIDTH as u8, pos.1 % SCREEN_HEIGHT as u8);
+        let pos = Point(
+            pos.0 % self.frame_buffer.width() as u8,
+            pos.1 % self.frame_buffer.height() as u8,
+        );
         for dl in sprite.iter_pixels() {
             let Point(x, y) = pos + dl;
 
@@ -68,8 +173,9 @@ impl<R: RandomNumberGenerator> Platform for ManagedPlatform<R> {
 
             let x = x as usize;
             let y = y as usize;
-            collision |= self.frame_buffer.0[y][x];
-            self.frame_buffer.0[y][x] ^= true;
+            collision |= self.frame_buffer.pixels[y][x];
+            self.frame_buffer.pixels[y][x] ^= true;
+            self.frame_buffer.dirty_rows.insert(y);
         }
 
         collision
@@ -81,6 +187,23 @@ impl<R: RandomNumberGenerator> Platform for ManagedPlatform<R> {
             .for_each(|r| r.fill(false));
     }
 
+    fn set_high_resolution(&mut self, enabled: bool) {
+        self.frame_buffer.high_resolution = enabled;
+        self.clear_screen();
+    }
+
+    fn scroll_down(&mut self, rows: u8) {
+        self.frame_buffer.scroll_down(rows as usize);
+    }
+
+    fn scroll_left(&mut self, columns: u8) {
+        self.frame_buffer.scroll_left(columns as usize);
+    }
+
+    fn scroll_right(&mut self, columns: u8) {
+        self.frame_buffer.scroll_right(columns as usize);
+    }
+
     fn get_delay_timer(&self) -> Word {
         self.delay_timer
     }
@@ -102,7 +225,14 @@ impl<R: RandomNumberGenerator> Platform for ManagedPlatform<R> {
     }
 
     fn get_random_word(&mut self) -> Word {
-        (self.rand)()
+        let word = match self.replayed_words.pop_front() {
+            Some(word) => word,
+            None => (self.rand)(),
+        };
+        if let Some(words) = &mut self.recorded_words {
+            words.push(word);
+        }
+        word
     }
 }
 
@@ -115,17 +245,56 @@ impl<R: RandomNumberGenerator> ManagedPlatform<R> {
             last_key: None,
             delay_timer: 0,
             sound_timer: 0,
+            replayed_words: VecDeque::new(),
+            recorded_words: None,
         }
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Relative cost of an operation, in multiples of the base operation duration.
+pub type CycleWeights = Box<dyn Fn(&Operation) -> u32>;
+
+/// Called at every 60Hz timer tick, see `ManagedInterpreter::set_on_frame`.
+pub type FrameCallback = Box<dyn FnMut(&Frame)>;
+
+/// Machine state at the end of an emulated frame.
+pub struct Frame<'a> {
+    /// Frames since the interpreter was created, starting at 1.
+    pub number: u64,
+    pub frame_buffer: &'a FrameBuffer,
+    /// Sound timer value after this tick's decrement.
+    pub sound_timer: Word,
+}
+
+impl Frame<'_> {
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound_timer > 0
+    }
+}
+
 pub struct ManagedInterpreter<R: RandomNumberGenerator> {
     inner: Interpreter<ManagedPlatform<R>>,
+    operation_period: Duration,
+    delay_tick_period: Duration,
+    sound_tick_period: Duration,
+    // Time left until the next operation or timer tick.
     operation_duration: Duration,
     delay_tick_duration: Duration,
     sound_tick_duration: Duration,
+    cycle_weights: Option<CycleWeights>,
+    on_frame: Option<FrameCallback>,
+    frames: u64,
+    /// Emulated time simulated so far.
+    elapsed: Duration,
+    recording: Option<Recording>,
+    executed_instructions: u64,
+    executed_cycles: u64,
+    breakpoints: HashSet<Address>,
+    paused: bool,
+    /// Lets execution leave the breakpoint it was paused at.
+    skip_breakpoint: bool,
 }
 
 impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
@@ -151,10 +320,23 @@ impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
         sound_tick_duration: Duration,
     ) -> Self {
         Self {
-            inner: Interpreter::new(image, ManagedPlatform::new(rand)),
+            inner: Interpreter::new(image, ManagedPlatform::new(rand), Quirks::default()),
+            operation_period: operation_duration,
+            delay_tick_period: delay_tick_duration,
+            sound_tick_period: sound_tick_duration,
             operation_duration,
             delay_tick_duration,
             sound_tick_duration,
+            cycle_weights: None,
+            on_frame: None,
+            frames: 0,
+            elapsed: Duration::ZERO,
+            recording: None,
+            executed_instructions: 0,
+            executed_cycles: 0,
+            breakpoints: HashSet::new(),
+            paused: false,
+            skip_breakpoint: false,
         }
     }
 
@@ -170,12 +352,93 @@ impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
         }
     }
 
+    fn end_frame(&mut self) {
+        self.frames += 1;
+        if let Some(on_frame) = &mut self.on_frame {
+            let platform = self.inner.platform();
+            on_frame(&Frame {
+                number: self.frames,
+                frame_buffer: &platform.frame_buffer,
+                sound_timer: platform.sound_timer,
+            });
+        }
+    }
+
+    /// Runs the next instruction and returns its cycle weight. Polls for a
+    /// key while waiting on `Fx0A` aren't counted as executed instructions.
+    fn run_counted(&mut self) -> Result<u32> {
+        if self.inner.has_exited() {
+            return Ok(1);
+        }
+        let weight = match (&self.cycle_weights, self.inner.next_operation()) {
+            (Some(weights), Ok(operation)) => weights(&operation).max(1),
+            _ => 1,
+        };
+        let polling = matches!(
+            self.inner.execution_state(),
+            ExecutionState::WaitingForKey { .. }
+        );
+        self.inner.run_next_instruction()?;
+        if !polling {
+            self.executed_instructions += 1;
+            self.executed_cycles += weight as u64;
+        }
+        Ok(weight)
+    }
+
     pub fn simulate_one_instruction(&mut self) -> Result<()> {
-        self.inner.run_next_instruction()
+        self.run_counted().map(|_| ())
+    }
+
+    pub fn set_instructions_per_second(&mut self, instructions_per_second: u32) {
+        self.operation_period = Duration::from_secs(1) / instructions_per_second.max(1);
+        self.operation_duration = self.operation_duration.min(self.operation_period);
+    }
+
+    pub fn instructions_per_second(&self) -> f64 {
+        1.0 / self.operation_period.as_secs_f64()
+    }
+
+    /// Makes each operation take `weights(op)` base durations instead of one.
+    pub fn set_cycle_weights(&mut self, weights: impl Fn(&Operation) -> u32 + 'static) {
+        self.cycle_weights = Some(Box::new(weights));
+    }
+
+    /// Calls `on_frame` at every delay timer tick inside `simulate_duration`,
+    /// after the timers are decremented and before the instructions of the
+    /// next frame run. Lets frontends present frames in step with the
+    /// emulated vblank.
+    pub fn set_on_frame(&mut self, on_frame: impl FnMut(&Frame) + 'static) {
+        self.on_frame = Some(Box::new(on_frame));
+    }
+
+    pub fn clear_on_frame(&mut self) {
+        self.on_frame = None;
+    }
+
+    /// Number of 60Hz timer ticks so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
     }
 
+    pub fn executed_instructions(&self) -> u64 {
+        self.executed_instructions
+    }
+
+    /// Sum of the cycle weights of all executed instructions.
+    pub fn executed_cycles(&self) -> u64 {
+        self.executed_cycles
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.executed_instructions = 0;
+        self.executed_cycles = 0;
+    }
+
+    /// Advances time, running instructions as they come due. Does nothing
+    /// while paused; hitting a breakpoint pauses before the instruction at it.
     pub fn simulate_duration(&mut self, mut duration: Duration) -> Result<()> {
-        loop {
+        while !self.paused {
             let min_dur = self
                 .delay_tick_duration
                 .min(self.sound_tick_duration.min(self.operation_duration));
@@ -184,22 +447,31 @@ impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
                 self.delay_tick_duration -= duration;
                 self.sound_tick_duration -= duration;
                 self.operation_duration -= duration;
+                self.elapsed += duration;
                 break;
             }
+            self.elapsed += min_dur;
 
             if min_dur == self.delay_tick_duration {
                 self.decrement_delay_timer();
-                self.delay_tick_duration = Self::DEFAULT_DELAY_TICK_DURATION;
+                self.delay_tick_duration = self.delay_tick_period;
                 self.decrement_sound_timer();
-                self.sound_tick_duration = Self::DEFAULT_SOUND_TICK_DURATION;
+                self.sound_tick_duration = self.sound_tick_period;
+                self.end_frame();
             } else {
                 self.delay_tick_duration -= min_dur;
                 self.sound_tick_duration -= min_dur;
             }
 
             if min_dur == self.operation_duration {
-                self.simulate_one_instruction()?;
-                self.operation_duration = Self::DEFAULT_OPERATION_DURATION;
+                if self.is_at_breakpoint() {
+                    self.operation_duration = Duration::ZERO;
+                    self.paused = true;
+                    break;
+                }
+                let weight = self.run_counted()?;
+                self.skip_breakpoint = false;
+                self.operation_duration = self.operation_period * weight;
             } else {
                 self.operation_duration -= min_dur;
             }
@@ -209,11 +481,51 @@ impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
         Ok(())
     }
 
+    /// Waiting for a key keeps the program counter on the `Fx0A`, whose
+    /// breakpoint only applies before it first runs.
+    fn is_at_breakpoint(&self) -> bool {
+        !self.skip_breakpoint
+            && !matches!(
+                self.inner.execution_state(),
+                ExecutionState::WaitingForKey { .. }
+            )
+            && self.breakpoints.contains(&self.inner.program_counter())
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.inner.has_exited()
+    }
+
+    pub fn execution_state(&self) -> ExecutionState {
+        self.inner.execution_state()
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.inner.set_quirks(quirks);
+    }
+
+    pub fn add_peripheral(&mut self, peripheral: impl Peripheral + 'static) {
+        self.inner.add_peripheral(peripheral);
+    }
+
     pub fn frame_buffer(&self) -> &FrameBuffer {
         &self.inner.platform().frame_buffer
     }
 
+    /// Returns the rows changed since the previous call, for redrawing only
+    /// those. Everything is dirty initially and after a resolution change.
+    pub fn take_dirty_rows(&mut self) -> DirtyRows {
+        std::mem::take(&mut self.inner.platform_mut().frame_buffer.dirty_rows)
+    }
+
     pub fn set_key_down(&mut self, key: Key, is_down: bool) {
+        if let Some(recording) = &mut self.recording {
+            recording.key_events.push(KeyEvent {
+                time: self.elapsed - recording.start,
+                key,
+                is_down,
+            });
+        }
         if is_down {
             let platform = self.inner.platform_mut();
             platform.keypad[key.as_usize()] = KeyEventKind::Pressed;
@@ -223,3 +535,127 @@ impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
+    pub fn add_breakpoint(&mut self, addr: Address) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: Address) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = Address> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.skip_breakpoint = true;
+    }
+
+    /// Runs exactly one instruction, ignoring breakpoints, and stays paused.
+    pub fn step(&mut self) -> Result<()> {
+        self.paused = true;
+        self.simulate_one_instruction()
+    }
+
+    pub fn program_counter(&self) -> Address {
+        self.inner.program_counter()
+    }
+
+    pub fn set_program_counter(&mut self, addr: Address) {
+        self.inner.set_program_counter(addr);
+    }
+
+    pub fn registers(&self) -> &[Word; REG_SIZE] {
+        self.inner.registers()
+    }
+
+    pub fn set_register(&mut self, index: RegisterIndex, value: Word) {
+        self.inner.registers_mut()[index.as_usize()] = value;
+    }
+
+    pub fn index_register(&self) -> usize {
+        self.inner.index_register()
+    }
+
+    pub fn set_index_register(&mut self, addr: Address) {
+        self.inner.set_index_register(addr);
+    }
+
+    pub fn memory(&self) -> &[Word; MEM_SIZE] {
+        self.inner.memory()
+    }
+
+    /// Copies `data` into memory starting at `addr`, truncating at the end of
+    /// memory.
+    pub fn write_memory(&mut self, addr: Address, data: &[Word]) {
+        let memory = &mut self.inner.memory_mut()[addr.as_usize()..];
+        let len = data.len().min(memory.len());
+        memory[..len].copy_from_slice(&data[..len]);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+struct Recording {
+    start: Duration,
+    key_events: Vec<KeyEvent>,
+}
+
+impl<R: RandomNumberGenerator> ManagedInterpreter<R> {
+    /// Starts recording the key events and random numbers, dropping what was
+    /// recorded before. Replaying reproduces the run if recording starts
+    /// before the first instruction and the run only advances by
+    /// `simulate_duration`.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording {
+            start: self.elapsed,
+            key_events: Vec::new(),
+        });
+        self.inner.platform_mut().recorded_words = Some(Vec::new());
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Returns what was recorded since `start_recording`, `None` if not
+    /// recording.
+    pub fn stop_recording(&mut self) -> Option<ReplayLog> {
+        let recording = self.recording.take()?;
+        Some(ReplayLog {
+            key_events: recording.key_events,
+            random_words: self.inner.platform_mut().recorded_words.take()?,
+            duration: self.elapsed - recording.start,
+        })
+    }
+
+    /// Runs for `log.duration`, pressing and releasing keys at the recorded
+    /// times and taking random numbers from the log while they last. A fresh
+    /// interpreter with the recorded image and settings repeats the run.
+    pub fn replay(&mut self, log: &ReplayLog) -> Result<()> {
+        self.inner
+            .platform_mut()
+            .replayed_words
+            .extend(&log.random_words);
+        let mut now = Duration::ZERO;
+        for event in &log.key_events {
+            self.simulate_duration(event.time.saturating_sub(now))?;
+            now = now.max(event.time);
+            self.set_key_down(event.key, event.is_down);
+        }
+        self.simulate_duration(log.duration.saturating_sub(now))
+    }
+}
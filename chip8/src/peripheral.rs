@@ -0,0 +1,31 @@
+use crate::{
+    data::{Address, OpCode, Word},
+    interpreter::REG_SIZE,
+};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A hardware extension attached to the interpreter.
+///
+/// Peripherals are consulted in registration order. The first one to claim
+/// an address or to handle an opcode wins.
+pub trait Peripheral {
+    /// Whether data accesses to `addr` (`Dxyn`, `Fx33`, `Fx55`, `Fx65`) go
+    /// to this peripheral instead of RAM. Instruction fetches always read RAM.
+    fn claims(&self, _addr: Address) -> bool {
+        false
+    }
+
+    fn read(&mut self, _addr: Address) -> Word {
+        0
+    }
+
+    fn write(&mut self, _addr: Address, _value: Word) {}
+
+    /// Called with every opcode before it is decoded. Returns `true` if the
+    /// peripheral has executed it, in which case the interpreter only
+    /// advances the program counter.
+    fn execute(&mut self, _opcode: OpCode, _registers: &mut [Word; REG_SIZE]) -> bool {
+        false
+    }
+}
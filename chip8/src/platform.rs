@@ -20,25 +20,43 @@ impl Add<Self> for Point {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Sprite<'a> {
     data: &'a [u8],
+    bytes_per_row: usize,
 }
 
 impl<'a> Sprite<'a> {
+    /// A sprite 8 pixels wide, one byte per row.
     pub fn new(data: &'a [u8]) -> Self {
-        Self { data }
+        Self {
+            data,
+            bytes_per_row: 1,
+        }
+    }
+
+    /// A SCHIP sprite 16 pixels wide, two bytes per row.
+    pub fn new_wide(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            bytes_per_row: 2,
+        }
     }
 
     pub fn iter_pixels(&self) -> impl Iterator<Item = Point> + '_ {
-        self.data.iter().enumerate().flat_map(|(y, row)| {
-            let y = y as u8;
-            (0..8).filter_map(move |x| {
-                let val = row & (0x80 >> x);
-                if val > 0 {
-                    Some(Point(x, y))
-                } else {
-                    None
-                }
+        self.data
+            .chunks(self.bytes_per_row)
+            .enumerate()
+            .flat_map(|(y, row)| {
+                let y = y as u8;
+                row.iter().enumerate().flat_map(move |(i, byte)| {
+                    (0..8).filter_map(move |x| {
+                        let val = byte & (0x80 >> x);
+                        if val > 0 {
+                            Some(Point(8 * i as u8 + x, y))
+                        } else {
+                            None
+                        }
+                    })
+                })
             })
-        })
     }
 }
 
@@ -58,6 +76,11 @@ pub enum KeyEventKind {
 pub trait Platform {
     fn draw_sprite(&mut self, pos: Point, sprite: Sprite) -> bool;
     fn clear_screen(&mut self);
+    /// Switches between the 64x32 and the SCHIP 128x64 display modes.
+    fn set_high_resolution(&mut self, enabled: bool);
+    fn scroll_down(&mut self, rows: u8);
+    fn scroll_left(&mut self, columns: u8);
+    fn scroll_right(&mut self, columns: u8);
     fn get_delay_timer(&self) -> Word;
     fn set_delay_timer(&mut self, value: Word);
     fn set_sound_timer(&mut self, value: Word);
@@ -0,0 +1,59 @@
+use crate::KeyEventKind;
+
+/// Behaviors on which CHIP-8 implementations historically disagree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift `vx` in place instead of shifting `vy` into `vx`.
+    pub shift_in_place: bool,
+    /// Where `Fx55`/`Fx65` leave `I`.
+    pub load_store_increment: IndexIncrement,
+    /// `8xy1`, `8xy2` and `8xy3` reset `vf` to zero.
+    pub vf_reset: bool,
+    /// `Bxnn` jumps to `xnn + vx` instead of `nnn + v0`.
+    pub jump_with_vx: bool,
+    /// Whether `Fx0A` completes when a key goes down or only once it is
+    /// released again.
+    pub key_wait_event: KeyEventKind,
+}
+
+/// How far `Fx55`/`Fx65` advance `I` after accessing `v0..=vx`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexIncrement {
+    /// By `x + 1`, past the last accessed register.
+    PastLast,
+    /// By `x`, onto the last accessed register.
+    ByX,
+    Unchanged,
+}
+
+impl Quirks {
+    pub const COSMAC_VIP: Self = Self {
+        shift_in_place: false,
+        load_store_increment: IndexIncrement::PastLast,
+        vf_reset: true,
+        jump_with_vx: false,
+        key_wait_event: KeyEventKind::Released,
+    };
+
+    pub const CHIP48: Self = Self {
+        shift_in_place: true,
+        load_store_increment: IndexIncrement::ByX,
+        vf_reset: false,
+        jump_with_vx: true,
+        key_wait_event: KeyEventKind::Pressed,
+    };
+
+    pub const SCHIP: Self = Self {
+        shift_in_place: true,
+        load_store_increment: IndexIncrement::Unchanged,
+        vf_reset: false,
+        jump_with_vx: true,
+        key_wait_event: KeyEventKind::Pressed,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::COSMAC_VIP
+    }
+}
@@ -0,0 +1,25 @@
+use core::time::Duration;
+
+use crate::{data::Word, platform::Key};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A key press or release, at a point of emulated time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Emulated time since the recording started.
+    pub time: Duration,
+    pub key: Key,
+    pub is_down: bool,
+}
+
+/// The external inputs of a run, see `ManagedInterpreter::start_recording`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayLog {
+    /// In chronological order.
+    pub key_events: Vec<KeyEvent>,
+    /// Every word the random number generator returned, in order.
+    pub random_words: Vec<Word>,
+    /// Emulated time the recording lasted.
+    pub duration: Duration,
+}
@@ -1,6 +1,11 @@
-use std::time::Duration;
+#![allow(clippy::unnecessary_fallible_conversions)]
 
-use chip8::{Ch8Image, FrameBuffer, ManagedInterpreter, Nibble};
+use std::{fs, time::Duration};
+
+use chip8::{
+    Ch8Image, FrameBuffer, Interpreter, Key, KeyMap, ManagedInterpreter, Nibble, Platform, Point,
+    Quirks, Sprite, Word,
+};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -27,6 +32,46 @@ fn check_display(fb: &FrameBuffer, expected_raw: &str) {
     }
 }
 
+#[derive(Default)]
+struct NullPlatform;
+
+impl Platform for NullPlatform {
+    fn draw_sprite(&mut self, _pos: Point, _sprite: Sprite) -> bool {
+        false
+    }
+    fn clear_screen(&mut self) {}
+    fn set_high_resolution(&mut self, _enabled: bool) {}
+    fn scroll_down(&mut self, _rows: u8) {}
+    fn scroll_left(&mut self, _columns: u8) {}
+    fn scroll_right(&mut self, _columns: u8) {}
+    fn get_delay_timer(&self) -> Word {
+        0
+    }
+    fn set_delay_timer(&mut self, _value: Word) {}
+    fn set_sound_timer(&mut self, _value: Word) {}
+    fn is_key_down(&self, _key: Key) -> bool {
+        false
+    }
+    fn consume_key_press(&mut self) -> Option<Key> {
+        None
+    }
+    fn get_random_word(&mut self) -> Word {
+        0
+    }
+}
+
+fn run_program(
+    program: &[u8],
+    quirks: Quirks,
+    instruction_count: usize,
+) -> Interpreter<NullPlatform> {
+    let mut inter = Interpreter::new(Ch8Image::new(program).unwrap(), NullPlatform, quirks);
+    for _ in 0..instruction_count {
+        inter.run_next_instruction().unwrap();
+    }
+    inter
+}
+
 fn test_by_instruction_count(image: &[u8], instruction_count: usize, expected_display: &str) {
     let mut inter = ManagedInterpreter::new(Ch8Image::new(image).unwrap(), rand::random);
     for _ in 0..instruction_count {
@@ -311,3 +356,574 @@ fn test_keypad() {
         ",
     );
 }
+
+#[test]
+fn test_quirks_profiles() {
+    assert_eq!(Quirks::default(), Quirks::COSMAC_VIP);
+
+    // v0 = 5, v1 = 3, v0 >>= 1.
+    let shift = [0x60, 0x05, 0x61, 0x03, 0x80, 0x16];
+    assert_eq!(run_program(&shift, Quirks::COSMAC_VIP, 3).registers()[0], 1);
+    assert_eq!(run_program(&shift, Quirks::CHIP48, 3).registers()[0], 2);
+
+    // vf = 7, v0 |= v1.
+    let logic = [0x6f, 0x07, 0x80, 0x11];
+    assert_eq!(
+        run_program(&logic, Quirks::COSMAC_VIP, 2).registers()[0xf],
+        0
+    );
+    assert_eq!(run_program(&logic, Quirks::SCHIP, 2).registers()[0xf], 7);
+
+    // I = 0x300, store v0..v1.
+    let store = [0xa3, 0x00, 0xf1, 0x55];
+    assert_eq!(
+        run_program(&store, Quirks::COSMAC_VIP, 2).index_register(),
+        0x302
+    );
+    assert_eq!(
+        run_program(&store, Quirks::CHIP48, 2).index_register(),
+        0x301
+    );
+    assert_eq!(
+        run_program(&store, Quirks::SCHIP, 2).index_register(),
+        0x300
+    );
+
+    // v0 = 0x10, v2 = 0x20, jump to 0x210 plus offset; v3 tells where we landed.
+    let mut jump = vec![0; 0x34];
+    jump[..6].copy_from_slice(&[0x60, 0x10, 0x62, 0x20, 0xb2, 0x10]);
+    jump[0x20..0x22].copy_from_slice(&[0x63, 0x01]);
+    jump[0x30..0x32].copy_from_slice(&[0x63, 0x02]);
+    assert_eq!(run_program(&jump, Quirks::COSMAC_VIP, 4).registers()[3], 1);
+    assert_eq!(run_program(&jump, Quirks::CHIP48, 4).registers()[3], 2);
+
+    let mut inter = run_program(&shift, Quirks::COSMAC_VIP, 0);
+    inter.set_quirks(Quirks::CHIP48);
+    assert_eq!(inter.quirks(), Quirks::CHIP48);
+}
+
+#[test]
+fn test_schip() {
+    let mut program = vec![0; 0x40];
+    program[..0x12].copy_from_slice(&[
+        0x00, 0xff, // hi-res
+        0xa2, 0x20, // I = sprite
+        0x60, 0x00, 0x61, 0x00, // v0 = v1 = 0
+        0xd0, 0x10, // 16x16 sprite at (0, 0)
+        0x00, 0xc2, // scroll down by 2
+        0x00, 0xfb, // scroll right by 4
+        0x00, 0xfd, // exit
+        0x00, 0xe0, // never reached
+    ]);
+    program[0x20..0x22].copy_from_slice(&[0xff, 0xff]);
+    program[0x3e..0x40].copy_from_slice(&[0x80, 0x01]);
+
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    for _ in 0..20 {
+        inter.simulate_one_instruction().unwrap();
+    }
+    assert!(inter.has_exited());
+
+    let fb = inter.frame_buffer();
+    assert!(fb.is_high_resolution());
+    assert_eq!((fb.width(), fb.height()), (128, 64));
+    let lit = fb
+        .iter_rows()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, lit)| **lit)
+                .map(move |(x, _)| (x, y))
+        })
+        .collect::<Vec<_>>();
+    let mut expected = (4..20).map(|x| (x, 2)).collect::<Vec<_>>();
+    expected.extend([(4, 17), (19, 17)]);
+    assert_eq!(lit, expected);
+
+    // v0 = 5, save v0, v0 = 0, restore v0.
+    let flags = [0x60, 0x05, 0xf0, 0x75, 0x60, 0x00, 0xf0, 0x85];
+    assert_eq!(run_program(&flags, Quirks::SCHIP, 4).registers()[0], 5);
+}
+
+#[test]
+fn test_font() {
+    // v0 = 0xA, I = sprite for v0.
+    let program = [0x60, 0x0a, 0xf0, 0x29];
+    let inter = run_program(&program, Quirks::default(), 2);
+    assert_eq!(inter.index_register(), 0x050 + 0xa * 5);
+    assert_eq!(
+        &inter.memory()[inter.index_register()..inter.index_register() + 5],
+        &[0xF0, 0x90, 0xF0, 0x90, 0x90]
+    );
+    assert_eq!(&inter.memory()[0x050..0x0a0], &chip8::FONT);
+
+    let mut inter = Interpreter::new_with_font_address(
+        Ch8Image::new(program).unwrap(),
+        NullPlatform,
+        Quirks::default(),
+        chip8::Address::new(0x100),
+    );
+    inter.run_next_instruction().unwrap();
+    inter.run_next_instruction().unwrap();
+    assert_eq!(inter.index_register(), 0x100 + 0xa * 5);
+    assert!(inter.memory()[0x050..0x0a0].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_image() {
+    use chip8::{Ch8ImageError, Image};
+
+    let image = Ch8Image::new(vec![0x12, 0x00]).unwrap();
+    assert_eq!(image.size(), 2);
+    assert_eq!(image.entry_point().as_usize(), 0x200);
+
+    let max_size = Ch8Image::<&[u8]>::MAX_SIZE;
+    assert_eq!(max_size, 0x1000 - 0x200);
+    assert!(Ch8Image::new(vec![0; max_size]).is_ok());
+    assert!(matches!(
+        Ch8Image::new(vec![0; max_size + 1]),
+        Err(Ch8ImageError::TooBig { size, .. }) if size == max_size + 1
+    ));
+
+    let dir = std::env::temp_dir().join(format!("chip8-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("rom.ch8");
+    fs::write(&path, [0x60, 0x2a]).unwrap();
+    let image = Ch8Image::from_file(&path).unwrap();
+    assert_eq!(image.size(), 2);
+    let mut inter = Interpreter::new(image, NullPlatform, Quirks::default());
+    inter.run_next_instruction().unwrap();
+    assert_eq!(inter.registers()[0], 0x2a);
+    assert!(matches!(
+        Ch8Image::from_file(dir.join("missing.ch8")),
+        Err(Ch8ImageError::Io(_))
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_debugger() {
+    use chip8::Address;
+
+    // 0x200: v0 += 1, 0x202: jump to 0x200.
+    let program = [0x70, 0x01, 0x12, 0x00];
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    inter.add_breakpoint(Address::new(0x202));
+    assert_eq!(
+        inter.breakpoints().collect::<Vec<_>>(),
+        [Address::new(0x202)]
+    );
+
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert!(inter.is_paused());
+    assert_eq!(inter.program_counter(), Address::new(0x202));
+    assert_eq!(inter.registers()[0], 1);
+
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert_eq!(inter.registers()[0], 1);
+
+    inter.step().unwrap();
+    assert!(inter.is_paused());
+    assert_eq!(inter.program_counter(), Address::new(0x200));
+
+    inter.resume();
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert!(inter.is_paused());
+    assert_eq!(inter.registers()[0], 2);
+
+    inter.set_register(Nibble::try_from(0).unwrap(), 10);
+    inter.write_memory(Address::new(0x200), &[0x70, 0x05]);
+    inter.set_program_counter(Address::new(0x200));
+    inter.remove_breakpoint(Address::new(0x202));
+    inter.resume();
+    inter.simulate_duration(Duration::from_millis(5)).unwrap();
+    assert!(!inter.is_paused());
+    assert_eq!(inter.registers()[0], 20);
+}
+
+#[test]
+fn test_breakpoint_on_key_wait() {
+    use chip8::Address;
+
+    // 0x200: wait for a key into v0, 0x202: jump to 0x202.
+    let program = [0xf0, 0x0a, 0x12, 0x02];
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    inter.add_breakpoint(Address::new(0x200));
+    inter.simulate_duration(Duration::from_millis(100)).unwrap();
+    assert!(inter.is_paused());
+    assert_eq!(inter.program_counter(), Address::new(0x200));
+
+    inter.resume();
+    inter.simulate_duration(Duration::from_millis(100)).unwrap();
+    assert!(!inter.is_paused());
+    assert_eq!(inter.program_counter(), Address::new(0x200));
+
+    inter.set_key_down(Nibble::try_from(7).unwrap(), true);
+    inter.simulate_duration(Duration::from_millis(100)).unwrap();
+    assert_eq!(inter.program_counter(), Address::new(0x200));
+    inter.set_key_down(Nibble::try_from(7).unwrap(), false);
+    inter.simulate_duration(Duration::from_millis(100)).unwrap();
+    assert!(!inter.is_paused());
+    assert_eq!(inter.program_counter(), Address::new(0x202));
+    assert_eq!(inter.registers()[0], 7);
+}
+
+#[test]
+fn test_cpu_speed() {
+    // 0x200: v0 += 1, 0x202: jump to 0x200.
+    let program = [0x70, 0x01, 0x12, 0x00];
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    let close = |actual: u64, expected: u64| actual.abs_diff(expected) <= 1;
+
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert!(close(inter.executed_instructions(), 500));
+    assert_eq!(inter.executed_cycles(), inter.executed_instructions());
+
+    inter.set_instructions_per_second(1000);
+    assert_eq!(inter.instructions_per_second(), 1000.0);
+    inter.reset_counters();
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert!(close(inter.executed_instructions(), 1000));
+
+    inter.set_cycle_weights(|op| match op {
+        chip8::Operation::Jump(_) => 3,
+        _ => 1,
+    });
+    inter.reset_counters();
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert!(close(inter.executed_instructions(), 500));
+    assert!(close(inter.executed_cycles(), 1000));
+
+    // Waiting for a key is no work.
+    let program = [0x60, 0x01, 0xf0, 0x0a];
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    inter.simulate_duration(Duration::from_secs(1)).unwrap();
+    assert_eq!(inter.executed_instructions(), 2);
+    assert_eq!(inter.executed_cycles(), 2);
+}
+
+#[test]
+fn test_wait_for_key() {
+    use chip8::ExecutionState;
+
+    // v3 = key, v0 = 1.
+    let program = [0xf3, 0x0a, 0x60, 0x01];
+    let key = Nibble::try_from(5).unwrap();
+    let run = |inter: &mut ManagedInterpreter<fn() -> u8>, count| {
+        for _ in 0..count {
+            inter.simulate_one_instruction().unwrap();
+        }
+    };
+
+    let mut inter: ManagedInterpreter<fn() -> u8> =
+        ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    run(&mut inter, 3);
+    assert!(matches!(
+        inter.execution_state(),
+        ExecutionState::WaitingForKey { pressed: None, .. }
+    ));
+    inter.set_key_down(key, true);
+    run(&mut inter, 3);
+    assert!(matches!(
+        inter.execution_state(),
+        ExecutionState::WaitingForKey { pressed: Some(k), .. } if k == key
+    ));
+    assert_eq!(inter.registers()[0], 0);
+    inter.set_key_down(key, false);
+    run(&mut inter, 2);
+    assert_eq!(inter.execution_state(), ExecutionState::Running);
+    assert_eq!(inter.registers()[3], 5);
+    assert_eq!(inter.registers()[0], 1);
+
+    let mut inter: ManagedInterpreter<fn() -> u8> =
+        ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    inter.set_quirks(Quirks::CHIP48);
+    inter.set_key_down(key, true);
+    run(&mut inter, 2);
+    assert_eq!(
+        inter.execution_state(),
+        ExecutionState::WaitingForKey {
+            register: Nibble::try_from(3).unwrap(),
+            pressed: None,
+        }
+    );
+    inter.set_key_down(key, false);
+    inter.set_key_down(key, true);
+    run(&mut inter, 2);
+    assert_eq!(inter.registers()[3], 5);
+    assert_eq!(inter.registers()[0], 1);
+}
+
+#[test]
+fn test_dirty_rows() {
+    // I = font sprite for 0, draw it at (0, 3), then clear the screen.
+    let program = [0xa0, 0x50, 0x60, 0x00, 0x61, 0x03, 0xd0, 0x15, 0x00, 0xe0];
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+
+    let dirty = inter.take_dirty_rows();
+    assert_eq!(dirty.iter(32).count(), 32);
+    assert!(inter.take_dirty_rows().is_empty());
+
+    for _ in 0..3 {
+        inter.simulate_one_instruction().unwrap();
+    }
+    assert!(inter.frame_buffer().dirty_rows().is_empty());
+
+    inter.simulate_one_instruction().unwrap();
+    let dirty = inter.take_dirty_rows();
+    assert_eq!(dirty.iter(32).collect::<Vec<_>>(), [3, 4, 5, 6, 7]);
+    assert!(dirty.contains(3) && !dirty.contains(8));
+
+    inter.simulate_one_instruction().unwrap();
+    assert_eq!(inter.take_dirty_rows().iter(32).count(), 32);
+}
+
+#[test]
+fn test_on_frame() {
+    use std::{cell::RefCell, rc::Rc};
+
+    // v0 = 10, sound timer = v0, draw font sprite for 0, loop.
+    let program = [0x60, 0x0a, 0xf0, 0x18, 0xa0, 0x50, 0xd0, 0x15, 0x12, 0x08];
+    let mut inter = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    let frames = Rc::new(RefCell::new(Vec::new()));
+    inter.set_on_frame({
+        let frames = frames.clone();
+        move |frame| {
+            let lit = frame
+                .frame_buffer
+                .iter_rows()
+                .flatten()
+                .filter(|&&p| p)
+                .count();
+            frames.borrow_mut().push((
+                frame.number,
+                frame.sound_timer,
+                frame.is_sound_playing(),
+                lit,
+            ));
+        }
+    });
+
+    inter.simulate_duration(Duration::from_millis(210)).unwrap();
+    let frames = frames.take();
+    assert_eq!(frames.len(), 12);
+    assert_eq!(inter.frames(), 12);
+    for (i, &(number, sound_timer, playing, lit)) in frames.iter().enumerate() {
+        assert_eq!(number, i as u64 + 1);
+        assert_eq!(sound_timer, 9u8.saturating_sub(i as u8));
+        assert_eq!(playing, i < 9);
+        assert_eq!(lit, 14);
+    }
+
+    inter.clear_on_frame();
+    inter.simulate_duration(Duration::from_millis(100)).unwrap();
+    assert_eq!(inter.frames(), 18);
+}
+
+#[test]
+fn test_replay() {
+    use chip8::{KeyEvent, ReplayLog};
+
+    // v0 = rand, v1 = rand, v2 = key, v0 += v2, draw at (v0, v1), loop.
+    let program = [
+        0xc0, 0xff, 0xc1, 0xff, 0xf2, 0x0a, 0x80, 0x24, 0xd0, 0x15, 0x12, 0x00,
+    ];
+    let key = |k: u8| Key::try_from(k).unwrap();
+    let snapshot = |inter: &ManagedInterpreter<fn() -> u8>| {
+        let rows = inter
+            .frame_buffer()
+            .iter_rows()
+            .map(|row| row.to_vec())
+            .collect::<Vec<_>>();
+        (
+            *inter.registers(),
+            inter.program_counter(),
+            inter.executed_instructions(),
+            rows,
+        )
+    };
+
+    let mut inter: ManagedInterpreter<fn() -> u8> =
+        ManagedInterpreter::new(Ch8Image::new(program).unwrap(), rand::random);
+    assert_eq!(inter.stop_recording(), None);
+    inter.start_recording();
+    assert!(inter.is_recording());
+    for (ms, k, is_down) in [
+        (100, 5, true),
+        (50, 5, false),
+        (30, 7, true),
+        (40, 7, false),
+    ] {
+        inter.simulate_duration(Duration::from_millis(ms)).unwrap();
+        inter.set_key_down(key(k), is_down);
+    }
+    inter.simulate_duration(Duration::from_millis(25)).unwrap();
+    let log = inter.stop_recording().unwrap();
+    assert!(!inter.is_recording());
+
+    assert_eq!(log.duration, Duration::from_millis(245));
+    assert_eq!(log.key_events.len(), 4);
+    assert_eq!(
+        log.key_events[1],
+        KeyEvent {
+            time: Duration::from_millis(150),
+            key: key(5),
+            is_down: false
+        }
+    );
+    assert!(log.random_words.len() >= 4);
+
+    // The recorded numbers take precedence over the generator.
+    let mut replayed: ManagedInterpreter<fn() -> u8> =
+        ManagedInterpreter::new(Ch8Image::new(program).unwrap(), || 0);
+    replayed.replay(&log).unwrap();
+    assert_eq!(snapshot(&replayed), snapshot(&inter));
+
+    let mut other = ManagedInterpreter::new(Ch8Image::new(program).unwrap(), || 0);
+    other.replay(&ReplayLog::default()).unwrap();
+    assert_eq!(other.executed_instructions(), 0);
+}
+
+#[test]
+fn test_memory_bounds() {
+    use chip8::Error;
+
+    let run = |program: &[u8], count| {
+        let mut inter = Interpreter::new(
+            Ch8Image::new(program).unwrap(),
+            NullPlatform,
+            Quirks::default(),
+        );
+        for _ in 1..count {
+            inter.run_next_instruction().unwrap();
+        }
+        inter.run_next_instruction()
+    };
+
+    // I = 0xFFF, then store, load, BCD, draw, I += v0 with v0 = 1.
+    for op in [[0xf1, 0x55], [0xf1, 0x65], [0xf0, 0x33], [0xd0, 0x05]] {
+        let program = [0xaf, 0xff, op[0], op[1]];
+        assert!(matches!(
+            run(&program, 2),
+            Err(Error::MemoryOutOfBounds(0xfff))
+        ));
+    }
+    let program = [0xaf, 0xff, 0x60, 0x01, 0xf0, 0x1e];
+    assert!(matches!(
+        run(&program, 3),
+        Err(Error::MemoryOutOfBounds(0x1000))
+    ));
+
+    // Jump to the last byte of memory.
+    let program = [0x1f, 0xff];
+    assert!(matches!(
+        run(&program, 2),
+        Err(Error::MemoryOutOfBounds(0xfff))
+    ));
+
+    // Infinite recursion.
+    let program = [0x22, 0x00];
+    assert!(matches!(run(&program, 17), Err(Error::StackOverflow)));
+}
+
+#[test]
+fn test_peripherals() {
+    use std::{cell::RefCell, rc::Rc};
+
+    use chip8::{Address, OpCode, Peripheral, REG_SIZE};
+
+    // Memory-mapped port at 0xF00 plus a `0123` "print V0" opcode.
+    #[derive(Default)]
+    struct SerialPort {
+        output: Rc<RefCell<Vec<Word>>>,
+        input: Word,
+    }
+
+    impl Peripheral for SerialPort {
+        fn claims(&self, addr: Address) -> bool {
+            addr == Address::new(0xf00)
+        }
+        fn read(&mut self, _addr: Address) -> Word {
+            self.input
+        }
+        fn write(&mut self, _addr: Address, value: Word) {
+            self.output.borrow_mut().push(value);
+        }
+        fn execute(&mut self, opcode: OpCode, registers: &mut [Word; REG_SIZE]) -> bool {
+            if opcode.as_u16() != 0x0123 {
+                return false;
+            }
+            self.output.borrow_mut().push(registers[0]);
+            true
+        }
+    }
+
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let program = [
+        0x60, 0x07, // V0 = 7
+        0x01, 0x23, // print V0
+        0xae, 0xff, // I = 0xEFF
+        0x61, 0x09, // V1 = 9
+        0xf1, 0x55, // store V0..V1 at 0xEFF..0xF00
+        0xae, 0xff, // I = 0xEFF
+        0xf1, 0x65, // load V0..V1
+    ];
+    let mut inter = Interpreter::new(
+        Ch8Image::new(&program).unwrap(),
+        NullPlatform,
+        Quirks::default(),
+    );
+    inter.add_peripheral(SerialPort {
+        output: output.clone(),
+        input: 42,
+    });
+    for _ in 0..7 {
+        inter.run_next_instruction().unwrap();
+    }
+
+    assert_eq!(*output.borrow(), [7, 9]);
+    assert_eq!(inter.memory()[0xeff], 7);
+    assert_eq!(inter.memory()[0xf00], 0);
+    assert_eq!(inter.registers()[..2], [7, 42]);
+    assert_eq!(inter.program_counter(), Address::new(0x20e));
+}
+
+#[test]
+fn test_keymap() {
+    let qwerty = KeyMap::qwerty();
+    assert_eq!(qwerty.len(), 16);
+    assert_eq!(qwerty.get(&'x'), Some(Key::from(0x0)));
+    assert_eq!(qwerty.get(&'4'), Some(Key::from(0xc)));
+    assert_eq!(qwerty.get(&'v'), Some(Key::from(0xf)));
+    assert_eq!(qwerty.get(&'p'), None);
+
+    let text = qwerty.to_string();
+    assert!(text.starts_with("x=0 1=1 2=2 3=3 q=4"));
+    assert!(text.ends_with("4=C r=D f=E v=F"));
+    assert_eq!(text.parse::<KeyMap<char>>().unwrap(), qwerty);
+
+    let mut map = "up=2  down=8\tleft=4 right=6 ==F"
+        .parse::<KeyMap<String>>()
+        .unwrap();
+    assert_eq!(map.get(&"=".to_string()), Some(Key::from(0xf)));
+    assert_eq!(map.bind("up".into(), Key::from(0x5)), Some(Key::from(0x2)));
+    assert_eq!(map.unbind(&"down".into()), Some(Key::from(0x8)));
+    assert_eq!(map.to_string(), "up=5 left=4 right=6 ==F");
+
+    let lengths = map.map_hosts(|host| (host.len() < 5).then_some(host.len()));
+    assert_eq!(
+        lengths.iter().collect::<Vec<_>>(),
+        [
+            (&2, Key::from(0x5)),
+            (&4, Key::from(0x4)),
+            (&1, Key::from(0xf))
+        ]
+    );
+
+    for invalid in ["a=10", "a=g", "a", "a=", "a=-1"] {
+        assert!(invalid.parse::<KeyMap<char>>().is_err(), "{invalid}");
+    }
+    assert!(KeyMap::from_layout("x123").is_err());
+    assert!(KeyMap::from_layout("x123qweasdzc4rfx").is_err());
+}
@@ -0,0 +1,167 @@
+use std::time::{Duration, Instant};
+
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+
+use chip8::{KeyMap, ManagedInterpreter, RandomNumberGenerator};
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct Config {
+    /// Colors of lit and unlit pixels, as `0xRRGGBB`.
+    pub foreground: u32,
+    pub background: u32,
+    /// Window pixels per CHIP-8 pixel in low-res mode, an even number. Hi-res
+    /// frames use half of it, so the window size stays the same.
+    pub scale: usize,
+    pub keymap: KeyMap<Key>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            foreground: 0xFFCC00,
+            background: 0x000000,
+            scale: 10,
+            keymap: crate::translate_keymap(&KeyMap::qwerty(), minifb_key).unwrap(),
+        }
+    }
+}
+
+impl Config {
+    /// Applies `--fg RRGGBB`, `--bg RRGGBB`, `--scale N` and `--keys KEYS`,
+    /// see `parse_keymap` for the latter. Fails on any other option but
+    /// `--gui`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--fg" => config.foreground = parse_color(value()?)?,
+                "--bg" => config.background = parse_color(value()?)?,
+                "--scale" => {
+                    config.scale = match value()?.parse() {
+                        Ok(scale) if scale >= 2 && scale % 2 == 0 => scale,
+                        _ => return Err("scale must be a positive even integer".into()),
+                    }
+                }
+                "--keys" => {
+                    let keymap = crate::parse_keymap(value()?)?;
+                    config.keymap = crate::translate_keymap(&keymap, minifb_key)?;
+                }
+                "--gui" => {}
+                _ => return Err(format!("unknown option: {arg}")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse_color(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value.trim_start_matches('#'), 16)
+        .ok()
+        .filter(|color| *color <= 0xFFFFFF)
+        .ok_or(format!("invalid color: {value}"))
+}
+
+fn minifb_key(c: char) -> Option<Key> {
+    let key = match c.to_ascii_lowercase() {
+        '0' => Key::Key0,
+        '1' => Key::Key1,
+        '2' => Key::Key2,
+        '3' => Key::Key3,
+        '4' => Key::Key4,
+        '5' => Key::Key5,
+        '6' => Key::Key6,
+        '7' => Key::Key7,
+        '8' => Key::Key8,
+        '9' => Key::Key9,
+        'a' => Key::A,
+        'b' => Key::B,
+        'c' => Key::C,
+        'd' => Key::D,
+        'e' => Key::E,
+        'f' => Key::F,
+        'g' => Key::G,
+        'h' => Key::H,
+        'i' => Key::I,
+        'j' => Key::J,
+        'k' => Key::K,
+        'l' => Key::L,
+        'm' => Key::M,
+        'n' => Key::N,
+        'o' => Key::O,
+        'p' => Key::P,
+        'q' => Key::Q,
+        'r' => Key::R,
+        's' => Key::S,
+        't' => Key::T,
+        'u' => Key::U,
+        'v' => Key::V,
+        'w' => Key::W,
+        'x' => Key::X,
+        'y' => Key::Y,
+        'z' => Key::Z,
+        _ => return None,
+    };
+    Some(key)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn run(mut interpreter: ManagedInterpreter<impl RandomNumberGenerator>, config: Config) {
+    let width = chip8::SCREEN_WIDTH * config.scale;
+    let height = chip8::SCREEN_HEIGHT * config.scale;
+    let mut window = Window::new("chip8", width, height, WindowOptions::default())
+        .expect("failed to open window");
+    window.limit_update_rate(Some(Duration::from_micros(16_600)));
+
+    let mut buffer = vec![config.background; width * height];
+    let mut last_instant = Instant::now();
+    let mut crashed = false;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        for (binding, key) in config.keymap.iter() {
+            if window.is_key_pressed(*binding, KeyRepeat::No) {
+                interpreter.set_key_down(key, true);
+            } else if window.is_key_released(*binding) {
+                interpreter.set_key_down(key, false);
+            }
+        }
+
+        let now = Instant::now();
+        let duration = now.duration_since(last_instant);
+        last_instant = now;
+
+        if !crashed {
+            if let Err(err) = interpreter.simulate_duration(duration) {
+                window.set_title(&format!("chip8 - CRASHED: {err}"));
+                crashed = true;
+            }
+        }
+
+        let dirty_rows = interpreter.take_dirty_rows();
+        let frame_buffer = interpreter.frame_buffer();
+        let scale = width / frame_buffer.width();
+        for y in dirty_rows.iter(frame_buffer.height()) {
+            let row = frame_buffer.iter_rows().nth(y).unwrap();
+            let line = &mut buffer[y * scale * width..][..width];
+            for (x, pixel) in row.iter().enumerate() {
+                let color = if *pixel {
+                    config.foreground
+                } else {
+                    config.background
+                };
+                line[x * scale..][..scale].fill(color);
+            }
+            let (line, rest) = buffer[y * scale * width..].split_at_mut(width);
+            for copy in rest[..(scale - 1) * width].chunks_mut(width) {
+                copy.copy_from_slice(line);
+            }
+        }
+
+        window
+            .update_with_buffer(&buffer, width, height)
+            .expect("failed to update window");
+    }
+}
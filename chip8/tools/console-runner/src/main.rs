@@ -1,4 +1,7 @@
-use std::{env::args, fs, time::Instant};
+#[cfg(feature = "gui")]
+mod gui;
+
+use std::{env::args, time::Instant};
 
 use ruscii::{
     app::{App, State},
@@ -8,42 +11,118 @@ use ruscii::{
     terminal::{Color, Style, Window},
 };
 
-use chip8::{Ch8Image, ManagedInterpreter, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::{Ch8Image, KeyMap, ManagedInterpreter, RandomNumberGenerator};
 
 ////////////////////////////////////////////////////////////////////////////////
 
-fn map_key(ruscii_key: Key) -> Option<chip8::Key> {
-    let value = match ruscii_key {
-        Key::Num1 => 0x1,
-        Key::Num2 => 0x2,
-        Key::Num3 => 0x3,
-        Key::Num4 => 0xC,
-        Key::Q => 0x4,
-        Key::W => 0x5,
-        Key::E => 0x6,
-        Key::R => 0xD,
-        Key::A => 0x7,
-        Key::S => 0x8,
-        Key::D => 0x9,
-        Key::F => 0xE,
-        Key::Z => 0xA,
-        Key::X => 0x0,
-        Key::C => 0xB,
-        Key::V => 0xF,
+/// Parses `--keys`: either a layout listing the host keys for `0`..`F`, e.g.
+/// `x123qweasdzc4rfv`, or `HOST=KEY` pairs such as `x=0 1=1 2=2`.
+fn parse_keymap(value: &str) -> Result<KeyMap<char>, String> {
+    let keymap = if value.contains('=') {
+        value.parse()
+    } else {
+        KeyMap::from_layout(value)
+    };
+    keymap.map_err(|err| err.to_string())
+}
+
+/// Translates a character key map to `host` keys, failing on characters the
+/// frontend cannot bind.
+fn translate_keymap<K: PartialEq>(
+    keymap: &KeyMap<char>,
+    host: impl Fn(char) -> Option<K>,
+) -> Result<KeyMap<K>, String> {
+    if let Some((c, _)) = keymap.iter().find(|(c, _)| host(**c).is_none()) {
+        return Err(format!("unsupported key: {c:?}"));
+    }
+    Ok(keymap.map_hosts(|c| host(*c)))
+}
+
+fn keymap_from_args(args: &[String]) -> Result<KeyMap<char>, String> {
+    match args.iter().position(|arg| arg == "--keys") {
+        Some(pos) => parse_keymap(args.get(pos + 1).ok_or("missing value for --keys")?),
+        None => Ok(KeyMap::qwerty()),
+    }
+}
+
+fn ruscii_key(c: char) -> Option<Key> {
+    let key = match c.to_ascii_lowercase() {
+        '0' => Key::Num0,
+        '1' => Key::Num1,
+        '2' => Key::Num2,
+        '3' => Key::Num3,
+        '4' => Key::Num4,
+        '5' => Key::Num5,
+        '6' => Key::Num6,
+        '7' => Key::Num7,
+        '8' => Key::Num8,
+        '9' => Key::Num9,
+        'a' => Key::A,
+        'b' => Key::B,
+        'c' => Key::C,
+        'd' => Key::D,
+        'e' => Key::E,
+        'f' => Key::F,
+        'g' => Key::G,
+        'h' => Key::H,
+        'i' => Key::I,
+        'j' => Key::J,
+        'k' => Key::K,
+        'l' => Key::L,
+        'm' => Key::M,
+        'n' => Key::N,
+        'o' => Key::O,
+        'p' => Key::P,
+        'q' => Key::Q,
+        'r' => Key::R,
+        's' => Key::S,
+        't' => Key::T,
+        'u' => Key::U,
+        'v' => Key::V,
+        'w' => Key::W,
+        'x' => Key::X,
+        'y' => Key::Y,
+        'z' => Key::Z,
         _ => return None,
     };
-    Some(chip8::Key::try_from(value).unwrap())
+    Some(key)
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
+#[cfg(feature = "gui")]
+const GUI_USAGE: &str =
+    "usage: console-runner IMAGE --gui [--fg RRGGBB] [--bg RRGGBB] [--scale N] [--keys KEYS]";
+
 fn main() {
-    let image_path = args().collect::<Vec<_>>()[1].clone();
-    let image_data = fs::read(image_path).unwrap();
-    let image = Ch8Image::new(image_data).expect("failed to load image");
+    let args = args().collect::<Vec<_>>();
+    let image = Ch8Image::from_file(&args[1]).expect("failed to load image");
+
+    let interpreter = ManagedInterpreter::new(image, rand::random);
 
-    let mut interpreter = ManagedInterpreter::new(image, rand::random);
+    if args[2..].iter().any(|arg| arg == "--gui") {
+        #[cfg(feature = "gui")]
+        {
+            let config = gui::Config::from_args(&args[2..]).unwrap_or_else(|err| {
+                eprintln!("{err}\n{GUI_USAGE}");
+                std::process::exit(2)
+            });
+            return gui::run(interpreter, config);
+        }
+        #[cfg(not(feature = "gui"))]
+        panic!("built without the `gui` feature");
+    }
+
+    let keymap = keymap_from_args(&args[2..])
+        .and_then(|keymap| translate_keymap(&keymap, ruscii_key))
+        .unwrap_or_else(|err| panic!("{err}"));
+    run_terminal(interpreter, keymap);
+}
 
+fn run_terminal(
+    mut interpreter: ManagedInterpreter<impl RandomNumberGenerator>,
+    keymap: KeyMap<Key>,
+) {
     let mut app = App::default();
     let mut last_instant = Instant::now();
     let mut crashed_error = None;
@@ -57,17 +136,19 @@ fn main() {
                 KeyEvent::Pressed(key) => (true, key),
                 KeyEvent::Released(key) => (false, key),
             };
-            if let Some(chip8_key) = map_key(*key) {
+            if let Some(chip8_key) = keymap.get(key) {
                 interpreter.set_key_down(chip8_key, is_pressed);
             }
         }
 
+        let screen_width = interpreter.frame_buffer().width();
+        let screen_height = interpreter.frame_buffer().height();
         let window_size = window.size();
         let mut pencil = Pencil::new(window.canvas_mut());
 
         pencil.set_origin(Vec2::xy(
-            (window_size.x - 2 * SCREEN_WIDTH as i32) / 2,
-            (window_size.y - SCREEN_HEIGHT as i32) / 2,
+            (window_size.x - 2 * screen_width as i32) / 2,
+            (window_size.y - screen_height as i32) / 2,
         ));
 
         let border_color = if crashed_error.is_some() {
@@ -78,7 +159,7 @@ fn main() {
         pencil.set_foreground(border_color).draw_rect(
             &RectCharset::simple_round_lines(),
             Vec2::xy(-1, -1),
-            Vec2::xy(SCREEN_WIDTH * 2 + 2, SCREEN_HEIGHT + 2),
+            Vec2::xy(screen_width * 2 + 2, screen_height + 2),
         );
 
         let now = Instant::now();
@@ -91,7 +172,7 @@ fn main() {
                 .set_style(Style::Bold)
                 .draw_center_text(
                     &format!("CRASHED: {}", err),
-                    Vec2::xy(SCREEN_WIDTH, SCREEN_HEIGHT + 1),
+                    Vec2::xy(screen_width, screen_height + 1),
                 );
         } else {
             crashed_error = interpreter.simulate_duration(duration).err();
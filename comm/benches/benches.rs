@@ -4,7 +4,7 @@ use std::{
     process::Command,
 };
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, Criterion};
 use rand::{distributions::Alphanumeric, seq::SliceRandom, thread_rng, Rng};
 use tempfile::{NamedTempFile, TempPath};
 
@@ -16,7 +16,7 @@ fn create_tempfile(data: &[String]) -> io::Result<TempPath> {
     let mut writer = BufWriter::new(file);
     for line in data {
         writer.write_all(line.as_bytes())?;
-        writer.write(b"\n")?;
+        writer.write_all(b"\n")?;
     }
     writer.flush()?;
     Ok(path)
@@ -28,7 +28,7 @@ fn create_tempfiles(first: &[String], second: &[String]) -> io::Result<(TempPath
 
 fn run_comm(path: &str, first: &TempPath, second: &TempPath) {
     let output = Command::new(path)
-        .args(&[first, second])
+        .args([first, second])
         .output()
         .expect("failed to call comm");
 
@@ -58,7 +58,7 @@ fn generate_input(
 
     let mut right_lines: Vec<_> = iter::repeat_with(random_string)
         .take(right_unique)
-        .chain(common_lines.into_iter())
+        .chain(common_lines)
         .collect();
     right_lines.shuffle(&mut thread_rng());
 
@@ -76,10 +76,10 @@ fn bench_50k_50k(c: &mut Criterion) {
         create_tempfiles(&first, &second).expect("failed to create tempfiles");
 
     group.bench_function("rust", |b| {
-        b.iter(|| black_box(run_comm(RUST_BINARY_PATH, &first_path, &second_path)))
+        b.iter(|| run_comm(RUST_BINARY_PATH, &first_path, &second_path))
     });
     group.bench_function("cpp", |b| {
-        b.iter(|| black_box(run_comm(CPP_BINARY_PATH, &first_path, &second_path)))
+        b.iter(|| run_comm(CPP_BINARY_PATH, &first_path, &second_path))
     });
 }
 
@@ -94,10 +94,10 @@ fn bench_0_100k(c: &mut Criterion) {
         create_tempfiles(&first, &second).expect("failed to create tempfiles");
 
     group.bench_function("rust", |b| {
-        b.iter(|| black_box(run_comm(RUST_BINARY_PATH, &first_path, &second_path)))
+        b.iter(|| run_comm(RUST_BINARY_PATH, &first_path, &second_path))
     });
     group.bench_function("cpp", |b| {
-        b.iter(|| black_box(run_comm(CPP_BINARY_PATH, &first_path, &second_path)))
+        b.iter(|| run_comm(CPP_BINARY_PATH, &first_path, &second_path))
     });
 }
 
@@ -1,35 +1,413 @@
 #![forbid(unsafe_code)]
 
 use std::{
-    collections::HashSet,
-    env,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    process,
 };
 
-fn read_lines(path: &str) -> HashSet<String> {
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, ValueEnum};
+
+/// Prints the lines present in all inputs, by default each distinct line once.
+#[derive(Parser)]
+struct Opts {
+    /// Input files, `-` for stdin
+    #[clap(required = true, num_args = 2..)]
+    files: Vec<String>,
+
+    /// Inputs are sorted: merge them in constant memory instead of building a
+    /// set of lines. Keys are compared bytewise
+    #[clap(long)]
+    sorted: bool,
+
+    /// Respect duplicates: print a line as many times as it occurs in every
+    /// input containing it
+    #[clap(long)]
+    multiset: bool,
+
+    /// Prefix each line with its number of occurrences in every input,
+    /// tab-separated. With `--multiset`, the counts stand for the repeats:
+    /// each line is printed once
+    #[clap(long)]
+    count: bool,
+
+    /// Print the lines common to all inputs (the default)
+    #[clap(long, conflicts_with_all = ["report", "unique_to"])]
+    common_to_all: bool,
+
+    /// Print every line, prefixed with the comma-separated numbers (starting
+    /// from 1) of the inputs containing it
+    #[clap(long, conflicts_with = "unique_to")]
+    report: bool,
+
+    /// Print the lines found only in FILE, which must be one of the inputs
+    #[clap(long, value_name = "FILE")]
+    unique_to: Option<String>,
+
+    /// Output format. Structured formats print one record per selected line
+    /// with the inputs containing it and the per-input counts
+    #[clap(long, value_enum, default_value_t = Output::Text)]
+    output: Output,
+
+    #[clap(flatten)]
+    key: KeyOptions,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Text,
+    /// JSON Lines: `{"line": ..., "files": [...], "counts": [...]}`
+    Json,
+    /// `files`, `counts` and `line` columns, the lists comma-separated
+    Tsv,
+}
+
+impl Opts {
+    fn unique_to_index(&self) -> Option<usize> {
+        let file = self.unique_to.as_ref()?;
+        self.files.iter().position(|path| path == file)
+    }
+
+    /// Whether a line with the given per-input counts is printed.
+    fn selects(&self, counts: &[usize]) -> bool {
+        if let Some(index) = self.unique_to_index() {
+            counts
+                .iter()
+                .enumerate()
+                .all(|(i, count)| (*count > 0) == (i == index))
+        } else {
+            self.report || counts.iter().all(|count| *count > 0)
+        }
+    }
+
+    /// Whether the line sets can simply be intersected.
+    fn is_plain_intersection(&self) -> bool {
+        !(self.multiset || self.count || self.report || self.unique_to.is_some())
+            && self.output == Output::Text
+    }
+}
+
+/// What part of a line is compared. Lines are printed as they first appear in
+/// the last input containing them.
+#[derive(Args, Clone)]
+struct KeyOptions {
+    /// Compare lines case-insensitively
+    #[clap(short, long)]
+    ignore_case: bool,
+
+    /// Ignore leading and trailing whitespace and treat inner runs of
+    /// whitespace as a single space
+    #[clap(short = 'w', long)]
+    normalize_whitespace: bool,
+
+    /// Compare only the N-th field (starting from 1); lines with fewer fields
+    /// have an empty key
+    #[clap(short, long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    field: Option<u32>,
+
+    /// Field delimiter for `--field`
+    #[clap(short, long, default_value_t = '\t', requires = "field")]
+    delimiter: char,
+}
+
+impl KeyOptions {
+    fn key<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut key = Cow::Borrowed(line);
+        if let Some(field) = self.field {
+            let field = line.split(self.delimiter).nth(field as usize - 1);
+            key = Cow::Borrowed(field.unwrap_or_default());
+        }
+        if self.normalize_whitespace {
+            key = Cow::Owned(key.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+        if self.ignore_case {
+            key = Cow::Owned(key.to_lowercase());
+        }
+        key
+    }
+
+    /// Like `key`, but reuses `line` if it is the key itself.
+    fn owned_key(&self, line: String) -> String {
+        let key = match self.key(&line) {
+            Cow::Borrowed(key) if key.len() == line.len() => None,
+            key => Some(key.into_owned()),
+        };
+        key.unwrap_or(line)
+    }
+}
+
+/// Prints a selected line according to the output options.
+fn emit(opts: &Opts, out: &mut impl Write, line: &str, counts: &[usize]) -> io::Result<()> {
+    if !opts.selects(counts) {
+        return Ok(());
+    }
+    let files = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(i, _)| i + 1);
+    match opts.output {
+        Output::Text => {}
+        Output::Json => {
+            return writeln!(
+                out,
+                "{{\"line\": {}, \"files\": [{}], \"counts\": [{}]}}",
+                json_string(line),
+                join(files, ", "),
+                join(counts, ", ")
+            );
+        }
+        Output::Tsv => {
+            return writeln!(out, "{}\t{}\t{}", join(files, ","), join(counts, ","), line);
+        }
+    }
+    let mut prefix = String::new();
+    if opts.report {
+        prefix = join(files, ",") + "\t";
+    }
+    if opts.count {
+        for count in counts {
+            prefix += &format!("{count}\t");
+        }
+    }
+    let repeat = if opts.multiset && !opts.count {
+        counts
+            .iter()
+            .filter(|count| **count > 0)
+            .min()
+            .copied()
+            .unwrap_or(0)
+    } else {
+        1
+    };
+    (0..repeat).try_for_each(|_| writeln!(out, "{prefix}{line}"))
+}
+
+fn join(items: impl IntoIterator<Item = impl ToString>, sep: &str) -> String {
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn open(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file =
+        File::open(path).map_err(|err| io::Error::new(err.kind(), format!("{path}: {err}")))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+fn read_keys(path: &str, key: &KeyOptions) -> io::Result<HashSet<String>> {
     let mut set = HashSet::new();
-    let file = File::open(path).unwrap();
-    let reader = BufReader::new(file);
-    for line in reader.lines() {
-        set.insert(line.unwrap());
+    for line in open(path)?.lines() {
+        set.insert(key.owned_key(line?));
     }
-    set
+    Ok(set)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = env::args().collect::<Vec<String>>();
-    let mut first_lines = read_lines(&args[1]);
+/// Prints the lines of the last input whose keys occur in all the others.
+fn intersect(opts: &Opts, out: &mut impl Write) -> io::Result<()> {
+    let (last, rest) = opts.files.split_last().unwrap();
+    let mut keys = read_keys(&rest[0], &opts.key)?;
+    for path in &rest[1..] {
+        let mut common = HashSet::new();
+        for line in open(path)?.lines() {
+            let key = opts.key.owned_key(line?);
+            if keys.contains(&key) {
+                common.insert(key);
+            }
+        }
+        keys = common;
+    }
 
-    let file = std::fs::File::open(&args[2])?;
-    let reader = std::io::BufReader::new(file);
-    for line in reader.lines() {
+    for line in open(last)?.lines() {
         let line = line?;
-        if first_lines.contains(&line) {
-            first_lines.remove(&line);
-            println!("{}", line);
+        if keys.remove(opts.key.key(&line).as_ref()) {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+struct Entry {
+    text: String,
+    /// Input `text` was taken from.
+    file: usize,
+    counts: Vec<usize>,
+}
+
+/// Counts every key in every input. Lines are printed in the order of their
+/// first occurrence.
+fn tabulate(opts: &Opts, out: &mut impl Write) -> io::Result<()> {
+    let mut order = Vec::new();
+    let mut entries = HashMap::<String, Entry>::new();
+    for (file, path) in opts.files.iter().enumerate() {
+        for line in open(path)?.lines() {
+            let text = line?;
+            let key = opts.key.key(&text);
+            if let Some(entry) = entries.get_mut(key.as_ref()) {
+                if entry.file < file {
+                    entry.file = file;
+                    entry.text = text.clone();
+                }
+                entry.counts[file] += 1;
+                continue;
+            }
+            let key = key.into_owned();
+            let mut counts = vec![0; opts.files.len()];
+            counts[file] = 1;
+            order.push(key.clone());
+            entries.insert(key, Entry { text, file, counts });
         }
     }
 
+    for key in order {
+        let entry = &entries[&key];
+        emit(opts, out, &entry.text, &entry.counts)?;
+    }
     Ok(())
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+struct Line {
+    key: String,
+    text: String,
+}
+
+/// Lines of a sorted input, failing on the first one out of order.
+struct SortedLines {
+    path: String,
+    lines: io::Lines<Box<dyn BufRead>>,
+    key: KeyOptions,
+    current: Option<Line>,
+}
+
+impl SortedLines {
+    fn open(path: &str, key: &KeyOptions) -> io::Result<Self> {
+        let mut lines = Self {
+            path: path.to_string(),
+            lines: open(path)?.lines(),
+            key: key.clone(),
+            current: None,
+        };
+        lines.current = lines.read()?;
+        Ok(lines)
+    }
+
+    fn read(&mut self) -> io::Result<Option<Line>> {
+        let Some(text) = self.lines.next().transpose()? else {
+            return Ok(None);
+        };
+        let key = self.key.key(&text).into_owned();
+        Ok(Some(Line { key, text }))
+    }
+
+    fn current_key(&self) -> Option<&str> {
+        self.current.as_ref().map(|line| line.key.as_str())
+    }
+
+    /// Moves to the next line, returning the current one.
+    fn advance(&mut self) -> io::Result<Option<Line>> {
+        let next = self.read()?;
+        if let (Some(current), Some(next)) = (&self.current, &next) {
+            if next.key < current.key {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: input is not sorted", self.path),
+                ));
+            }
+        }
+        Ok(std::mem::replace(&mut self.current, next))
+    }
+
+    /// Skips the lines with the key `key`, returning how many there were.
+    fn skip_equal(&mut self, key: &str) -> io::Result<usize> {
+        let mut count = 0;
+        while self.current_key() == Some(key) {
+            self.advance()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+fn merge(opts: &Opts, out: &mut impl Write) -> io::Result<()> {
+    let mut inputs = opts
+        .files
+        .iter()
+        .map(|path| SortedLines::open(path, &opts.key))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut counts = vec![0; inputs.len()];
+    while let Some(key) = inputs.iter().filter_map(SortedLines::current_key).min() {
+        let key = key.to_string();
+        let mut text = None;
+        for (input, count) in inputs.iter_mut().zip(&mut counts) {
+            *count = 0;
+            if input.current_key() == Some(&key) {
+                text = input.advance()?.map(|line| line.text);
+                *count = 1 + input.skip_equal(&key)?;
+            }
+        }
+        emit(opts, out, &text.unwrap(), &counts)?;
+    }
+    Ok(())
+}
+
+fn run(opts: &Opts) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    if opts.sorted {
+        merge(opts, &mut out)?;
+    } else if opts.is_plain_intersection() {
+        intersect(opts, &mut out)?;
+    } else {
+        tabulate(opts, &mut out)?;
+    }
+    out.flush()
+}
+
+fn main() {
+    let opts = Opts::parse();
+    if opts.files.iter().filter(|path| *path == "-").count() > 1 {
+        Opts::command()
+            .error(ErrorKind::ArgumentConflict, "only one input can be stdin")
+            .exit();
+    }
+    if opts.unique_to.is_some() && opts.unique_to_index().is_none() {
+        Opts::command()
+            .error(
+                ErrorKind::InvalidValue,
+                "the --unique-to file must be one of the inputs",
+            )
+            .exit();
+    }
+
+    if let Err(err) = run(&opts) {
+        eprintln!("comm: {err}");
+        process::exit(1);
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     collections::HashSet,
     io::{self, Write},
-    process::Command,
+    process::{Command, Stdio},
 };
 
 use pretty_assertions::assert_eq;
@@ -15,11 +15,15 @@ const BINARY_PATH: &str = if cfg!(debug_assertions) {
 };
 
 fn run_comm(first: &[&str], second: &[&str]) -> Vec<String> {
+    run_comm_with_args(&[], first, second)
+}
+
+fn run_comm_with_args(args: &[&str], first: &[&str], second: &[&str]) -> Vec<String> {
     fn create_tempfile(data: &[&str]) -> io::Result<TempPath> {
         let (mut file, path) = NamedTempFile::new()?.into_parts();
         for line in data {
             file.write_all(line.as_bytes())?;
-            file.write(b"\n")?;
+            file.write_all(b"\n")?;
         }
         file.flush()?;
         Ok(path)
@@ -28,7 +32,8 @@ fn run_comm(first: &[&str], second: &[&str]) -> Vec<String> {
     let first_path = create_tempfile(first).expect("failed to create temp file");
     let second_path = create_tempfile(second).expect("failed to create temp file");
     let output = Command::new(BINARY_PATH)
-        .args(&[first_path, second_path])
+        .args(args)
+        .args([&first_path, &second_path])
         .output()
         .expect("failed to call comm");
 
@@ -109,3 +114,263 @@ fn test_random() {
         check(&first, &second, &answer);
     }
 }
+
+#[test]
+fn test_stdin() {
+    let (mut file, path) = NamedTempFile::new().unwrap().into_parts();
+    file.write_all(b"foo\nbar\n").unwrap();
+    file.flush().unwrap();
+
+    for args in [["-", path.to_str().unwrap()], [path.to_str().unwrap(), "-"]] {
+        let mut child = Command::new(BINARY_PATH)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to call comm");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"bar\nbaz\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "comm process failed");
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "bar\n");
+    }
+}
+
+#[test]
+fn test_bad_arguments() {
+    for args in [&[][..], &["-"], &["-", "-"], &["/nonexistent/file", "-"]] {
+        let output = Command::new(BINARY_PATH)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .expect("failed to call comm");
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("panicked"), "{stderr}");
+    }
+
+    let output = Command::new(BINARY_PATH).arg("--help").output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("stdin"));
+}
+
+#[test]
+fn test_sorted() {
+    let run = |first: &[&str], second: &[&str]| run_comm_with_args(&["--sorted"], first, second);
+    assert_eq!(run(&["a", "b", "c"], &["b", "c", "d"]), ["b", "c"]);
+    assert_eq!(
+        run(&["a", "a", "b", "b"], &["a", "b", "b", "c"]),
+        ["a", "b"]
+    );
+    assert_eq!(run(&["", "", "x"], &["", "y"]), [""]);
+    assert_eq!(run(&[], &["a"]), Vec::<String>::new());
+
+    let mut rng = StdRng::seed_from_u64(42);
+    for _ in 0..100 {
+        let mut first = (0..rng.gen_range(0..50))
+            .map(|_| {
+                ["x", "y", "z", "xy", "yz"]
+                    .choose(&mut rng)
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        let mut second = (0..rng.gen_range(0..50))
+            .map(|_| {
+                ["x", "y", "z", "xz", "zz"]
+                    .choose(&mut rng)
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        first.sort();
+        second.sort();
+        let mut expected = run_comm(&first, &second);
+        expected.sort();
+        assert_eq!(run(&first, &second), expected);
+    }
+}
+
+#[test]
+fn test_sorted_rejects_unsorted_input() {
+    let (mut file, path) = NamedTempFile::new().unwrap().into_parts();
+    file.write_all(b"b\na\n").unwrap();
+    file.flush().unwrap();
+
+    let output = Command::new(BINARY_PATH)
+        .arg("--sorted")
+        .args([&path, &path])
+        .output()
+        .expect("failed to call comm");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("not sorted"));
+}
+
+#[test]
+fn test_duplicates() {
+    let first = ["a", "b", "a", "c", "a"];
+    let second = ["a", "c", "a", "d", "c"];
+    for sorted in [false, true] {
+        let (mut first, mut second) = (first.to_vec(), second.to_vec());
+        let mut args = vec![];
+        if sorted {
+            first.sort();
+            second.sort();
+            args.push("--sorted");
+        }
+        let run = |flag| {
+            let mut args = args.clone();
+            args.push(flag);
+            let mut output = run_comm_with_args(&args, &first, &second);
+            output.sort();
+            output
+        };
+        assert_eq!(run("--multiset"), ["a", "a", "c"]);
+        assert_eq!(run("--count"), ["1\t2\tc", "3\t2\ta"]);
+        let mut args = args.clone();
+        args.extend(["--multiset", "--count"]);
+        let mut output = run_comm_with_args(&args, &first, &second);
+        output.sort();
+        assert_eq!(output, ["1\t2\tc", "3\t2\ta"]);
+    }
+}
+
+#[test]
+fn test_comparison_options() {
+    let check_with = |args: &[&str], first: &[&str], second: &[&str], expected: &[&str]| {
+        for sorted in [false, true] {
+            let mut args = args.to_vec();
+            if sorted {
+                args.push("--sorted");
+            }
+            let mut output = run_comm_with_args(&args, first, second);
+            output.sort();
+            assert_eq!(output, expected, "args: {args:?}");
+        }
+    };
+
+    check_with(
+        &["-i"],
+        &["Apple", "banana"],
+        &["BANANA", "cherry"],
+        &["BANANA"],
+    );
+    check_with(&["-w"], &["  a  b ", "c"], &["a b", "d"], &["a b"]);
+    check_with(
+        &["--field", "2", "--delimiter", ","],
+        &["3", "1,alice", "2,bob"],
+        &["", "x,alice", "y,carol"],
+        &["", "x,alice"],
+    );
+    check_with(
+        &["-f", "1", "-i", "--count"],
+        &["a\tone", "A\ttwo", "b\tthree"],
+        &["a\tfour", "c\tfive"],
+        &["2\t1\ta\tfour"],
+    );
+
+    let output = Command::new(BINARY_PATH)
+        .args(["--field", "0", "-", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+fn run_comm_files(args: &[&str], files: &[&[&str]]) -> Vec<String> {
+    let paths = files
+        .iter()
+        .map(|lines| {
+            let (mut file, path) = NamedTempFile::new()?.into_parts();
+            for line in *lines {
+                writeln!(file, "{line}")?;
+            }
+            Ok(path)
+        })
+        .collect::<io::Result<Vec<_>>>()
+        .expect("failed to create temp file");
+    let output = Command::new(BINARY_PATH)
+        .args(args.iter().map(|arg| match arg.strip_prefix('#') {
+            Some(index) => paths[index.parse::<usize>().unwrap()].as_os_str(),
+            None => arg.as_ref(),
+        }))
+        .args(&paths)
+        .output()
+        .expect("failed to call comm");
+    assert!(output.status.success(), "comm process failed");
+
+    let mut result = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    result.sort();
+    result
+}
+
+#[test]
+fn test_many_inputs() {
+    let files: &[&[&str]] = &[
+        &["a", "b", "c", "a"],
+        &["b", "c", "d"],
+        &["c", "b", "e", "b"],
+    ];
+    for sorted in [&[][..], &["--sorted"]] {
+        let files = files
+            .iter()
+            .map(|lines| {
+                let mut lines = lines.to_vec();
+                if !sorted.is_empty() {
+                    lines.sort();
+                }
+                lines
+            })
+            .collect::<Vec<_>>();
+        let files = files.iter().map(|lines| &lines[..]).collect::<Vec<_>>();
+        let run = |args: &[&str]| run_comm_files(&[sorted, args].concat(), &files);
+
+        assert_eq!(run(&[]), ["b", "c"]);
+        assert_eq!(run(&["--common-to-all"]), ["b", "c"]);
+        assert_eq!(run(&["--multiset"]), ["b", "c"]);
+        assert_eq!(run(&["--count"]), ["1\t1\t1\tc", "1\t1\t2\tb"]);
+        assert_eq!(
+            run(&["--report"]),
+            ["1\ta", "1,2,3\tb", "1,2,3\tc", "2\td", "3\te"]
+        );
+        assert_eq!(run(&["--report", "--multiset"])[..2], ["1\ta", "1\ta"]);
+        assert_eq!(run(&["--unique-to", "#0"]), ["a"]);
+        assert_eq!(run(&["--unique-to", "#2", "--count"]), ["0\t0\t1\te"]);
+    }
+
+    let output = Command::new(BINARY_PATH)
+        .args(["--unique-to", "other", "-", "/dev/null"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_structured_output() {
+    let files: &[&[&str]] = &[&["a", "say \"hi\"\t!", "a"], &["a", "say \"hi\"\t!", "b"]];
+    assert_eq!(
+        run_comm_files(&["--output", "json"], files),
+        [
+            r#"{"line": "a", "files": [1, 2], "counts": [2, 1]}"#,
+            r#"{"line": "say \"hi\"\t!", "files": [1, 2], "counts": [1, 1]}"#,
+        ]
+    );
+    assert_eq!(
+        run_comm_files(&["--output", "tsv", "--report"], files),
+        ["1,2\t1,1\tsay \"hi\"\t!", "1,2\t2,1\ta", "2\t0,1\tb"]
+    );
+    assert_eq!(
+        run_comm_files(&["--output", "text", "--sorted"], &[&["a", "b"], &["b"]]),
+        ["b"]
+    );
+}
@@ -4,7 +4,8 @@ use std::{borrow::Borrow, iter::FromIterator, ops::Index};
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Default, Debug, PartialEq, Eq)]
+/// Maps compare lexicographically by their entries in key order.
+#[derive(Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct FlatMap<K, V>(Vec<(K, V)>);
 
 impl<K: Ord, V> FlatMap<K, V> {
@@ -71,6 +72,115 @@ impl<K: Ord, V> FlatMap<K, V> {
                 .remove(self.0.iter().position(|(k, _)| k.borrow() == key)?),
         )
     }
+
+    /// A cursor at the first entry.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, K, V> {
+        CursorMut {
+            map: self,
+            index: 0,
+        }
+    }
+
+    /// A cursor at the first entry with a key not less than `key`.
+    pub fn cursor_at_mut<Q>(&mut self, key: &Q) -> CursorMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut cursor = self.cursor_front_mut();
+        cursor.seek(key);
+        cursor
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A position in a `FlatMap`: either at an entry or past the last one.
+/// Edits at the cursor only shift the entries after it and don't search for
+/// the position again.
+pub struct CursorMut<'a, K, V> {
+    map: &'a mut FlatMap<K, V>,
+    index: usize,
+}
+
+impl<K: Ord, V> CursorMut<'_, K, V> {
+    /// Number of entries before the cursor.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.index == self.map.len()
+    }
+
+    pub fn key(&self) -> Option<&K> {
+        self.map.0.get(self.index).map(|(k, _)| k)
+    }
+
+    pub fn value(&self) -> Option<&V> {
+        self.map.0.get(self.index).map(|(_, v)| v)
+    }
+
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        self.map.0.get_mut(self.index).map(|(_, v)| v)
+    }
+
+    /// The entry before the cursor.
+    pub fn peek_prev(&self) -> Option<(&K, &V)> {
+        let (k, v) = self.map.0.get(self.index.checked_sub(1)?)?;
+        Some((k, v))
+    }
+
+    /// Moves to the next entry. Returns `false` if already past the last one.
+    pub fn move_next(&mut self) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+        self.index += 1;
+        true
+    }
+
+    /// Moves to the previous entry. Returns `false` if already at the first
+    /// one.
+    pub fn move_prev(&mut self) -> bool {
+        if self.index == 0 {
+            return false;
+        }
+        self.index -= 1;
+        true
+    }
+
+    /// Moves to the first entry with a key not less than `key`. Returns
+    /// whether its key is equal to `key`.
+    pub fn seek<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let found = self.map.0.binary_search_by(|(k, _)| k.borrow().cmp(key));
+        self.index = found.unwrap_or_else(|pos| pos);
+        found.is_ok()
+    }
+
+    /// Inserts an entry before the cursor, which stays at the same entry.
+    /// Panics unless `key` is between the keys around the cursor.
+    pub fn insert_before(&mut self, key: K, value: V) {
+        assert!(
+            self.peek_prev().is_none_or(|(prev, _)| *prev < key)
+                && self.key().is_none_or(|next| key < *next),
+            "key is out of order at the cursor"
+        );
+        self.map.0.insert(self.index, (key, value));
+        self.index += 1;
+    }
+
+    /// Removes the entry at the cursor, moving to the one after it.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        if self.is_at_end() {
+            return None;
+        }
+        Some(self.map.0.remove(self.index))
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -3,7 +3,10 @@ use flatmap::FlatMap;
 use pretty_assertions::assert_eq;
 use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
-use std::{collections::HashMap, iter::FromIterator};
+use std::{
+    collections::{BTreeSet, HashMap},
+    iter::FromIterator,
+};
 
 #[test]
 fn test_basics() {
@@ -83,6 +86,23 @@ fn test_conversions() {
     assert_eq!(vec_three.as_slice(), expected);
 }
 
+#[test]
+fn test_clone_and_order() {
+    let map = FlatMap::from(vec![(3, "c"), (1, "a")]);
+    let mut snapshot = map.clone();
+    snapshot.insert(2, "b");
+    assert_eq!(map.as_slice(), &[(1, "a"), (3, "c")]);
+    assert_eq!(snapshot.as_slice(), &[(1, "a"), (2, "b"), (3, "c")]);
+
+    assert!(snapshot < map);
+    assert!(FlatMap::from(vec![(1, "a")]) < map);
+    assert!(FlatMap::from(vec![(3, "a"), (1, "a")]) < map);
+    assert!(FlatMap::<i32, &str>::new() < FlatMap::from(vec![(0, "")]));
+
+    let set = BTreeSet::from_iter([map.clone(), snapshot.clone(), map.clone()]);
+    assert_eq!(Vec::from_iter(set), vec![snapshot, map]);
+}
+
 #[test]
 fn test_dedup() {
     let map_one = FlatMap::from(vec![(1, 1), (5, 5), (4, 41), (4, 40), (4, 44)]);
@@ -99,6 +119,84 @@ fn test_dedup() {
     assert_eq!(map_three.as_slice(), expected);
 }
 
+#[test]
+fn test_cursor() {
+    let mut map = FlatMap::from(vec![(1, "a"), (3, "c"), (5, "e"), (7, "g")]);
+
+    let mut cursor = map.cursor_front_mut();
+    assert_eq!(cursor.key(), Some(&1));
+    assert!(!cursor.move_prev());
+    assert!(cursor.move_next());
+    assert_eq!(cursor.peek_prev(), Some((&1, &"a")));
+    cursor.insert_before(2, "b");
+    assert_eq!((cursor.index(), cursor.key()), (2, Some(&3)));
+    *cursor.value_mut().unwrap() = "C";
+    assert_eq!(cursor.remove_current(), Some((3, "C")));
+    assert_eq!(cursor.value(), Some(&"e"));
+
+    assert!(!cursor.seek(&6));
+    assert_eq!(cursor.key(), Some(&7));
+    assert!(cursor.seek(&1));
+    assert_eq!(cursor.index(), 0);
+    assert!(!cursor.seek(&10));
+    assert!(cursor.is_at_end());
+    assert_eq!(cursor.key(), None);
+    assert_eq!(cursor.remove_current(), None);
+    assert!(!cursor.move_next());
+    cursor.insert_before(8, "h");
+    assert!(cursor.move_prev());
+    assert_eq!(cursor.key(), Some(&8));
+
+    assert_eq!(
+        map.as_slice(),
+        &[(1, "a"), (2, "b"), (5, "e"), (7, "g"), (8, "h")]
+    );
+
+    let mut cursor = map.cursor_at_mut(&4);
+    assert_eq!(cursor.key(), Some(&5));
+    cursor.insert_before(3, "c");
+    assert_eq!(map.get(&3), Some(&"c"));
+}
+
+#[test]
+fn test_cursor_merge() {
+    let mut rng = StdRng::seed_from_u64(23);
+    let mut map = (0..200)
+        .map(|_| (rng.gen_range(0..1000), 1))
+        .collect::<FlatMap<_, _>>();
+    let updates = (0..200)
+        .map(|_| rng.gen_range(0..1000))
+        .collect::<BTreeSet<_>>();
+    let mut expected = map.as_slice().iter().copied().collect::<HashMap<_, _>>();
+    for key in &updates {
+        *expected.entry(*key).or_insert(0) += 1;
+    }
+
+    // Bumps the existing keys and inserts the missing ones in a single pass.
+    let mut cursor = map.cursor_front_mut();
+    for key in updates {
+        while cursor.key().is_some_and(|k| *k < key) {
+            cursor.move_next();
+        }
+        if cursor.key() == Some(&key) {
+            *cursor.value_mut().unwrap() += 1;
+        } else {
+            cursor.insert_before(key, 1);
+        }
+    }
+
+    let mut expected = expected.into_iter().collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(map.as_slice(), expected.as_slice());
+}
+
+#[test]
+#[should_panic]
+fn test_cursor_insert_out_of_order() {
+    let mut map = FlatMap::from(vec![(1, 1), (3, 3)]);
+    map.cursor_at_mut(&3).insert_before(0, 0);
+}
+
 #[test]
 fn test_random_insertions_small() {
     let mut rng = StdRng::seed_from_u64(23254452323);
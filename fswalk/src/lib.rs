@@ -1,27 +1,56 @@
 #![forbid(unsafe_code)]
 
+mod hash;
+
+pub use hash::{Digest, HashAlgorithm};
+
+use hash::Hasher;
 use std::{
     fs::{self},
     io::{self, Read},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 ////////////////////////////////////////////////////////////////////////////////
 
 type Callback<'a> = dyn FnMut(&mut Handle) + 'a;
+type DigestSink<'a> = dyn FnMut(&Path, Digest) + 'a;
+
+const CHUNK_SIZE: usize = 64 * 1024;
 
 #[derive(Default)]
 pub struct Walker<'a> {
     callbacks: Vec<Box<Callback<'a>>>,
+    hashers: Vec<(HashAlgorithm, Box<DigestSink<'a>>)>,
+    same_file_system: bool,
 }
 
 impl<'a> Walker<'a> {
     pub fn new() -> Self {
         Self {
             callbacks: Vec::new(),
+            hashers: Vec::new(),
+            same_file_system: false,
         }
     }
 
+    /// Don't descend into directories on another file system than the
+    /// walked one, like mount points. Their handles are still passed to the
+    /// callbacks. Only has an effect on Unix, where device ids are compared.
+    pub fn same_file_system(&mut self, enabled: bool) {
+        self.same_file_system = enabled;
+    }
+
+    /// Hashes every file under the walked directory, passing its path and
+    /// digest to `sink`. Content is streamed in chunks unless a callback also
+    /// asks to read the file; either way each file is read once per walk.
+    pub fn hash_files<F>(&mut self, algo: HashAlgorithm, sink: F)
+    where
+        F: FnMut(&Path, Digest) + 'a,
+    {
+        self.hashers.push((algo, Box::new(sink)));
+    }
+
     pub fn add_callback<F>(&mut self, callback: F)
     where
         F: FnMut(&mut Handle) + 'a,
@@ -30,13 +59,53 @@ impl<'a> Walker<'a> {
     }
 
     pub fn walk<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        if self.callbacks.is_empty() {
+        if self.callbacks.is_empty() && self.hashers.is_empty() {
             return Ok(());
         }
-        Self::rec_walk(path.as_ref(), self.callbacks.as_mut_slice())
+        let device = self.device(path.as_ref())?;
+        Self::rec_walk(
+            path.as_ref(),
+            self.callbacks.as_mut_slice(),
+            &mut self.hashers,
+            device,
+            None,
+        )
+    }
+
+    /// Runs the directory and file callbacks as `walk` would, but only
+    /// records what would be descended into and read. No file is opened and
+    /// no content callback or digest sink is called.
+    pub fn plan<P: AsRef<Path>>(&mut self, path: P) -> io::Result<WalkPlan> {
+        let mut plan = WalkPlan::default();
+        if !self.callbacks.is_empty() || !self.hashers.is_empty() {
+            let device = self.device(path.as_ref())?;
+            Self::rec_walk(
+                path.as_ref(),
+                self.callbacks.as_mut_slice(),
+                &mut self.hashers,
+                device,
+                Some(&mut plan),
+            )?;
+        }
+        Ok(plan)
+    }
+
+    /// The device to stay on, if any.
+    fn device(&self, path: &Path) -> io::Result<Option<u64>> {
+        if self.same_file_system {
+            device_id(path)
+        } else {
+            Ok(None)
+        }
     }
 
-    fn rec_walk(dir: &Path, callbacks: &mut [Box<Callback>]) -> io::Result<()> {
+    fn rec_walk(
+        dir: &Path,
+        callbacks: &mut [Box<Callback>],
+        hashers: &mut [(HashAlgorithm, Box<DigestSink>)],
+        device: Option<u64>,
+        mut plan: Option<&mut WalkPlan>,
+    ) -> io::Result<()> {
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
@@ -66,20 +135,26 @@ impl<'a> Walker<'a> {
                     idx += 1;
                 }
             }
+            // Nobody is interested in what is below.
+            if idx == 0 && hashers.is_empty() {
+                continue;
+            }
 
-            match handle {
-                Handle::Dir(dir) => Self::rec_walk(dir.path(), &mut callbacks[0..idx])?,
-                Handle::File(file_handle) => {
-                    let mut file = fs::File::open(file_handle.path())?;
-                    let mut buf = Vec::new();
-                    file.read_to_end(&mut buf)?;
-                    let mut content_handle = Handle::Content {
-                        file_path: file_handle.path(),
-                        content: &buf,
-                    };
-                    for cb in callbacks.iter_mut().take(idx) {
-                        cb(&mut content_handle);
+            match (handle, plan.as_deref_mut()) {
+                (Handle::Dir(dir), mut plan) => {
+                    if device.is_some() && device_id(dir.path())? != device {
+                        continue;
+                    }
+                    if let Some(plan) = &mut plan {
+                        plan.descended.push(dir.path().to_owned());
                     }
+                    Self::rec_walk(dir.path(), &mut callbacks[0..idx], hashers, device, plan)?
+                }
+                (Handle::File(file_handle), Some(plan)) => {
+                    plan.read.push(file_handle.path().to_owned())
+                }
+                (Handle::File(file_handle), None) => {
+                    Self::visit_file(file_handle.path(), &mut callbacks[0..idx], hashers)?
                 }
                 _ => {}
             }
@@ -87,6 +162,51 @@ impl<'a> Walker<'a> {
         Ok(())
     }
 
+    fn visit_file(
+        path: &Path,
+        callbacks: &mut [Box<Callback>],
+        hashers: &mut [(HashAlgorithm, Box<DigestSink>)],
+    ) -> io::Result<()> {
+        let mut file = fs::File::open(path)?;
+        let mut states = hashers
+            .iter()
+            .map(|(algo, _)| Hasher::new(*algo))
+            .collect::<Vec<_>>();
+
+        if callbacks.is_empty() {
+            let mut buf = vec![0; CHUNK_SIZE];
+            loop {
+                let len = match file.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(len) => len,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+                for state in &mut states {
+                    state.update(&buf[..len]);
+                }
+            }
+        } else {
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf)?;
+            for state in &mut states {
+                state.update(&buf);
+            }
+            let mut content_handle = Handle::Content {
+                file_path: path,
+                content: &buf,
+            };
+            for cb in callbacks.iter_mut() {
+                cb(&mut content_handle);
+            }
+        }
+
+        for ((_, sink), state) in hashers.iter_mut().zip(states) {
+            sink(path, state.finish());
+        }
+        Ok(())
+    }
+
     fn checked(handle: &mut Handle) -> bool {
         match handle {
             Handle::Dir(dir) => {
@@ -104,6 +224,30 @@ impl<'a> Walker<'a> {
     }
 }
 
+#[cfg(unix)]
+fn device_id(path: &Path) -> io::Result<Option<u64>> {
+    use std::os::unix::fs::MetadataExt;
+
+    Ok(Some(fs::metadata(path)?.dev()))
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// What `Walker::walk` would do, see `Walker::plan`. Paths are in visiting
+/// order and include the walked directory as a prefix.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WalkPlan {
+    /// Directories that would be descended into, not counting the walked one.
+    pub descended: Vec<PathBuf>,
+    /// Files whose content would be read, for the callbacks or for hashing.
+    pub read: Vec<PathBuf>,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub enum Handle<'a> {
@@ -1,11 +1,12 @@
 use tempdir::TempDir;
 
 use std::{
+    collections::BTreeMap,
     fs, io,
-    path::{Component, Path},
+    path::{Component, Path, PathBuf},
 };
 
-use fswalk::{Handle, Walker};
+use fswalk::{Handle, HashAlgorithm, WalkPlan, Walker};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -111,11 +112,8 @@ fn test_two_handlers() {
                 Handle::Content { file_path, .. } => file_path.to_owned(),
             };
             for comp in path_to_check.components() {
-                match comp {
-                    Component::Normal(path) => {
-                        assert!(!path.to_str().unwrap().starts_with(forbidden_prefix))
-                    }
-                    _ => (),
+                if let Component::Normal(path) = comp {
+                    assert!(!path.to_str().unwrap().starts_with(forbidden_prefix))
                 }
             }
 
@@ -170,3 +168,165 @@ fn test_error() {
     walker.add_callback(|_| ());
     assert!(walker.walk("oiuabsas/sapdigu/aspgdh").is_err());
 }
+
+#[test]
+fn test_hash_files() {
+    let tree_desc: TreeDesc = &[
+        ("abc", b"abc"),
+        ("digits/nine", b"123456789"),
+        ("digits/empty", b""),
+        ("skipped/abc", b"abc"),
+    ];
+    let tmp_dir = make_tree(tree_desc).unwrap();
+    fs::write(tmp_dir.path().join("digits/big"), vec![b'a'; 1_000_000]).unwrap();
+
+    let relative = |path: &Path| {
+        let path = path.strip_prefix(tmp_dir.path()).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let mut sha = BTreeMap::new();
+    let mut crc = BTreeMap::new();
+    let mut fnv = BTreeMap::new();
+    let mut content_reads = 0;
+    {
+        let mut walker = Walker::new();
+        walker.hash_files(HashAlgorithm::Sha256, |path, digest| {
+            sha.insert(relative(path), digest.to_string());
+        });
+        walker.hash_files(HashAlgorithm::Crc32, |path, digest| {
+            crc.insert(relative(path), digest.to_string());
+        });
+        walker.hash_files(HashAlgorithm::Fnv1a64, |path, digest| {
+            fnv.insert(relative(path), digest.as_bytes().to_vec());
+        });
+        walker.add_callback(|handle| match handle {
+            Handle::Dir(dir_handle) => {
+                if !dir_handle.path().ends_with("skipped") {
+                    dir_handle.descend();
+                }
+            }
+            Handle::File(file_handle) => file_handle.read(),
+            Handle::Content { .. } => content_reads += 1,
+        });
+        walker.walk(tmp_dir.path()).unwrap();
+    }
+
+    assert_eq!(content_reads, 4);
+    assert_eq!(sha.len(), 5);
+    assert_eq!(
+        sha["abc"],
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(sha["skipped/abc"], sha["abc"]);
+    assert_eq!(
+        sha["digits/empty"],
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        sha["digits/big"],
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+    assert_eq!(crc["digits/nine"], "cbf43926");
+    assert_eq!(crc["digits/empty"], "00000000");
+    assert_eq!(fnv["digits/empty"], 0xcbf29ce484222325u64.to_be_bytes());
+    assert_eq!(fnv["abc"], 0xe71fa2190541574bu64.to_be_bytes());
+}
+
+#[test]
+fn test_plan() {
+    let tree_desc: TreeDesc = &[
+        ("notes.txt", b"notes"),
+        ("image.png", b"png"),
+        ("src/main.txt", b"main"),
+        ("src/deep/lib.txt", b"lib"),
+        ("target/out.txt", b"out"),
+    ];
+    let tmp_dir = make_tree(tree_desc).unwrap();
+    let sorted = |plan: WalkPlan| {
+        let relative = |paths: Vec<PathBuf>| {
+            let mut paths = paths
+                .iter()
+                .map(|path| {
+                    let path = path.strip_prefix(tmp_dir.path()).unwrap();
+                    path.to_str().unwrap().to_string()
+                })
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+        (relative(plan.descended), relative(plan.read))
+    };
+
+    let mut content_reads = 0;
+    {
+        let mut walker = Walker::new();
+        walker.add_callback(|handle| match handle {
+            Handle::Dir(dir_handle) => {
+                if !dir_handle.path().ends_with("target") {
+                    dir_handle.descend();
+                }
+            }
+            Handle::File(file_handle) => {
+                if file_handle.path().extension().is_some_and(|e| e == "txt") {
+                    file_handle.read();
+                }
+            }
+            Handle::Content { .. } => content_reads += 1,
+        });
+        let (descended, read) = sorted(walker.plan(tmp_dir.path()).unwrap());
+        assert_eq!(descended, ["src", "src/deep"]);
+        assert_eq!(read, ["notes.txt", "src/deep/lib.txt", "src/main.txt"]);
+    }
+    assert_eq!(content_reads, 0);
+
+    // Hashing reads every file.
+    let mut digests = 0;
+    let mut walker = Walker::new();
+    walker.hash_files(HashAlgorithm::Crc32, |_, _| digests += 1);
+    let (descended, read) = sorted(walker.plan(tmp_dir.path()).unwrap());
+    assert_eq!(descended, ["src", "src/deep", "target"]);
+    assert_eq!(read.len(), 5);
+    drop(walker);
+    assert_eq!(digests, 0);
+
+    assert_eq!(
+        Walker::new().plan(tmp_dir.path()).unwrap(),
+        WalkPlan::default()
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_same_file_system() {
+    let tree_desc: TreeDesc = &[("src/main.txt", b"main"), ("src/deep/lib.txt", b"lib")];
+    let tmp_dir = make_tree(tree_desc).unwrap();
+    // procfs is never on the same file system as a temporary directory.
+    std::os::unix::fs::symlink("/proc", tmp_dir.path().join("proc")).unwrap();
+
+    let plan = |same_file_system| {
+        let mut walker = Walker::new();
+        walker.same_file_system(same_file_system);
+        let root = tmp_dir.path().to_owned();
+        walker.add_callback(move |handle| {
+            if let Handle::Dir(dir_handle) = handle {
+                // Don't go deep into /proc.
+                let path = dir_handle.path();
+                if path == root.join("proc") || !path.starts_with(root.join("proc")) {
+                    dir_handle.descend();
+                }
+            }
+        });
+        let mut descended = walker
+            .plan(tmp_dir.path())
+            .unwrap()
+            .descended
+            .iter()
+            .map(|path| path.strip_prefix(tmp_dir.path()).unwrap().to_owned())
+            .collect::<Vec<_>>();
+        descended.sort();
+        descended
+    };
+
+    assert_eq!(plan(false), ["proc", "src", "src/deep"].map(PathBuf::from));
+    assert_eq!(plan(true), ["src", "src/deep"].map(PathBuf::from));
+}
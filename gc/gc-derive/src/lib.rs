@@ -1,6 +1,6 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Fields, Ident};
 
 #[proc_macro_derive(Scan, attributes(scan))]
 pub fn derive_scan(input: TokenStream) -> TokenStream {
@@ -34,9 +34,51 @@ pub fn derive_scan(input: TokenStream) -> TokenStream {
         } else {
             None
         }
+    } else if let Data::Enum(data_enum) = &input.data {
+        Some(derive_enum(ident, data_enum))
     } else {
         None
     };
 
     custom_impl.unwrap_or(default_impl).into()
 }
+
+/// Scans all the fields of whichever variant the value is.
+fn derive_enum(ident: &Ident, data_enum: &DataEnum) -> proc_macro2::TokenStream {
+    let arms = data_enum.variants.iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        let bindings = (0..variant.fields.len())
+            .map(|i| format_ident!("field_{}", i))
+            .collect::<Vec<_>>();
+        let pattern = match &variant.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| &f.ident);
+                quote! { { #(#names: #bindings),* } }
+            }
+            Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+            Fields::Unit => quote! {},
+        };
+        let objects = if bindings.is_empty() {
+            quote! { vec![] }
+        } else {
+            quote! { [#(Scan::get_objects(#bindings)),*].concat() }
+        };
+        quote! { Self::#variant_ident #pattern => #objects, }
+    });
+
+    // Bindings are references, unless there is nothing to bind.
+    let scrutinee = if data_enum.variants.is_empty() {
+        quote! { *self }
+    } else {
+        quote! { self }
+    };
+    quote! {
+        impl Scan for #ident {
+            fn get_objects(&self) -> Vec<usize> {
+                match #scrutinee {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
@@ -3,8 +3,9 @@
 pub use gc_derive::Scan;
 
 use std::{
+    any::type_name,
     cell::RefCell,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     marker::PhantomData,
     ops::Deref,
     rc::{Rc, Weak},
@@ -91,35 +92,106 @@ impl Scan for i32 {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-pub struct Arena(Vec<Rc<dyn Scan>>);
+/// Object counts for values of one type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TypeStats {
+    pub live: usize,
+    pub reclaimed: usize,
+}
+
+/// Called with the `std::any::type_name` and the address of an object, see
+/// `Arena::on_alloc` and `Arena::on_reclaim`.
+pub type Hook = Box<dyn FnMut(&'static str, usize)>;
+
+struct Allocation {
+    obj: Rc<dyn Scan>,
+    type_name: &'static str,
+}
+
+pub struct Arena {
+    objects: Vec<Allocation>,
+    reclaimed: HashMap<&'static str, usize>,
+    on_alloc: Option<Hook>,
+    on_reclaim: Option<Hook>,
+}
 
 impl Arena {
     pub fn new() -> Self {
-        Self(vec![])
+        Self {
+            objects: vec![],
+            reclaimed: HashMap::new(),
+            on_alloc: None,
+            on_reclaim: None,
+        }
+    }
+
+    /// Calls `hook` after every allocation, replacing the previous hook.
+    pub fn on_alloc(&mut self, hook: impl FnMut(&'static str, usize) + 'static) {
+        self.on_alloc = Some(Box::new(hook));
+    }
+
+    /// Calls `hook` for every object a sweep frees, right before it is
+    /// dropped. Replaces the previous hook.
+    pub fn on_reclaim(&mut self, hook: impl FnMut(&'static str, usize) + 'static) {
+        self.on_reclaim = Some(Box::new(hook));
     }
 
     pub fn allocation_count(&self) -> usize {
-        self.0.len()
+        self.objects.len()
+    }
+
+    /// Addresses of the live objects, as given by `Gc::extract_addr`, in
+    /// allocation order.
+    pub fn addrs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.objects.iter().map(|a| Self::addr(&a.obj))
+    }
+
+    fn addr(obj: &Rc<dyn Scan>) -> usize {
+        Rc::as_ptr(obj) as *const u8 as usize
     }
 
     pub fn alloc<T: Scan + 'static>(&mut self, obj: T) -> Gc<T> {
         let rc: Rc<T> = Rc::new(obj);
         let weak = Rc::downgrade(&rc);
-        self.0.push(rc);
+        let allocation = Allocation {
+            obj: rc,
+            type_name: type_name::<T>(),
+        };
+        if let Some(hook) = &mut self.on_alloc {
+            hook(allocation.type_name, Self::addr(&allocation.obj));
+        }
+        self.objects.push(allocation);
         Gc { weak }
     }
 
+    /// Live and reclaimed object counts by `std::any::type_name` of the
+    /// allocated type. Reclaim counts accumulate over all sweeps.
+    pub fn stats_by_type(&self) -> BTreeMap<&'static str, TypeStats> {
+        let mut stats = BTreeMap::<_, TypeStats>::new();
+        for allocation in &self.objects {
+            stats.entry(allocation.type_name).or_default().live += 1;
+        }
+        for (type_name, reclaimed) in &self.reclaimed {
+            stats.entry(type_name).or_default().reclaimed = *reclaimed;
+        }
+        stats
+    }
+
+    /// Frees the objects unreachable from outside the arena. Survivors keep
+    /// their allocation order, and the garbage is dropped in allocation order
+    /// too, so that sweeps are reproducible.
     pub fn sweep(&mut self) {
-        let idx_by_obj = (0..self.0.len())
-            .map(|i| (Rc::as_ptr(&self.0[i]) as *const u8 as usize, i))
+        let idx_by_obj = (0..self.objects.len())
+            .map(|i| (Self::addr(&self.objects[i].obj), i))
             .collect::<HashMap<_, _>>();
-        let mut point_to = vec![0; self.0.len()];
+        let mut point_to = vec![0; self.objects.len()];
 
-        let graph = self
-            .0
+        let graph: Vec<Vec<usize>> = self
+            .objects
             .iter()
             .map(|a| {
-                a.get_objects()
+                a.obj
+                    .get_objects()
                     .iter()
                     .map(|x| {
                         point_to[idx_by_obj[x]] += 1;
@@ -129,31 +201,34 @@ impl Arena {
             })
             .collect();
 
-        let mut marked = HashSet::with_capacity(self.0.len());
+        let mut marked = HashSet::with_capacity(self.objects.len());
         for (i, count) in point_to.iter().enumerate() {
-            if Rc::weak_count(&self.0[i]) > *count {
+            if Rc::weak_count(&self.objects[i].obj) > *count {
                 Self::mark_all(i, &mut marked, &graph);
             }
         }
 
-        let mut j = 0;
-        for i in 0..self.0.len() {
+        let objects = std::mem::take(&mut self.objects);
+        for (i, allocation) in objects.into_iter().enumerate() {
             if marked.contains(&i) {
-                if i > j {
-                    self.0.swap(j, i);
+                self.objects.push(allocation);
+            } else {
+                *self.reclaimed.entry(allocation.type_name).or_default() += 1;
+                if let Some(hook) = &mut self.on_reclaim {
+                    hook(allocation.type_name, Self::addr(&allocation.obj));
                 }
-                j += 1;
             }
         }
-        self.0.truncate(j);
     }
 
-    fn mark_all(root_addr: usize, marked: &mut HashSet<usize>, graph: &Vec<Vec<usize>>) {
-        if !marked.insert(root_addr) {
-            return;
-        }
-        for u in &graph[root_addr] {
-            Self::mark_all(*u, marked, graph);
+    /// Marks what `root_addr` reaches, with a work list rather than recursion
+    /// so that deep object chains don't overflow the stack.
+    fn mark_all(root_addr: usize, marked: &mut HashSet<usize>, graph: &[Vec<usize>]) {
+        let mut pending = vec![root_addr];
+        while let Some(v) = pending.pop() {
+            if marked.insert(v) {
+                pending.extend(graph[v].iter().filter(|u| !marked.contains(u)));
+            }
         }
     }
 }
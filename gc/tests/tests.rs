@@ -1,6 +1,6 @@
-use gc::{Arena, Gc, Scan};
+use gc::{Arena, Gc, Scan, TypeStats};
 
-use std::cell::RefCell;
+use std::{cell::RefCell, rc::Rc};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -22,6 +22,35 @@ struct Vertex {
     neigh: Vec<Gc<RefCell<Vertex>>>,
 }
 
+#[derive(Scan)]
+enum Value {
+    Nil,
+    Int(i32),
+    Pair(Gc<RefCell<Value>>, Gc<RefCell<Value>>),
+    Cell { value: Option<Gc<RefCell<Value>>> },
+}
+
+#[derive(Scan)]
+#[allow(dead_code)]
+enum Never {}
+
+struct Tracked {
+    id: i32,
+    drops: Rc<RefCell<Vec<i32>>>,
+}
+
+impl Scan for Tracked {
+    fn get_objects(&self) -> Vec<usize> {
+        vec![]
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.drops.borrow_mut().push(self.id);
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 #[test]
@@ -94,6 +123,45 @@ fn test_cyclic_list() {
     assert_eq!(arena.allocation_count(), 0);
 }
 
+#[test]
+fn test_deep_list() {
+    const LEN: usize = 100_000;
+    let mut arena = Arena::new();
+
+    // Allocated tail first, so that every node points to an earlier one.
+    let mut head = arena.alloc(RefCell::new(Node::default()));
+    for _ in 1..LEN {
+        head = arena.alloc(RefCell::new(Node {
+            next: Some(head.clone()),
+        }));
+    }
+    arena.sweep();
+    assert_eq!(arena.allocation_count(), LEN);
+
+    // A cycle through the whole list, reachable only from the middle.
+    let mut middle = head.clone();
+    for _ in 0..LEN / 2 {
+        let next = middle.borrow().borrow().next.clone().unwrap();
+        middle = next;
+    }
+    let mut tail = middle.clone();
+    loop {
+        let next = tail.borrow().borrow().next.clone();
+        match next {
+            Some(next) => tail = next,
+            None => break,
+        }
+    }
+    tail.borrow().borrow_mut().next = Some(head.clone());
+    drop((head, tail));
+    arena.sweep();
+    assert_eq!(arena.allocation_count(), LEN);
+
+    drop(middle);
+    arena.sweep();
+    assert_eq!(arena.allocation_count(), 0);
+}
+
 #[test]
 fn test_cliques() {
     let mut arena = Arena::new();
@@ -117,7 +185,7 @@ fn test_cliques() {
     arena.sweep();
     assert_eq!(arena.allocation_count(), 5);
 
-    let c2 = make_clique(&mut arena).into_iter().skip(2).next().unwrap();
+    let c2 = make_clique(&mut arena).into_iter().nth(2).unwrap();
     assert_eq!(arena.allocation_count(), 10);
     arena.sweep();
     assert_eq!(arena.allocation_count(), 10);
@@ -130,3 +198,153 @@ fn test_cliques() {
     arena.sweep();
     assert_eq!(arena.allocation_count(), 0);
 }
+
+#[test]
+fn test_stats_by_type() {
+    let mut arena = Arena::new();
+    let mut ints = (0..3).map(|x| arena.alloc(Int { x })).collect::<Vec<_>>();
+    let node = arena.alloc(RefCell::new(Node::default()));
+    node.borrow().borrow_mut().next = Some(node.clone());
+    assert_eq!(
+        arena
+            .stats_by_type()
+            .values()
+            .map(|s| s.live)
+            .sum::<usize>(),
+        4
+    );
+
+    drop(node);
+    drop(ints.remove(1));
+    arena.sweep();
+
+    let stats = arena.stats_by_type();
+    let int_name = std::any::type_name::<Int>();
+    let node_name = std::any::type_name::<RefCell<Node>>();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(
+        stats[int_name],
+        TypeStats {
+            live: 2,
+            reclaimed: 1
+        }
+    );
+    assert_eq!(
+        stats[node_name],
+        TypeStats {
+            live: 0,
+            reclaimed: 1
+        }
+    );
+}
+
+#[test]
+fn test_sweep_order() {
+    let mut arena = Arena::new();
+    let drops = Rc::new(RefCell::new(Vec::new()));
+    let mut objects = (0..6)
+        .map(|id| {
+            let drops = drops.clone();
+            Some(arena.alloc(Tracked { id, drops }))
+        })
+        .collect::<Vec<_>>();
+    let addrs = objects
+        .iter()
+        .map(|obj| obj.as_ref().unwrap().extract_addr())
+        .collect::<Vec<_>>();
+    assert_eq!(arena.addrs().collect::<Vec<_>>(), addrs);
+
+    for i in [4, 1, 2] {
+        objects[i] = None;
+    }
+    arena.sweep();
+    assert_eq!(*drops.borrow(), [1, 2, 4]);
+    assert_eq!(
+        arena.addrs().collect::<Vec<_>>(),
+        [addrs[0], addrs[3], addrs[5]]
+    );
+
+    let last = arena.alloc(Int { x: 6 });
+    objects[0] = None;
+    arena.sweep();
+    assert_eq!(*drops.borrow(), [1, 2, 4, 0]);
+    assert_eq!(
+        arena.addrs().collect::<Vec<_>>(),
+        [addrs[3], addrs[5], last.extract_addr()]
+    );
+}
+
+#[test]
+fn test_hooks() {
+    let mut arena = Arena::new();
+    let allocs = Rc::new(RefCell::new(Vec::new()));
+    let reclaims = Rc::new(RefCell::new(Vec::new()));
+    let drops = Rc::new(RefCell::new(Vec::new()));
+    arena.on_alloc({
+        let allocs = allocs.clone();
+        move |type_name, addr| allocs.borrow_mut().push((type_name, addr))
+    });
+    arena.on_reclaim({
+        let (reclaims, drops) = (reclaims.clone(), drops.clone());
+        move |type_name, addr| {
+            // Not dropped yet.
+            assert!(drops.borrow().is_empty());
+            reclaims.borrow_mut().push((type_name, addr));
+        }
+    });
+
+    let int = arena.alloc(Int { x: 1 });
+    let tracked = arena.alloc(Tracked {
+        id: 0,
+        drops: drops.clone(),
+    });
+    let int_name = std::any::type_name::<Int>();
+    let tracked_name = std::any::type_name::<Tracked>();
+    assert_eq!(
+        *allocs.borrow(),
+        [
+            (int_name, int.extract_addr()),
+            (tracked_name, tracked.extract_addr())
+        ]
+    );
+
+    arena.sweep();
+    assert!(reclaims.borrow().is_empty());
+
+    let addr = tracked.extract_addr();
+    drop(tracked);
+    arena.sweep();
+    assert_eq!(*reclaims.borrow(), [(tracked_name, addr)]);
+    assert_eq!(*drops.borrow(), [0]);
+    assert_eq!(allocs.borrow().len(), 2);
+}
+
+#[test]
+fn test_enum() {
+    let mut arena = Arena::new();
+    let nil = arena.alloc(RefCell::new(Value::Nil));
+    let int = arena.alloc(RefCell::new(Value::Int(1)));
+    let cell = arena.alloc(RefCell::new(Value::Cell { value: None }));
+    let pair = arena.alloc(RefCell::new(Value::Pair(int.clone(), cell.clone())));
+    assert_eq!(
+        pair.borrow().get_objects(),
+        [int.extract_addr(), cell.extract_addr()]
+    );
+    assert!(int.borrow().get_objects().is_empty());
+
+    // pair -> cell -> pair
+    *cell.borrow().borrow_mut() = Value::Cell {
+        value: Some(pair.clone()),
+    };
+    assert_eq!(cell.borrow().get_objects(), [pair.extract_addr()]);
+    drop((int, cell));
+    arena.sweep();
+    assert_eq!(arena.allocation_count(), 4);
+
+    drop(pair);
+    arena.sweep();
+    assert_eq!(arena.allocation_count(), 1);
+    drop(nil);
+    arena.sweep();
+    assert_eq!(arena.allocation_count(), 0);
+}
@@ -1,5 +1,9 @@
 #![forbid(unsafe_code)]
 
+mod schema;
+
+pub use schema::*;
+
 use std::collections::HashMap;
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -32,7 +36,7 @@ pub fn parse(content: &str) -> IniFile {
                 }
             } else if line.contains('=') {
                 let (key, value) = line.split_at(line.find('=').expect("checked by cond"));
-                let value = value.trim_start_matches(|c| c == '=').trim();
+                let value = value.trim_start_matches('=').trim();
                 if value.contains('=') {
                     panic!("= is not avaliable in value");
                 }
@@ -0,0 +1,237 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::IniFile;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// What a value must parse as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    /// Anything, the empty value included.
+    String,
+    Integer,
+    Float,
+    /// `true`, `false`, `yes`, `no`, `on`, `off`, `1` or `0`, in any case.
+    Bool,
+}
+
+impl ValueType {
+    pub fn accepts(self, value: &str) -> bool {
+        match self {
+            Self::String => true,
+            Self::Integer => value.parse::<i64>().is_ok(),
+            Self::Float => value.parse::<f64>().is_ok(),
+            Self::Bool => ["true", "false", "yes", "no", "on", "off", "1", "0"]
+                .iter()
+                .any(|b| b.eq_ignore_ascii_case(value)),
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::String => "a string",
+            Self::Integer => "an integer",
+            Self::Float => "a number",
+            Self::Bool => "a boolean",
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Sections an `IniFile` may have, see `validate`. Sections not described
+/// are allowed unless `deny_unknown_sections` is set.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    sections: Vec<SectionSchema>,
+    deny_unknown_sections: bool,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn section(mut self, section: SectionSchema) -> Self {
+        self.sections.push(section);
+        self
+    }
+
+    pub fn deny_unknown_sections(mut self) -> Self {
+        self.deny_unknown_sections = true;
+        self
+    }
+}
+
+/// Keys a section may have. Keys not described are allowed unless
+/// `deny_unknown_keys` is set.
+#[derive(Clone, Debug)]
+pub struct SectionSchema {
+    name: String,
+    required: bool,
+    keys: Vec<KeySchema>,
+    deny_unknown_keys: bool,
+}
+
+#[derive(Clone, Debug)]
+struct KeySchema {
+    name: String,
+    value_type: ValueType,
+    required: bool,
+}
+
+impl SectionSchema {
+    pub fn required(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            required: true,
+            keys: Vec::new(),
+            deny_unknown_keys: false,
+        }
+    }
+
+    pub fn optional(name: impl Into<String>) -> Self {
+        Self {
+            required: false,
+            ..Self::required(name)
+        }
+    }
+
+    pub fn key(self, name: impl Into<String>, value_type: ValueType) -> Self {
+        self.push_key(name.into(), value_type, true)
+    }
+
+    pub fn optional_key(self, name: impl Into<String>, value_type: ValueType) -> Self {
+        self.push_key(name.into(), value_type, false)
+    }
+
+    pub fn deny_unknown_keys(mut self) -> Self {
+        self.deny_unknown_keys = true;
+        self
+    }
+
+    fn push_key(mut self, name: String, value_type: ValueType, required: bool) -> Self {
+        self.keys.push(KeySchema {
+            name,
+            value_type,
+            required,
+        });
+        self
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    MissingSection {
+        section: String,
+    },
+    MissingKey {
+        section: String,
+        key: String,
+    },
+    InvalidValue {
+        section: String,
+        key: String,
+        value: String,
+        expected: ValueType,
+    },
+    UnknownSection {
+        section: String,
+    },
+    UnknownKey {
+        section: String,
+        key: String,
+    },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::MissingSection { section } => write!(f, "missing section [{section}]"),
+            Self::MissingKey { section, key } => {
+                write!(f, "missing key {key:?} in section [{section}]")
+            }
+            Self::InvalidValue {
+                section,
+                key,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value {value:?} of key {key:?} in section [{section}]: expected {expected}"
+            ),
+            Self::UnknownSection { section } => write!(f, "unknown section [{section}]"),
+            Self::UnknownKey { section, key } => {
+                write!(f, "unknown key {key:?} in section [{section}]")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks `ini` against `schema`. Errors come in the order of the schema,
+/// then unknown sections and keys sorted by name; none means `ini` is valid.
+pub fn validate(ini: &IniFile, schema: &Schema) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    for section_schema in &schema.sections {
+        let section = section_schema.name.clone();
+        let Some(entries) = ini.get(&section) else {
+            if section_schema.required {
+                errors.push(ValidationError::MissingSection { section });
+            }
+            continue;
+        };
+
+        for key_schema in &section_schema.keys {
+            let key = key_schema.name.clone();
+            match entries.get(&key) {
+                None if key_schema.required => errors.push(ValidationError::MissingKey {
+                    section: section.clone(),
+                    key,
+                }),
+                Some(value) if !key_schema.value_type.accepts(value) => {
+                    errors.push(ValidationError::InvalidValue {
+                        section: section.clone(),
+                        key,
+                        value: value.clone(),
+                        expected: key_schema.value_type,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        if section_schema.deny_unknown_keys {
+            let mut unknown = entries
+                .keys()
+                .filter(|key| section_schema.keys.iter().all(|k| k.name != **key))
+                .collect::<Vec<_>>();
+            unknown.sort();
+            errors.extend(unknown.into_iter().map(|key| ValidationError::UnknownKey {
+                section: section.clone(),
+                key: key.clone(),
+            }));
+        }
+    }
+
+    if schema.deny_unknown_sections {
+        let mut unknown = ini
+            .keys()
+            .filter(|section| schema.sections.iter().all(|s| s.name != **section))
+            .collect::<Vec<_>>();
+        unknown.sort();
+        errors.extend(
+            unknown
+                .into_iter()
+                .map(|section| ValidationError::UnknownSection {
+                    section: section.clone(),
+                }),
+        );
+    }
+    errors
+}
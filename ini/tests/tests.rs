@@ -1,4 +1,4 @@
-use ini::{parse, IniFile};
+use ini::{parse, validate, IniFile, Schema, SectionSchema, ValidationError, ValueType};
 
 use pretty_assertions::assert_eq;
 
@@ -257,3 +257,89 @@ fn test_triple_equals() {
          abra = cadabra=foo",
     );
 }
+
+#[test]
+fn test_schema() {
+    let schema = Schema::new()
+        .section(
+            SectionSchema::required("server")
+                .key("host", ValueType::String)
+                .key("port", ValueType::Integer)
+                .optional_key("verbose", ValueType::Bool)
+                .deny_unknown_keys(),
+        )
+        .section(SectionSchema::required("limits").key("ratio", ValueType::Float))
+        .section(SectionSchema::optional("cache").key("size", ValueType::Integer));
+
+    let ini = parse(
+        "[server]\n\
+         host = localhost\n\
+         port = 8080\n\
+         verbose = Yes\n\
+         [limits]\n\
+         ratio = 0.5\n\
+         [extra]\n\
+         a = b",
+    );
+    assert_eq!(validate(&ini, &schema), []);
+    assert_eq!(
+        validate(&ini, &schema.clone().deny_unknown_sections()),
+        [ValidationError::UnknownSection {
+            section: "extra".to_string()
+        }]
+    );
+
+    let ini = parse(
+        "[server]\n\
+         port = eighty\n\
+         verbose = maybe\n\
+         timeout = 5\n\
+         debug\n\
+         [cache]\n\
+         size = 1",
+    );
+    let errors = validate(&ini, &schema);
+    assert_eq!(
+        errors,
+        [
+            ValidationError::MissingKey {
+                section: "server".to_string(),
+                key: "host".to_string()
+            },
+            ValidationError::InvalidValue {
+                section: "server".to_string(),
+                key: "port".to_string(),
+                value: "eighty".to_string(),
+                expected: ValueType::Integer
+            },
+            ValidationError::InvalidValue {
+                section: "server".to_string(),
+                key: "verbose".to_string(),
+                value: "maybe".to_string(),
+                expected: ValueType::Bool
+            },
+            ValidationError::UnknownKey {
+                section: "server".to_string(),
+                key: "debug".to_string()
+            },
+            ValidationError::UnknownKey {
+                section: "server".to_string(),
+                key: "timeout".to_string()
+            },
+            ValidationError::MissingSection {
+                section: "limits".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        [
+            "missing key \"host\" in section [server]",
+            "invalid value \"eighty\" of key \"port\" in section [server]: expected an integer",
+            "invalid value \"maybe\" of key \"verbose\" in section [server]: expected a boolean",
+            "unknown key \"debug\" in section [server]",
+            "unknown key \"timeout\" in section [server]",
+            "missing section [limits]",
+        ]
+    );
+}
@@ -1,14 +1,31 @@
 #![forbid(unsafe_code)]
 
-use std::{cell::RefCell, collections::VecDeque, fmt::Debug, rc::Rc};
+pub mod oneshot;
+pub mod rpc;
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Debug,
+    rc::Rc,
+    sync,
+    task::Waker,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 
 ////////////////////////////////////////////////////////////////////////////////
-///
+struct Message<T> {
+    value: T,
+    deadline: Option<Instant>,
+}
+
 #[derive(Default)]
 pub struct Inner<T> {
-    buffer: VecDeque<T>,
+    buffer: VecDeque<Message<T>>,
     state: InnerState,
+    waker: Option<Waker>,
+    expired: usize,
 }
 
 impl<T> Inner<T> {
@@ -16,15 +33,30 @@ impl<T> Inner<T> {
         Self {
             buffer: VecDeque::new(),
             state: InnerState::default(),
+            waker: None,
+            expired: 0,
         }
     }
 
+    /// Drops the values whose deadline has passed on the way.
     pub fn pop_front(&mut self) -> Option<T> {
-        self.buffer.pop_front()
+        let now = Instant::now();
+        while let Some(message) = self.buffer.pop_front() {
+            match message.deadline {
+                Some(deadline) if deadline <= now => self.expired += 1,
+                _ => return Some(message.value),
+            }
+        }
+        None
     }
 
     pub fn push_back(&mut self, value: T) {
-        self.buffer.push_back(value);
+        self.push_back_with_deadline(value, None);
+    }
+
+    pub fn push_back_with_deadline(&mut self, value: T, deadline: Option<Instant>) {
+        self.buffer.push_back(Message { value, deadline });
+        self.wake();
     }
 
     pub fn is_empty(&self) -> bool {
@@ -33,6 +65,13 @@ impl<T> Inner<T> {
 
     pub fn change_state(&mut self, state: InnerState) {
         self.state = state;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
     }
 }
 
@@ -57,10 +96,22 @@ pub struct Sender<T> {
 
 impl<T> Sender<T> {
     pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.send_with_deadline(value, None)
+    }
+
+    /// Sends a value that the receiver drops instead of returning once `ttl`
+    /// has passed, see `Receiver::expired_count`.
+    pub fn send_with_ttl(&self, value: T, ttl: Duration) -> Result<(), SendError<T>> {
+        self.send_with_deadline(value, Instant::now().checked_add(ttl))
+    }
+
+    fn send_with_deadline(&self, value: T, deadline: Option<Instant>) -> Result<(), SendError<T>> {
         if self.is_closed() {
             return Err(SendError { value });
         }
-        self.inner.borrow_mut().push_back(value);
+        self.inner
+            .borrow_mut()
+            .push_back_with_deadline(value, deadline);
         Ok(())
     }
 
@@ -88,7 +139,7 @@ impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
         // first is the last tx that will be dropped and second is rx
         if Rc::strong_count(&self.inner) == 2 {
-            self.inner.borrow_mut().state = InnerState::Closed;
+            self.inner.borrow_mut().change_state(InnerState::Closed);
         }
     }
 }
@@ -108,17 +159,43 @@ pub struct Receiver<T> {
 }
 
 impl<T> Receiver<T> {
+    /// Skips the values sent with a TTL that has run out.
     pub fn recv(&mut self) -> Result<T, ReceiveError> {
         let mut buffer = self.inner.borrow_mut();
-        match buffer.state {
-            InnerState::Open if buffer.is_empty() => Err(ReceiveError::Empty),
-            _ => buffer.pop_front().ok_or(ReceiveError::Closed),
+        match (buffer.pop_front(), &buffer.state) {
+            (Some(value), _) => Ok(value),
+            (None, InnerState::Open) => Err(ReceiveError::Empty),
+            (None, InnerState::Closed) => Err(ReceiveError::Closed),
         }
     }
 
+    /// Number of values dropped so far because their TTL ran out.
+    pub fn expired_count(&self) -> usize {
+        self.inner.borrow().expired
+    }
+
     pub fn close(&mut self) {
         self.inner.borrow_mut().change_state(InnerState::Closed);
     }
+
+    /// Closes the channel and moves the buffered values into a new
+    /// `std::sync::mpsc` channel, whose receiver disconnects once they are
+    /// consumed.
+    pub fn into_std(mut self) -> sync::mpsc::Receiver<T> {
+        self.close();
+        let (sender, receiver) = sync::mpsc::channel();
+        while let Ok(value) = self.recv() {
+            sender.send(value).unwrap();
+        }
+        receiver
+    }
+
+    /// Adapts the receiver into a `futures::Stream` that ends once the channel
+    /// is closed and drained.
+    #[cfg(feature = "stream")]
+    pub fn into_stream(self) -> ReceiverStream<T> {
+        ReceiverStream { receiver: self }
+    }
 }
 
 impl<T> Drop for Receiver<T> {
@@ -129,6 +206,42 @@ impl<T> Drop for Receiver<T> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+#[cfg(feature = "stream")]
+pub struct ReceiverStream<T> {
+    receiver: Receiver<T>,
+}
+
+#[cfg(feature = "stream")]
+impl<T> ReceiverStream<T> {
+    pub fn into_inner(self) -> Receiver<T> {
+        self.receiver
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T> futures_core::Stream for ReceiverStream<T> {
+    type Item = T;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<T>> {
+        use std::task::Poll;
+
+        let receiver = &mut self.get_mut().receiver;
+        match receiver.recv() {
+            Ok(value) => Poll::Ready(Some(value)),
+            Err(ReceiveError::Closed) => Poll::Ready(None),
+            Err(ReceiveError::Empty) => {
+                receiver.inner.borrow_mut().waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     let inner = Rc::new(RefCell::new(Inner::new()));
     (
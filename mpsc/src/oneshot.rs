@@ -0,0 +1,84 @@
+//! A channel for a single value, e.g. the reply to a request, see `rpc`.
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{ReceiveError, SendError};
+
+////////////////////////////////////////////////////////////////////////////////
+
+struct Inner<T> {
+    value: Option<T>,
+    /// Set once either side is dropped or the receiver is closed.
+    closed: bool,
+}
+
+pub struct Sender<T> {
+    inner: Rc<RefCell<Inner<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// Fails if the receiver is closed or gone.
+    pub fn send(self, value: T) -> Result<(), SendError<T>> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.closed {
+            return Err(SendError { value });
+        }
+        inner.value = Some(value);
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.borrow().closed
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.inner.borrow_mut().closed = true;
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct Receiver<T> {
+    inner: Rc<RefCell<Inner<T>>>,
+}
+
+impl<T> Receiver<T> {
+    /// Returns `ReceiveError::Empty` while the sender may still send, and
+    /// `ReceiveError::Closed` once the value is taken or will never come.
+    pub fn recv(&mut self) -> Result<T, ReceiveError> {
+        let mut inner = self.inner.borrow_mut();
+        match (inner.value.take(), inner.closed) {
+            (Some(value), _) => Ok(value),
+            (None, false) => Err(ReceiveError::Empty),
+            (None, true) => Err(ReceiveError::Closed),
+        }
+    }
+
+    /// Makes the sender fail. A value sent before is still received.
+    pub fn close(&mut self) {
+        self.inner.borrow_mut().closed = true;
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Rc::new(RefCell::new(Inner {
+        value: None,
+        closed: false,
+    }));
+    (
+        Sender {
+            inner: Rc::clone(&inner),
+        },
+        Receiver { inner },
+    )
+}
@@ -0,0 +1,35 @@
+//! Requests sent over a channel together with a `oneshot` sender for the
+//! response, for actors serving their callers through a single channel.
+
+use crate::{oneshot, SendError, Sender};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// What the serving side receives: the request and where to respond to it.
+pub struct Request<Req, Resp> {
+    pub request: Req,
+    pub reply: oneshot::Sender<Resp>,
+}
+
+impl<Req, Resp> Request<Req, Resp> {
+    /// Sends the response, failing if the caller has stopped waiting.
+    pub fn respond(self, response: Resp) -> Result<(), SendError<Resp>> {
+        self.reply.send(response)
+    }
+}
+
+/// Sends `request` to the serving side of `sender` and returns the receiver
+/// of the response. If the request is dropped without a response, the
+/// receiver reports `ReceiveError::Closed`.
+pub fn call<Req, Resp>(
+    sender: &Sender<Request<Req, Resp>>,
+    request: Req,
+) -> Result<oneshot::Receiver<Resp>, SendError<Req>> {
+    let (reply, receiver) = oneshot::channel();
+    match sender.send(Request { request, reply }) {
+        Ok(()) => Ok(receiver),
+        Err(SendError { value }) => Err(SendError {
+            value: value.request,
+        }),
+    }
+}
@@ -1,6 +1,6 @@
-use mpsc::{channel, ReceiveError};
+use mpsc::{channel, oneshot, rpc, ReceiveError, SendError};
 
-use std::{error::Error, iter::repeat};
+use std::{error::Error, iter::repeat_n, thread, time::Duration};
 
 #[derive(Debug)]
 struct Int(usize);
@@ -21,7 +21,7 @@ fn test_simple() {
 #[test]
 fn test_sender_clone() {
     let (sender, mut receiver) = channel::<Int>();
-    let senders = repeat(sender).take(10).collect::<Vec<_>>();
+    let senders = repeat_n(sender, 10).collect::<Vec<_>>();
     for k in 0..10 {
         for i in k..k + 10 {
             senders[i % 10].send(Int(i % 10)).unwrap();
@@ -35,15 +35,15 @@ fn test_sender_clone() {
 #[test]
 fn test_close() {
     let (sender, mut receiver) = channel::<Int>();
-    let senders = repeat(sender).take(10).collect::<Vec<_>>();
+    let senders = repeat_n(sender, 10).collect::<Vec<_>>();
     for i in 0..10 {
         senders[0].send(Int(i)).unwrap();
     }
     receiver.close();
 
-    for i in 0..10 {
-        assert!(senders[i].is_closed());
-        let err = senders[i].send(Int(i)).unwrap_err();
+    for (i, sender) in senders.iter().enumerate() {
+        assert!(sender.is_closed());
+        let err = sender.send(Int(i)).unwrap_err();
         assert!(Error::source(&err).is_none());
         assert_eq!(err.value.0, i);
     }
@@ -63,7 +63,7 @@ fn test_close() {
 #[test]
 fn test_senders_dropped() {
     let (sender, mut receiver) = channel::<Int>();
-    let senders = repeat(sender).take(10).collect::<Vec<_>>();
+    let senders = repeat_n(sender, 10).collect::<Vec<_>>();
     for i in 0..10 {
         senders[0].send(Int(i)).unwrap();
     }
@@ -88,15 +88,15 @@ fn test_senders_dropped() {
 #[test]
 fn test_receiver_dropped() {
     let (sender, receiver) = channel::<Int>();
-    let senders = repeat(sender).take(10).collect::<Vec<_>>();
+    let senders = repeat_n(sender, 10).collect::<Vec<_>>();
     for i in 0..10 {
         senders[0].send(Int(i)).unwrap();
     }
     drop(receiver);
 
-    for i in 0..10 {
-        assert!(senders[i].is_closed());
-        let err = senders[i].send(Int(i)).unwrap_err();
+    for (i, sender) in senders.iter().enumerate() {
+        assert!(sender.is_closed());
+        let err = sender.send(Int(i)).unwrap_err();
         assert!(Error::source(&err).is_none());
         assert_eq!(err.value.0, i);
     }
@@ -112,3 +112,154 @@ fn test_same_channel() {
     assert!(!first.same_channel(&second));
     assert!(!second.same_channel(&first));
 }
+
+#[test]
+fn test_into_std() {
+    let (sender, receiver) = channel::<Int>();
+    for i in 0..5 {
+        sender.send(Int(i)).unwrap();
+    }
+
+    let receiver = receiver.into_std();
+    assert!(sender.is_closed());
+    assert!(sender.send(Int(5)).is_err());
+
+    let values = std::thread::spawn(move || receiver.iter().map(|x| x.0).collect::<Vec<_>>())
+        .join()
+        .unwrap();
+    assert_eq!(values, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_send_with_ttl() {
+    let (sender, mut receiver) = channel::<Int>();
+    sender.send_with_ttl(Int(0), Duration::ZERO).unwrap();
+    sender
+        .send_with_ttl(Int(1), Duration::from_secs(3600))
+        .unwrap();
+    sender
+        .send_with_ttl(Int(2), Duration::from_millis(20))
+        .unwrap();
+    sender.send(Int(3)).unwrap();
+    sender.send_with_ttl(Int(4), Duration::MAX).unwrap();
+
+    assert_eq!(receiver.recv().unwrap().0, 1);
+    assert_eq!(receiver.expired_count(), 1);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(receiver.recv().unwrap().0, 3);
+    assert_eq!(receiver.expired_count(), 2);
+    assert_eq!(receiver.recv().unwrap().0, 4);
+
+    sender.send_with_ttl(Int(5), Duration::ZERO).unwrap();
+    assert!(matches!(receiver.recv(), Err(ReceiveError::Empty)));
+    sender.send_with_ttl(Int(6), Duration::ZERO).unwrap();
+    drop(sender);
+    assert!(matches!(receiver.recv(), Err(ReceiveError::Closed)));
+    assert_eq!(receiver.expired_count(), 4);
+}
+
+#[test]
+fn test_oneshot() {
+    let (sender, mut receiver) = oneshot::channel::<Int>();
+    assert!(matches!(receiver.recv(), Err(ReceiveError::Empty)));
+    sender.send(Int(1)).unwrap();
+    assert_eq!(receiver.recv().unwrap().0, 1);
+    assert!(matches!(receiver.recv(), Err(ReceiveError::Closed)));
+
+    let (sender, mut receiver) = oneshot::channel::<Int>();
+    drop(sender);
+    assert!(matches!(receiver.recv(), Err(ReceiveError::Closed)));
+
+    let (sender, mut receiver) = oneshot::channel::<Int>();
+    receiver.close();
+    assert!(sender.is_closed());
+    assert_eq!(sender.send(Int(2)).unwrap_err().value.0, 2);
+
+    let (sender, receiver) = oneshot::channel::<Int>();
+    assert!(!sender.is_closed());
+    drop(receiver);
+    assert!(sender.is_closed());
+    assert_eq!(sender.send(Int(3)).unwrap_err().value.0, 3);
+}
+
+#[test]
+fn test_rpc() {
+    let (sender, mut requests) = channel::<rpc::Request<Int, String>>();
+    let mut first = rpc::call(&sender, Int(1)).unwrap();
+    let mut second = rpc::call(&sender, Int(2)).unwrap();
+    let mut third = rpc::call(&sender, Int(3)).unwrap();
+    assert!(matches!(first.recv(), Err(ReceiveError::Empty)));
+
+    // Served out of order, the third one never.
+    let one = requests.recv().unwrap();
+    let two = requests.recv().unwrap();
+    let response = format!("got {}", two.request.0);
+    two.respond(response).unwrap();
+    assert!(matches!(first.recv(), Err(ReceiveError::Empty)));
+    assert_eq!(second.recv().unwrap(), "got 2");
+    let response = format!("got {}", one.request.0);
+    one.respond(response).unwrap();
+    assert_eq!(first.recv().unwrap(), "got 1");
+    drop(requests.recv().unwrap());
+    assert!(matches!(third.recv(), Err(ReceiveError::Closed)));
+
+    // The caller gave up on the response.
+    let fourth = rpc::call(&sender, Int(4)).unwrap();
+    drop(fourth);
+    let request = requests.recv().unwrap();
+    assert_eq!(
+        request.respond("late".to_string()).unwrap_err().value,
+        "late"
+    );
+
+    drop(requests);
+    assert!(matches!(
+        rpc::call(&sender, Int(5)),
+        Err(SendError { value: Int(5) })
+    ));
+}
+
+#[cfg(feature = "stream")]
+#[test]
+fn test_stream() {
+    use futures_core::Stream;
+    use std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let (sender, receiver) = channel::<Int>();
+    let mut stream = receiver.into_stream();
+    let counter = Arc::new(CountingWaker::default());
+    let waker = Waker::from(counter.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut poll = || match Pin::new(&mut stream).poll_next(&mut cx) {
+        Poll::Ready(value) => Poll::Ready(value.map(|x| x.0)),
+        Poll::Pending => Poll::Pending,
+    };
+
+    assert_eq!(poll(), Poll::Pending);
+    sender.send(Int(1)).unwrap();
+    sender.send(Int(2)).unwrap();
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert_eq!(poll(), Poll::Ready(Some(1)));
+    assert_eq!(poll(), Poll::Ready(Some(2)));
+    assert_eq!(poll(), Poll::Pending);
+
+    drop(sender);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+    assert_eq!(poll(), Poll::Ready(None));
+}
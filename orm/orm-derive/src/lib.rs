@@ -33,7 +33,7 @@ pub fn derive_object(input: TokenStream) -> TokenStream {
                 }
             }
 
-            fn as_table_row(&self) -> ::orm::storage::Row {
+            fn as_table_row(&self) -> ::orm::storage::Row<'_> {
                 vec![#((&self.#field).into()),*]
             }
 
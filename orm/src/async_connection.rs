@@ -0,0 +1,74 @@
+use crate::{Connection, Error, Result, Transaction};
+
+use std::{
+    panic,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A [`Connection`] that can be shared between async tasks.
+///
+/// Every operation runs on tokio's blocking thread pool, so the runtime's
+/// worker threads are never blocked on SQLite. Transactions are executed one
+/// at a time, in the order they acquire the connection.
+#[derive(Clone)]
+pub struct AsyncConnection {
+    inner: Arc<Mutex<Connection>>,
+}
+
+impl AsyncConnection {
+    pub async fn open_sqlite_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        spawn_blocking(move || Connection::open_sqlite_file(path))
+            .await
+            .map(Self::from)
+    }
+
+    pub async fn open_in_memory() -> Result<Self> {
+        spawn_blocking(Connection::open_in_memory)
+            .await
+            .map(Self::from)
+    }
+
+    /// Runs `f` against a fresh transaction.
+    ///
+    /// `f` has the usual [`Transaction`] semantics: changes are persisted only
+    /// if it calls [`Transaction::commit`], otherwise they are rolled back.
+    pub async fn transaction<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(Transaction<'_>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let inner = self.inner.clone();
+        spawn_blocking(move || {
+            let mut conn = inner.lock().unwrap_or_else(PoisonError::into_inner);
+            let tx = conn.new_transaction()?;
+            f(tx)
+        })
+        .await
+    }
+}
+
+impl From<Connection> for AsyncConnection {
+    fn from(conn: Connection) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(conn)),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+async fn spawn_blocking<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(err) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(Error::Storage(Box::new(err))),
+    }
+}
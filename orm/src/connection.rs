@@ -17,7 +17,7 @@ impl StorageConnection for rusqlite::Connection {
 ////////////////////////////////////////////////////////////////////////////////
 
 pub struct Connection {
-    inner: Box<dyn StorageConnection>,
+    inner: Box<dyn StorageConnection + Send>,
 }
 
 impl Connection {
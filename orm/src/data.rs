@@ -43,9 +43,9 @@ pub enum SqlType {
     TinyInt,
 }
 
-impl ToString for SqlType {
-    fn to_string(&self) -> String {
-        String::from(match self {
+impl fmt::Display for SqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
             SqlType::Text => "TEXT",
             SqlType::Blob => "BLOB",
             SqlType::BigInt => "BIGINT",
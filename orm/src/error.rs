@@ -14,8 +14,10 @@ pub enum Error {
     MissingColumn(Box<MissingColumnError>),
     #[error("database is locked")]
     LockConflict,
+    /// `Send + Sync`, so that errors can be returned from another thread,
+    /// see `AsyncConnection`.
     #[error("storage error: {0}")]
-    Storage(#[source] Box<dyn std::error::Error>),
+    Storage(#[source] Box<dyn std::error::Error + Send + Sync>),
 }
 
 impl From<rusqlite::Error> for Error {
@@ -1,17 +1,23 @@
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "async")]
+mod async_connection;
 mod connection;
 mod error;
+mod query;
 mod transaction;
 
 pub mod data;
 pub mod object;
 pub mod storage;
 
+#[cfg(feature = "async")]
+pub use async_connection::AsyncConnection;
 pub use connection::Connection;
 pub use data::ObjectId;
 pub use error::{Error, Result};
 pub use object::Object;
+pub use query::{Query, QueryIter};
 pub use transaction::{ObjectState, Transaction, Tx};
 
 pub use orm_derive::Object;
@@ -5,7 +5,7 @@ use std::any::Any;
 ////////////////////////////////////////////////////////////////////////////////
 
 pub trait Object: Any {
-    fn as_table_row(&self) -> Row;
+    fn as_table_row(&self) -> Row<'_>;
     fn from_table_row(row: Row) -> Self;
     fn schema() -> &'static Schema;
 }
@@ -31,14 +31,14 @@ pub struct Attribute {
 }
 
 pub trait Store {
-    fn as_table_row(&self) -> Row;
+    fn as_table_row(&self) -> Row<'_>;
     fn schema(&self) -> &'static Schema;
     fn as_any(&self) -> &dyn Any;
     fn as_mut_any(&mut self) -> &mut dyn Any;
 }
 
 impl<T: Object> Store for T {
-    fn as_table_row(&self) -> Row {
+    fn as_table_row(&self) -> Row<'_> {
         Object::as_table_row(self)
     }
 
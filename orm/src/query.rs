@@ -0,0 +1,64 @@
+use crate::{
+    error::*,
+    object::Object,
+    storage::{RowCursor, StorageQuery},
+    transaction::Tx,
+    Transaction,
+};
+
+use std::marker::PhantomData;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A prepared query over all stored objects of type `T`, see
+/// [`Transaction::query`].
+pub struct Query<'t, T> {
+    tx: &'t Transaction<'t>,
+    inner: Box<dyn StorageQuery + 't>,
+    object_type: PhantomData<T>,
+}
+
+impl<'t, T: Object> Query<'t, T> {
+    pub(crate) fn new(tx: &'t Transaction<'t>, inner: Box<dyn StorageQuery + 't>) -> Self {
+        Self {
+            tx,
+            inner,
+            object_type: PhantomData,
+        }
+    }
+
+    /// Runs the query, fetching the objects lazily in id order.
+    ///
+    /// Objects already loaded by this transaction are taken from its cache,
+    /// so they reflect the pending changes; deleted ones are skipped. The
+    /// others are only kept in memory while in use or once changed.
+    pub fn iter(&mut self) -> Result<QueryIter<'_, 't, T>> {
+        Ok(QueryIter {
+            tx: self.tx,
+            rows: self.inner.rows()?,
+            object_type: PhantomData,
+        })
+    }
+}
+
+pub struct QueryIter<'q, 't, T> {
+    tx: &'t Transaction<'t>,
+    rows: RowCursor<'q>,
+    object_type: PhantomData<T>,
+}
+
+impl<'q, 't, T: Object> Iterator for QueryIter<'q, 't, T> {
+    type Item = Result<Tx<'t, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (id, row) = match self.rows.next()? {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(obj) = self.tx.load(id, row) {
+                return Some(Ok(obj));
+            }
+        }
+    }
+}
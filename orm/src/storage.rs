@@ -14,6 +14,55 @@ use std::{borrow::Cow, fmt::Write};
 pub type Row<'a> = Vec<Value<'a>>;
 pub type RowSlice<'a> = [Value<'a>];
 
+pub(crate) type RowCursor<'a> = Box<dyn Iterator<Item = Result<(ObjectId, Row<'static>)>> + 'a>;
+
+/// A prepared `SELECT` over a whole table.
+pub(crate) trait StorageQuery {
+    fn rows(&mut self) -> Result<RowCursor<'_>>;
+}
+
+struct SqliteQuery<'a> {
+    statement: rusqlite::Statement<'a>,
+    schema: &'static Schema,
+}
+
+impl StorageQuery for SqliteQuery<'_> {
+    fn rows(&mut self) -> Result<RowCursor<'_>> {
+        let schema = self.schema;
+        let rows = self
+            .statement
+            .query_map([], move |sqlite_row| {
+                let id = sqlite_row.get::<_, i64>(schema.attrs.len())?;
+                Ok((id.into(), read_row(sqlite_row, schema)?))
+            })
+            .map_col_err(schema)?;
+        Ok(Box::new(rows.map(move |row| row.map_col_err(schema))))
+    }
+}
+
+fn column_list(schema: &Schema) -> String {
+    schema
+        .attrs
+        .iter()
+        .map(|attr| attr.col_name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn read_row(sqlite_row: &rusqlite::Row, schema: &Schema) -> rusqlite::Result<Row<'static>> {
+    let mut row = Row::with_capacity(schema.attrs.len());
+    for (i, attr) in schema.attrs.iter().enumerate() {
+        row.push(match attr.data_type {
+            DataType::String => Value::String(Cow::Owned(sqlite_row.get(i)?)),
+            DataType::Bytes => Value::Bytes(Cow::Owned(sqlite_row.get(i)?)),
+            DataType::Int64 => Value::Int64(sqlite_row.get(i)?),
+            DataType::Float64 => Value::Float64(sqlite_row.get(i)?),
+            DataType::Bool => Value::Bool(sqlite_row.get::<_, i64>(i)? > 0),
+        });
+    }
+    Ok(row)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub(crate) trait StorageTransaction {
@@ -23,6 +72,7 @@ pub(crate) trait StorageTransaction {
     fn insert_row(&self, schema: &Schema, row: &RowSlice) -> Result<ObjectId>;
     fn update_row(&self, id: ObjectId, schema: &Schema, row: &RowSlice) -> Result<()>;
     fn select_row(&self, id: ObjectId, schema: &Schema) -> Result<Row<'static>>;
+    fn select_all(&self, schema: &'static Schema) -> Result<Box<dyn StorageQuery + '_>>;
     fn delete_row(&self, id: ObjectId, schema: &Schema) -> Result<()>;
 
     fn commit(&self) -> Result<()>;
@@ -49,7 +99,7 @@ impl<'a> StorageTransaction for rusqlite::Transaction<'a> {
                 query,
                 ", {} {}",
                 attr.col_name,
-                attr.data_type.to_sql_type().to_string()
+                attr.data_type.to_sql_type()
             )
             .unwrap();
         });
@@ -75,8 +125,7 @@ impl<'a> StorageTransaction for rusqlite::Transaction<'a> {
                     .map(|a| a.col_name)
                     .collect::<Vec<_>>()
                     .join(","),
-                std::iter::repeat("?")
-                    .take(row.len())
+                std::iter::repeat_n("?", row.len())
                     .collect::<Vec<_>>()
                     .join(","),
             );
@@ -116,36 +165,35 @@ impl<'a> StorageTransaction for rusqlite::Transaction<'a> {
     fn select_row(&self, id: ObjectId, schema: &Schema) -> Result<Row<'static>> {
         let mut query = "SELECT ".to_string();
 
-        if let Some(attr) = schema.attrs.first() {
-            write!(query, "{}", attr.col_name).unwrap();
-            schema.attrs.iter().skip(1).for_each(|attr| {
-                write!(query, ", {}", attr.col_name).unwrap();
-            });
-        } else {
+        if schema.attrs.is_empty() {
             query.push('1');
+        } else {
+            query.push_str(&column_list(schema));
         }
 
         write!(query, " FROM \"{}\" WHERE id = ?", schema.table_name).unwrap();
 
         (move || {
             self.prepare(&query)?
-                .query_row([i64::from(id)], |sqlite_row| {
-                    let mut row = Row::with_capacity(schema.attrs.len());
-                    for (i, attr) in schema.attrs.iter().enumerate() {
-                        row.push(match attr.data_type {
-                            DataType::String => Value::String(Cow::Owned(sqlite_row.get(i)?)),
-                            DataType::Bytes => Value::Bytes(Cow::Owned(sqlite_row.get(i)?)),
-                            DataType::Int64 => Value::Int64(sqlite_row.get(i)?),
-                            DataType::Float64 => Value::Float64(sqlite_row.get(i)?),
-                            DataType::Bool => Value::Bool(sqlite_row.get::<_, i64>(i)? > 0),
-                        });
-                    }
-                    Ok(row)
-                })
+                .query_row([i64::from(id)], |sqlite_row| read_row(sqlite_row, schema))
         })()
         .map_table_err(schema, id)
     }
 
+    fn select_all(&self, schema: &'static Schema) -> Result<Box<dyn StorageQuery + '_>> {
+        // `id` goes last so that column indices in errors match `schema.attrs`
+        let mut query = "SELECT ".to_string();
+        for attr in schema.attrs {
+            write!(query, "{}, ", attr.col_name).unwrap();
+        }
+        write!(query, "id FROM \"{}\" ORDER BY id", schema.table_name).unwrap();
+
+        Ok(Box::new(SqliteQuery {
+            statement: self.prepare(&query).map_col_err(schema)?,
+            schema,
+        }))
+    }
+
     fn delete_row(&self, id: ObjectId, schema: &Schema) -> Result<()> {
         self.execute(
             &format!("DELETE FROM {} WHERE id = ?", schema.table_name),
@@ -2,27 +2,28 @@ use crate::{
     data::ObjectId,
     error::*,
     object::{Object, Store},
-    storage::StorageTransaction,
+    query::Query,
+    storage::{Row, StorageTransaction},
 };
 use std::{
     any::{Any, TypeId},
     cell::{Cell, Ref, RefCell, RefMut},
     collections::HashMap,
     marker::PhantomData,
-    rc::Rc,
+    rc::{Rc, Weak},
 };
 
 ////////////////////////////////////////////////////////////////////////////////
 pub struct Transaction<'a> {
     inner: Box<dyn StorageTransaction + 'a>,
-    cache: RefCell<Cache>,
+    cache: Rc<RefCell<Cache>>,
 }
 
 impl<'a> Transaction<'a> {
     pub(crate) fn new(inner: Box<dyn StorageTransaction + 'a>) -> Self {
         Self {
             inner,
-            cache: RefCell::default(),
+            cache: Rc::default(),
         }
     }
 
@@ -33,6 +34,7 @@ impl<'a> Transaction<'a> {
             id: self.inner.insert_row(T::schema(), &obj.as_table_row())?,
             state: Cell::new(ObjectState::Clean),
             obj: RefCell::new(Box::new(obj)),
+            cache: Weak::new(),
         });
         self.cache.borrow_mut().insert(node.clone());
 
@@ -65,6 +67,7 @@ impl<'a> Transaction<'a> {
             obj: RefCell::new(Box::new(T::from_table_row(
                 self.inner.select_row(id, T::schema())?,
             ))),
+            cache: Weak::new(),
         });
         self.cache.borrow_mut().insert(node.clone());
 
@@ -74,6 +77,48 @@ impl<'a> Transaction<'a> {
         })
     }
 
+    /// Prepares a query over all stored objects of type `T`.
+    pub fn query<T: Object>(&self) -> Result<Query<'_, T>> {
+        self.create_if_not_exists::<T>()?;
+        Ok(Query::new(self, self.inner.select_all(T::schema())?))
+    }
+
+    /// Returns the cached object with the given id, or one built from `row`.
+    /// `None` if the object has been deleted in this transaction.
+    ///
+    /// Objects built from `row` are only cached while some `Tx` refers to
+    /// them or once they are changed, so that a long scan doesn't keep every
+    /// row in memory.
+    pub(crate) fn load<T: Object>(&self, id: ObjectId, row: Row<'static>) -> Option<Tx<'_, T>> {
+        let cached = self.cache.borrow().get::<T>(id);
+        let node = match cached {
+            Some(node) => match node.state.get() {
+                ObjectState::Removed => return None,
+                _ => node,
+            },
+            None => {
+                let node = Rc::new(ObjectNode {
+                    id,
+                    state: Cell::new(ObjectState::Clean),
+                    obj: RefCell::new(Box::new(T::from_table_row(row))),
+                    cache: Rc::downgrade(&self.cache),
+                });
+                self.cache.borrow_mut().insert_unpinned(&node);
+                node
+            }
+        };
+
+        Some(Tx {
+            lifetime: PhantomData,
+            node,
+        })
+    }
+
+    /// Number of objects the transaction currently holds in memory.
+    pub fn cached_object_count(&self) -> usize {
+        self.cache.borrow().len()
+    }
+
     fn create_if_not_exists<T: Object>(&self) -> Result<()> {
         if !self.inner.table_exists(T::schema().table_name)? {
             self.inner.create_table(T::schema())?;
@@ -143,7 +188,10 @@ impl<'a, T: Any> Tx<'a, T> {
 
     pub fn borrow_mut(&self) -> RefMut<'_, T> {
         match self.state() {
-            ObjectState::Clean => self.node.state.set(ObjectState::Modified),
+            ObjectState::Clean => {
+                self.node.state.set(ObjectState::Modified);
+                ObjectNode::pin(&self.node);
+            }
             ObjectState::Modified => (),
             ObjectState::Removed => panic!("cannot borrow a removed object"),
         }
@@ -158,6 +206,7 @@ impl<'a, T: Any> Tx<'a, T> {
             panic!("cannot delete a borrowed object");
         }
         self.node.state.set(ObjectState::Removed);
+        ObjectNode::pin(&self.node);
     }
 }
 
@@ -165,22 +214,78 @@ struct ObjectNode {
     obj: RefCell<Box<dyn Store>>,
     id: ObjectId,
     state: Cell<ObjectState>,
+    /// The cache holding the node unpinned, see `Transaction::load`.
+    cache: Weak<RefCell<Cache>>,
+}
+
+impl ObjectNode {
+    /// Makes the cache keep an unpinned node alive, for its changes to be
+    /// committed.
+    fn pin(node: &Rc<Self>) {
+        if let Some(cache) = node.cache.upgrade() {
+            cache.borrow_mut().insert(node.clone());
+        }
+    }
+}
+
+impl Drop for ObjectNode {
+    fn drop(&mut self) {
+        if let Some(cache) = self.cache.upgrade() {
+            if let Ok(mut cache) = cache.try_borrow_mut() {
+                cache.evict(self.obj.get_mut().as_any().type_id(), self.id);
+            }
+        }
+    }
+}
+
+enum CacheEntry {
+    Pinned(Rc<ObjectNode>),
+    Unpinned(Weak<ObjectNode>),
 }
 
 #[derive(Default)]
-struct Cache(HashMap<(TypeId, ObjectId), Rc<ObjectNode>>);
+struct Cache(HashMap<(TypeId, ObjectId), CacheEntry>);
 
 impl Cache {
     pub fn insert(&mut self, node: Rc<ObjectNode>) {
         let type_id = node.obj.borrow().as_any().type_id();
-        self.0.insert((type_id, node.id), node);
+        self.0.insert((type_id, node.id), CacheEntry::Pinned(node));
+    }
+
+    pub fn insert_unpinned(&mut self, node: &Rc<ObjectNode>) {
+        let type_id = node.obj.borrow().as_any().type_id();
+        self.0.insert(
+            (type_id, node.id),
+            CacheEntry::Unpinned(Rc::downgrade(node)),
+        );
     }
 
     pub fn get<T: Any>(&self, obj_id: ObjectId) -> Option<Rc<ObjectNode>> {
-        self.0.get(&(TypeId::of::<T>(), obj_id)).cloned()
+        match self.0.get(&(TypeId::of::<T>(), obj_id))? {
+            CacheEntry::Pinned(node) => Some(node.clone()),
+            CacheEntry::Unpinned(node) => node.upgrade(),
+        }
+    }
+
+    /// Drops the entry of a dropped unpinned node.
+    fn evict(&mut self, type_id: TypeId, obj_id: ObjectId) {
+        let key = (type_id, obj_id);
+        if let Some(CacheEntry::Unpinned(node)) = self.0.get(&key) {
+            if node.strong_count() == 0 {
+                self.0.remove(&key);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
     }
 
+    /// The pinned nodes, the only ones that may have changes.
     pub fn iter_nodes(&self) -> impl Iterator<Item = &Rc<ObjectNode>> {
-        self.0.values()
+        self.0.values().filter_map(|entry| match entry {
+            CacheEntry::Pinned(node) => Some(node),
+            CacheEntry::Unpinned(_) => None,
+        })
     }
 }
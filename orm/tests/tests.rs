@@ -632,6 +632,79 @@ fn test_missing_column_renamed() {
     }
 }
 
+#[test]
+fn test_query_iter() {
+    #[derive(Object)]
+    struct Counter {
+        value: i64,
+    }
+
+    let mut conn = Connection::open_in_memory().unwrap();
+
+    let tx = conn.new_transaction().unwrap();
+    assert_eq!(tx.query::<Counter>().unwrap().iter().unwrap().count(), 0);
+    let ids = (0..1000)
+        .map(|value| tx.create(Counter { value }).unwrap().id())
+        .collect::<Vec<_>>();
+    tx.commit().unwrap();
+
+    let tx = conn.new_transaction().unwrap();
+    let modified = tx.get::<Counter>(ids[1]).unwrap();
+    modified.borrow_mut().value = -1;
+    tx.get::<Counter>(ids[2]).unwrap().delete();
+
+    let mut query = tx.query::<Counter>().unwrap();
+    let mut objects = query.iter().unwrap();
+    let first = objects.next().unwrap().unwrap();
+    assert_eq!(first.id(), ids[0]);
+    assert!(matches!(first.state(), ObjectState::Clean));
+    let second = objects.next().unwrap().unwrap();
+    assert_eq!(second.borrow().value, -1);
+    assert!(matches!(second.state(), ObjectState::Modified));
+
+    let sum = objects.map(|obj| obj.unwrap().borrow().value).sum::<i64>();
+    assert_eq!(sum, (3..1000).sum::<i64>());
+
+    assert_eq!(query.iter().unwrap().count(), 999);
+    drop(query);
+    drop(modified);
+    drop(first);
+    drop(second);
+    tx.commit().unwrap();
+
+    let tx = conn.new_transaction().unwrap();
+    let values = tx
+        .query::<Counter>()
+        .unwrap()
+        .iter()
+        .unwrap()
+        .take(3)
+        .map(|obj| obj.unwrap().borrow().value)
+        .collect::<Vec<_>>();
+    assert_eq!(values, [0, -1, 3]);
+    drop(tx);
+
+    // Rows no handle refers to are let go of along the way.
+    let tx = conn.new_transaction().unwrap();
+    let kept = tx.get::<Counter>(ids[0]).unwrap();
+    let mut query = tx.query::<Counter>().unwrap();
+    let mut max_cached = 0;
+    for obj in query.iter().unwrap() {
+        let obj = obj.unwrap();
+        max_cached = max_cached.max(tx.cached_object_count());
+        if obj.id() == ids[500] {
+            obj.borrow_mut().value = 5000;
+        }
+    }
+    assert!(max_cached <= 3, "{max_cached}");
+    assert_eq!(tx.cached_object_count(), 2);
+    drop((query, kept));
+    tx.commit().unwrap();
+
+    let tx = conn.new_transaction().unwrap();
+    assert_eq!(tx.get::<Counter>(ids[500]).unwrap().borrow().value, 5000);
+}
+
 #[cfg(feature = "test_lifetimes_create")]
 #[test]
 fn test_lifetimes_create() {
@@ -659,3 +732,67 @@ fn test_lifetimes_create() {
 
     eprintln!("is_tall: {}", order.borrow().is_tall);
 }
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async() {
+    let conn = orm::AsyncConnection::open_in_memory().await.unwrap();
+
+    let user_id = conn
+        .transaction(|tx| {
+            let id = tx
+                .create(User {
+                    name: "Alice".into(),
+                    picture: b"fwe98vhwe"[..].into(),
+                    visits: 12,
+                    balance: 50.,
+                    is_admin: false,
+                })?
+                .id();
+            tx.commit()?;
+            Ok(id)
+        })
+        .await
+        .unwrap();
+
+    let handles = (0..4)
+        .map(|_| {
+            let conn = conn.clone();
+            tokio::spawn(async move {
+                conn.transaction(move |tx| {
+                    tx.get::<User>(user_id)?.borrow_mut().visits += 1;
+                    tx.commit()
+                })
+                .await
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.await.unwrap().unwrap();
+    }
+
+    conn.transaction(move |tx| {
+        tx.get::<User>(user_id)?.borrow_mut().balance = 0.;
+        tx.rollback()
+    })
+    .await
+    .unwrap();
+
+    let user = conn
+        .transaction(move |tx| {
+            let user = tx.get::<User>(user_id)?.borrow().clone();
+            Ok(user)
+        })
+        .await
+        .unwrap();
+    assert_eq!(user.visits, 16);
+    assert_eq!(user.balance, 50.);
+}
+
+#[test]
+fn test_send() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send::<orm::Connection>();
+    assert_send_sync::<orm::Error>();
+}
@@ -0,0 +1,147 @@
+#[cfg(feature = "tar")]
+use std::{ffi::OsString, io, path::PathBuf};
+use std::{io::Read, path::Path, sync::mpsc::Sender};
+
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+
+#[cfg(feature = "tar")]
+use crate::send_error;
+use crate::{Event, FileFilter, Search, Stopped};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The kinds of files searched through when `search_archives` is set, told
+/// apart by their extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ArchiveKind {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "tar")]
+    Tar,
+    #[cfg(all(feature = "gzip", feature = "tar"))]
+    TarGzip,
+}
+
+impl ArchiveKind {
+    pub(crate) fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+        #[cfg(all(feature = "gzip", feature = "tar"))]
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Some(Self::TarGzip);
+        }
+        #[cfg(feature = "tar")]
+        if name.ends_with(".tar") {
+            return Some(Self::Tar);
+        }
+        #[cfg(feature = "gzip")]
+        if name.ends_with(".gz") {
+            return Some(Self::Gzip);
+        }
+        None
+    }
+
+    /// Whether `filter` lets an archive named `name` through. A compressed
+    /// file is matched by its name both with and without `.gz`, while a tar
+    /// archive only has to pass the exclude globs, as its members are
+    /// filtered on their own.
+    pub(crate) fn accepts(self, filter: &FileFilter, name: &str) -> bool {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let stem = name.strip_suffix(".gz").unwrap_or(name);
+                (filter.included(name) || filter.included(stem))
+                    && !filter.excluded(name)
+                    && !filter.excluded(stem)
+            }
+            #[cfg(feature = "tar")]
+            Self::Tar => !filter.excluded(name),
+            #[cfg(all(feature = "gzip", feature = "tar"))]
+            Self::TarGzip => !filter.excluded(name),
+        }
+    }
+}
+
+impl Search {
+    pub(crate) fn search_archive(
+        &self,
+        path: &Path,
+        kind: ArchiveKind,
+        reader: Box<dyn Read>,
+        sender: &Sender<Event>,
+    ) -> Result<(), Stopped> {
+        match kind {
+            #[cfg(feature = "gzip")]
+            ArchiveKind::Gzip => self.search_reader(path, MultiGzDecoder::new(reader), sender),
+            #[cfg(feature = "tar")]
+            ArchiveKind::Tar => self.search_tar(path, reader, sender),
+            #[cfg(all(feature = "gzip", feature = "tar"))]
+            ArchiveKind::TarGzip => self.search_tar(path, MultiGzDecoder::new(reader), sender),
+        }
+    }
+
+    /// Searches the regular files of the archive that pass the filter. Each
+    /// member is read into memory before being searched.
+    #[cfg(feature = "tar")]
+    fn search_tar(
+        &self,
+        path: &Path,
+        reader: impl Read,
+        sender: &Sender<Event>,
+    ) -> Result<(), Stopped> {
+        let mut archive = tar::Archive::new(reader);
+        let entries = match archive.entries() {
+            Ok(entries) => entries,
+            Err(error) => return send_error(sender, path, error),
+        };
+        for entry in entries {
+            self.check_cancelled()?;
+            // The archive can't be read past a broken header.
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => return send_error(sender, path, error),
+            };
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let (member, name) = match entry.path() {
+                Ok(member) => (
+                    member_path(path, &member),
+                    member
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+                Err(error) => {
+                    send_error(sender, path, error)?;
+                    continue;
+                }
+            };
+            let too_large = self
+                .filter
+                .max_file_size
+                .is_some_and(|max_size| entry.size() > max_size);
+            if !self.filter.accepts_name(&name) || too_large {
+                continue;
+            }
+
+            let mut data = Vec::new();
+            if let Err(error) = entry.read_to_end(&mut data) {
+                return send_error(sender, &member, error);
+            }
+            self.search_reader(&member, io::Cursor::new(data), sender)?;
+        }
+        Ok(())
+    }
+}
+
+/// `archive!member`, the path matches inside archive members are reported
+/// with.
+#[cfg(feature = "tar")]
+fn member_path(archive: &Path, member: &Path) -> PathBuf {
+    let mut path = OsString::from(archive);
+    path.push("!");
+    path.push(member);
+    path.into()
+}
@@ -1,21 +1,39 @@
 #![forbid(unsafe_code)]
 
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader},
+    collections::HashSet,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    ops::Range,
     path::{Path, PathBuf},
-    sync::mpsc::{self, Sender},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, SendError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
+use glob::{Pattern, PatternError};
 use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+#[cfg(any(feature = "gzip", feature = "tar"))]
+mod archive;
 
 ////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Match {
     pub path: PathBuf,
+    /// The matching line, or all lines spanned by a multiline match joined
+    /// with `\n`.
     pub line: String,
     pub line_number: usize,
+    /// Last line of the match; equals `line_number` outside multiline mode.
+    pub end_line_number: usize,
 }
 
 #[derive(Debug)]
@@ -29,60 +47,771 @@ pub enum Event {
     Error(Error),
 }
 
+/// What to search and how. Built from `SearchOptions::new()` with the
+/// `with_*` methods, e.g.
+/// `SearchOptions::new().with_regex(true).with_include(["*.rs"])`.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct SearchOptions {
+    pub case_insensitive: bool,
+    /// Only match the pattern at word boundaries.
+    pub whole_word: bool,
+    /// Report the lines that do *not* match.
+    pub invert_match: bool,
+    /// Treat the pattern as a regular expression rather than a plain string.
+    pub regex: bool,
+    /// Stop searching a file after this many reported lines.
+    pub max_matches_per_file: Option<usize>,
+    /// Only search files whose name matches one of these globs, e.g. `*.rs`.
+    pub include: Vec<String>,
+    /// Skip files whose name matches one of these globs.
+    pub exclude: Vec<String>,
+    /// Skip files larger than this many bytes.
+    pub max_file_size: Option<u64>,
+    /// Search the whole file at once so that matches may span several lines.
+    /// Not compatible with `invert_match`.
+    pub multiline: bool,
+    /// Descend into symlinked directories. A directory reachable through
+    /// several links is searched once; links back to an enclosing directory
+    /// are reported as errors.
+    pub follow_symlinks: bool,
+    /// Do not search symlinks pointing to files.
+    pub skip_symlinked_files: bool,
+    /// Decode files starting with a UTF-16 byte order mark instead of
+    /// treating them as UTF-8.
+    pub detect_utf16: bool,
+    /// Search inside `.gz` files (with the `gzip` feature) and the members
+    /// of `.tar`, `.tar.gz` and `.tgz` archives (with the `tar` feature).
+    /// Members are reported as `archive_path!member_path`.
+    #[cfg(any(feature = "gzip", feature = "tar"))]
+    pub search_archives: bool,
+    /// Setting this flag aborts the search as soon as possible.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Periodic progress reports, see [`ProgressCallback`].
+    pub progress: Option<ProgressCallback>,
+}
+
+impl SearchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn with_whole_word(mut self, whole_word: bool) -> Self {
+        self.whole_word = whole_word;
+        self
+    }
+
+    pub fn with_invert_match(mut self, invert_match: bool) -> Self {
+        self.invert_match = invert_match;
+        self
+    }
+
+    pub fn with_regex(mut self, regex: bool) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    pub fn with_max_matches_per_file(mut self, max_matches: usize) -> Self {
+        self.max_matches_per_file = Some(max_matches);
+        self
+    }
+
+    pub fn with_include(mut self, globs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.include = globs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_exclude(mut self, globs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.exclude = globs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_max_file_size(mut self, max_size: u64) -> Self {
+        self.max_file_size = Some(max_size);
+        self
+    }
+
+    pub fn with_multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    pub fn with_skip_symlinked_files(mut self, skip_symlinked_files: bool) -> Self {
+        self.skip_symlinked_files = skip_symlinked_files;
+        self
+    }
+
+    pub fn with_detect_utf16(mut self, detect_utf16: bool) -> Self {
+        self.detect_utf16 = detect_utf16;
+        self
+    }
+
+    #[cfg(any(feature = "gzip", feature = "tar"))]
+    pub fn with_search_archives(mut self, search_archives: bool) -> Self {
+        self.search_archives = search_archives;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressCallback) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+}
+
+/// Snapshot of a running search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub files_scanned: u64,
+    pub bytes_read: u64,
+    pub matches: u64,
+    /// Directory of the file most recently picked up by a worker. Files are
+    /// handed out directory by directory, so the workers mostly share it.
+    pub current_dir: Option<PathBuf>,
+    /// Set on the last report, sent once the search is over.
+    pub finished: bool,
+}
+
+/// Called from the worker pool with a [`Progress`] at most once per
+/// `interval`, and once more when the search is over. Calls never overlap.
+#[derive(Clone)]
+pub struct ProgressCallback {
+    interval: Duration,
+    callback: Arc<dyn Fn(&Progress) + Send + Sync>,
+}
+
+impl ProgressCallback {
+    pub fn new(interval: Duration, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Self {
+            interval,
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressCallback")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How many lines are scanned between two checks of the cancellation flag.
+const CANCEL_CHECK_INTERVAL: usize = 1024;
+
 pub fn run<P: AsRef<Path>>(path: P, pattern: &str) -> Vec<Event> {
-    let path = path.as_ref();
+    run_with_options(path, pattern, &SearchOptions::default())
+}
+
+pub fn run_with_options<P: AsRef<Path>>(
+    path: P,
+    pattern: &str,
+    options: &SearchOptions,
+) -> Vec<Event> {
+    run_streaming_with_options(path, pattern, options).collect()
+}
+
+pub fn run_streaming<P: AsRef<Path>>(path: P, pattern: &str) -> Events {
+    run_streaming_with_options(path, pattern, &SearchOptions::default())
+}
+
+/// Starts the search in the background and yields events as they are found.
+/// Dropping the returned iterator cancels the remaining work.
+pub fn run_streaming_with_options<P: AsRef<Path>>(
+    path: P,
+    pattern: &str,
+    options: &SearchOptions,
+) -> Events {
+    let path = path.as_ref().to_path_buf();
     let (sender, receiver) = mpsc::channel();
 
-    if path.is_file() {
-        process_file(path, pattern, sender.clone());
-    } else if path.is_dir() {
-        get_files_in_directory(path)
-            .par_iter()
-            .for_each(|file| process_file(file, pattern, sender.clone()));
-    } else {
-        return vec![Event::Error(Error {
-            path: path.to_path_buf(),
-            error: io::Error::new(io::ErrorKind::Other, "Invalid path"),
-        })];
-    }
-
-    drop(sender);
-    receiver.iter().collect::<Vec<_>>()
-}
-
-fn process_file<P: AsRef<Path>>(file_path: P, pattern: &str, sender: Sender<Event>) {
-    let file = File::open(&file_path).unwrap();
-    let reader = BufReader::new(file);
-
-    for (line_number, line) in reader.lines().enumerate() {
-        if let Ok(line) = line {
-            if line.contains(pattern) {
-                sender
-                    .send(Event::Match(Match {
-                        path: file_path.as_ref().to_path_buf(),
+    let search = match Search::new(pattern, options) {
+        Ok(search) => search,
+        Err(error) => {
+            let _ = send_error(&sender, &path, error);
+            return Events { receiver };
+        }
+    };
+
+    thread::spawn(move || {
+        if path.is_file() {
+            let _ = search.process_file(&path, &sender);
+        } else if path.is_dir() {
+            if let Ok(files) = search.get_files_in_directory(&path, &sender) {
+                // Bridging hands the files out in walk order, so that the
+                // workers finish a directory before moving on to the next one.
+                let _ = files
+                    .iter()
+                    .par_bridge()
+                    .try_for_each_with(sender.clone(), |sender, file| {
+                        search.process_file(file, sender)
+                    });
+            }
+        } else {
+            let _ = send_error(&sender, &path, io::Error::other("Invalid path"));
+        }
+        if let Some(progress) = &search.progress {
+            progress.report(true);
+        }
+        // Ends the events only after the final report.
+        drop(sender);
+    });
+
+    Events { receiver }
+}
+
+/// Iterator over the events of a running search.
+pub struct Events {
+    receiver: Receiver<Event>,
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn new(pattern: &str, options: &SearchOptions) -> Result<Self, regex::Error> {
+        if !options.regex && !options.case_insensitive && !options.whole_word {
+            return Ok(Self::Substring(pattern.to_string()));
+        }
+
+        let mut pattern = if options.regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{pattern})\b");
+        }
+        RegexBuilder::new(&pattern)
+            .case_insensitive(options.case_insensitive)
+            .multi_line(options.multiline)
+            .build()
+            .map(Self::Regex)
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Self::Substring(pattern) => line.contains(pattern.as_str()),
+            Self::Regex(regex) => regex.is_match(line),
+        }
+    }
+
+    /// Byte ranges of all non-overlapping matches in `text`.
+    fn find_iter<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        match self {
+            Self::Substring(pattern) => Box::new(
+                text.match_indices(pattern.as_str())
+                    .map(|(start, found)| start..start + found.len()),
+            ),
+            Self::Regex(regex) => Box::new(regex.find_iter(text).map(|found| found.range())),
+        }
+    }
+}
+
+struct Search {
+    matcher: Matcher,
+    filter: FileFilter,
+    options: SearchOptions,
+    progress: Option<ProgressTracker>,
+}
+
+impl Search {
+    fn new(pattern: &str, options: &SearchOptions) -> io::Result<Self> {
+        if options.multiline && options.invert_match {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invert_match is not supported in multiline mode",
+            ));
+        }
+        Ok(Self {
+            matcher: Matcher::new(pattern, options)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+            filter: FileFilter::new(options)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+            options: options.clone(),
+            progress: options.progress.clone().map(ProgressTracker::new),
+        })
+    }
+
+    fn check_cancelled(&self) -> Result<(), Stopped> {
+        match &self.options.cancel {
+            Some(cancel) if cancel.load(Ordering::Relaxed) => Err(Stopped),
+            _ => Ok(()),
+        }
+    }
+
+    fn process_file(&self, file_path: &Path, sender: &Sender<Event>) -> Result<(), Stopped> {
+        self.check_cancelled()?;
+        if !self.filter.accepts(file_path) {
+            return Ok(());
+        }
+        if let Some(progress) = &self.progress {
+            progress.start_file(file_path);
+        }
+
+        let res = self.search_file(file_path, sender);
+        if let Some(progress) = &self.progress {
+            progress.counters.files.fetch_add(1, Ordering::Relaxed);
+            progress.tick();
+        }
+        res
+    }
+
+    fn search_file(&self, file_path: &Path, sender: &Sender<Event>) -> Result<(), Stopped> {
+        let file = match File::open(file_path) {
+            Ok(file) => file,
+            Err(error) => return send_error(sender, file_path, error),
+        };
+        let reader: Box<dyn Read> = match &self.progress {
+            Some(progress) => Box::new(CountingReader {
+                inner: file,
+                counters: progress.counters.clone(),
+            }),
+            None => Box::new(file),
+        };
+        #[cfg(any(feature = "gzip", feature = "tar"))]
+        if self.options.search_archives {
+            if let Some(kind) = archive::ArchiveKind::of(file_path) {
+                return self.search_archive(file_path, kind, reader, sender);
+            }
+        }
+        self.search_reader(file_path, reader, sender)
+    }
+
+    /// Searches `reader`, reporting its matches and errors under `file_path`.
+    fn search_reader(
+        &self,
+        file_path: &Path,
+        reader: impl Read + 'static,
+        sender: &Sender<Event>,
+    ) -> Result<(), Stopped> {
+        let lines = match read_lines(reader, self.options.detect_utf16) {
+            Ok(lines) => lines,
+            Err(error) => return send_error(sender, file_path, error),
+        };
+        if self.options.multiline {
+            self.search_multiline(file_path, lines, sender)
+        } else {
+            self.search_lines(file_path, lines, sender)
+        }
+    }
+
+    fn report_match(&self, sender: &Sender<Event>, found: Match) -> Result<(), Stopped> {
+        if let Some(progress) = &self.progress {
+            progress.counters.matches.fetch_add(1, Ordering::Relaxed);
+        }
+        sender.send(Event::Match(found))?;
+        Ok(())
+    }
+
+    fn search_lines(
+        &self,
+        file_path: &Path,
+        lines: Lines,
+        sender: &Sender<Event>,
+    ) -> Result<(), Stopped> {
+        let max_matches = self.options.max_matches_per_file.unwrap_or(usize::MAX);
+
+        let mut matches = 0;
+        for (line_number, line) in lines.enumerate() {
+            if matches == max_matches {
+                break;
+            }
+            if line_number % CANCEL_CHECK_INTERVAL == 0 {
+                self.check_cancelled()?;
+                if let Some(progress) = &self.progress {
+                    progress.tick();
+                }
+            }
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => return send_error(sender, file_path, error),
+            };
+            if self.matcher.is_match(&line) != self.options.invert_match {
+                matches += 1;
+                self.report_match(
+                    sender,
+                    Match {
+                        path: file_path.to_path_buf(),
                         line,
                         line_number: line_number + 1,
-                    }))
-                    .unwrap();
+                        end_line_number: line_number + 1,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn search_multiline(
+        &self,
+        file_path: &Path,
+        lines: Lines,
+        sender: &Sender<Event>,
+    ) -> Result<(), Stopped> {
+        let lines = match lines.collect::<io::Result<Vec<_>>>() {
+            Ok(lines) => lines,
+            Err(error) => return send_error(sender, file_path, error),
+        };
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let text = lines.join("\n");
+        let mut line_starts = vec![0];
+        line_starts.extend(lines.iter().scan(0, |offset, line| {
+            *offset += line.len() + 1;
+            Some(*offset)
+        }));
+        let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+
+        let max_matches = self.options.max_matches_per_file.unwrap_or(usize::MAX);
+        let mut matches = 0;
+        let mut next_line = 0;
+        for range in self.matcher.find_iter(&text) {
+            if matches == max_matches {
+                break;
+            }
+            self.check_cancelled()?;
+
+            // Each line is reported at most once, as for line-by-line search.
+            let first = line_of(range.start);
+            if first < next_line {
+                continue;
+            }
+            let last = line_of(range.end.saturating_sub(1).max(range.start));
+            next_line = last + 1;
+
+            matches += 1;
+            self.report_match(
+                sender,
+                Match {
+                    path: file_path.to_path_buf(),
+                    line: lines[first..=last].join("\n"),
+                    line_number: first + 1,
+                    end_line_number: last + 1,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    fn get_files_in_directory(
+        &self,
+        directory: &Path,
+        sender: &Sender<Event>,
+    ) -> Result<Vec<PathBuf>, Stopped> {
+        let mut walk = Walk::default();
+        self.visit_dirs(directory, &mut walk, sender)?;
+        Ok(walk.files)
+    }
+
+    fn visit_dirs(
+        &self,
+        dir: &Path,
+        walk: &mut Walk,
+        sender: &Sender<Event>,
+    ) -> Result<(), Stopped> {
+        self.check_cancelled()?;
+        let id = if self.options.follow_symlinks {
+            match dir_id(dir) {
+                Ok(id) if walk.ancestors.contains(&id) => {
+                    let error = io::Error::other("Filesystem loop detected");
+                    return send_error(sender, dir, error);
+                }
+                Ok(id) if walk.visited.contains(&id) => return Ok(()),
+                Ok(id) => Some(id),
+                Err(error) => return send_error(sender, dir, error),
             }
+        } else {
+            None
+        };
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => return send_error(sender, dir, error),
+        };
+
+        walk.ancestors.extend(id);
+        // Subdirectories are visited after all the files, which keeps the
+        // files of every directory together.
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let (path, file_type) =
+                match entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))) {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        send_error(sender, dir, error)?;
+                        continue;
+                    }
+                };
+
+            if file_type.is_symlink() {
+                // A dangling link is kept as a file so that the failure to
+                // open it gets reported.
+                if path.is_dir() {
+                    if self.options.follow_symlinks {
+                        subdirs.push(path);
+                    }
+                } else if !self.options.skip_symlinked_files {
+                    walk.files.push(path);
+                }
+            } else if file_type.is_dir() {
+                subdirs.push(path);
+            } else {
+                walk.files.push(path);
+            }
+        }
+        for subdir in subdirs {
+            self.visit_dirs(&subdir, walk, sender)?;
         }
+        if self.options.follow_symlinks {
+            walk.visited.extend(walk.ancestors.pop());
+        }
+        Ok(())
     }
 }
 
-fn get_files_in_directory(directory: &Path) -> Vec<PathBuf> {
-    let mut files = vec![];
-    visit_dirs(directory, &mut files);
-    files
+#[derive(Default)]
+struct Walk {
+    files: Vec<PathBuf>,
+    /// Directories that were fully traversed; only tracked when following
+    /// symlinks, as is `ancestors`.
+    visited: HashSet<DirId>,
+    ancestors: Vec<DirId>,
 }
 
-fn visit_dirs(dir: &Path, files: &mut Vec<PathBuf>) {
-    for entry in std::fs::read_dir(dir).unwrap() {
-        let path = entry.unwrap().path();
+#[cfg(unix)]
+type DirId = (u64, u64);
 
-        if path.is_dir() {
-            visit_dirs(&path, files);
-        } else {
-            files.push(path);
+#[cfg(unix)]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    fs::canonicalize(path)
+}
+
+struct FileFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    max_file_size: Option<u64>,
+    #[cfg(any(feature = "gzip", feature = "tar"))]
+    archives: bool,
+}
+
+impl FileFilter {
+    fn new(options: &SearchOptions) -> Result<Self, PatternError> {
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|glob| Pattern::new(glob))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: compile(&options.include)?,
+            exclude: compile(&options.exclude)?,
+            max_file_size: options.max_file_size,
+            #[cfg(any(feature = "gzip", feature = "tar"))]
+            archives: options.search_archives,
+        })
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        let name = name.to_string_lossy();
+        #[cfg(any(feature = "gzip", feature = "tar"))]
+        let accepted = match archive::ArchiveKind::of(path).filter(|_| self.archives) {
+            Some(kind) => kind.accepts(self, &name),
+            None => self.accepts_name(&name),
+        };
+        #[cfg(not(any(feature = "gzip", feature = "tar")))]
+        let accepted = self.accepts_name(&name);
+        if !accepted {
+            return false;
         }
+        match (self.max_file_size, path.metadata()) {
+            (Some(max_size), Ok(metadata)) => metadata.len() <= max_size,
+            _ => true,
+        }
+    }
+
+    /// Whether a file named `name` passes the include and exclude globs.
+    fn accepts_name(&self, name: &str) -> bool {
+        self.included(name) && !self.excluded(name)
+    }
+
+    fn included(&self, name: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(name))
+    }
+
+    fn excluded(&self, name: &str) -> bool {
+        self.exclude.iter().any(|pattern| pattern.matches(name))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Counters {
+    files: AtomicU64,
+    bytes: AtomicU64,
+    matches: AtomicU64,
+}
+
+struct ProgressTracker {
+    callback: ProgressCallback,
+    counters: Arc<Counters>,
+    current_dir: Mutex<Option<PathBuf>>,
+    /// Held while reporting, so that reports are serialized.
+    next_report: Mutex<Instant>,
+}
+
+impl ProgressTracker {
+    fn new(callback: ProgressCallback) -> Self {
+        Self {
+            next_report: Mutex::new(Instant::now() + callback.interval),
+            callback,
+            counters: Default::default(),
+            current_dir: Mutex::new(None),
+        }
+    }
+
+    fn start_file(&self, path: &Path) {
+        let dir = path.parent();
+        let mut current_dir = self.current_dir.lock().unwrap();
+        if current_dir.as_deref() != dir {
+            *current_dir = dir.map(Path::to_path_buf);
+        }
+    }
+
+    /// Reports unless it's too early or another worker is already at it.
+    fn tick(&self) {
+        let Ok(mut next_report) = self.next_report.try_lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if now >= *next_report {
+            *next_report = now + self.callback.interval;
+            (self.callback.callback)(&self.snapshot(false));
+        }
+    }
+
+    fn report(&self, finished: bool) {
+        let _guard = self.next_report.lock().unwrap();
+        (self.callback.callback)(&self.snapshot(finished));
+    }
+
+    fn snapshot(&self, finished: bool) -> Progress {
+        Progress {
+            files_scanned: self.counters.files.load(Ordering::Relaxed),
+            bytes_read: self.counters.bytes.load(Ordering::Relaxed),
+            matches: self.counters.matches.load(Ordering::Relaxed),
+            current_dir: self.current_dir.lock().unwrap().clone(),
+            finished,
+        }
+    }
+}
+
+struct CountingReader<R> {
+    inner: R,
+    counters: Arc<Counters>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.counters.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+type Lines = Box<dyn Iterator<Item = io::Result<String>>>;
+
+/// Splits the file into lines, replacing invalid UTF-8 sequences with
+/// `U+FFFD` rather than dropping the whole line.
+fn read_lines(file: impl Read + 'static, detect_utf16: bool) -> io::Result<Lines> {
+    let mut reader = BufReader::new(file);
+    if detect_utf16 {
+        let decode: Option<fn([u8; 2]) -> u16> = match reader.fill_buf()? {
+            [0xFF, 0xFE, ..] => Some(u16::from_le_bytes),
+            [0xFE, 0xFF, ..] => Some(u16::from_be_bytes),
+            _ => None,
+        };
+        if let Some(decode) = decode {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes)?;
+            let units = bytes[2..]
+                .chunks_exact(2)
+                .map(|pair| decode([pair[0], pair[1]]));
+            let text = char::decode_utf16(units)
+                .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect::<String>();
+            let lines = text
+                .lines()
+                .map(|line| Ok(line.to_owned()))
+                .collect::<Vec<_>>();
+            return Ok(Box::new(lines.into_iter()));
+        }
+    }
+
+    Ok(Box::new(reader.split(b'\n').map(|line| {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(String::from_utf8(line)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
+    })))
+}
+
+/// Signals that the search was cancelled or its receiver went away.
+struct Stopped;
+
+impl<T> From<SendError<T>> for Stopped {
+    fn from(_: SendError<T>) -> Self {
+        Stopped
     }
 }
+
+fn send_error(sender: &Sender<Event>, path: &Path, error: io::Error) -> Result<(), Stopped> {
+    sender.send(Event::Error(Error {
+        path: path.to_path_buf(),
+        error,
+    }))?;
+    Ok(())
+}
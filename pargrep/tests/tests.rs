@@ -4,6 +4,10 @@ use std::{
     fs,
     io::{self, BufRead, BufReader, BufWriter, Write},
     path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
 
@@ -14,8 +18,8 @@ type TreeDesc = &'static [(&'static str, &'static [u8])];
 fn make_tree(desc: TreeDesc) -> io::Result<TempDir> {
     let tmp_dir = TempDir::new("pargrep")?;
     for (path_str, data) in desc.iter() {
-        if path_str.ends_with("/") {
-            let dir_path = tmp_dir.path().join(path_str.strip_suffix("/").unwrap());
+        if let Some(dir) = path_str.strip_suffix('/') {
+            let dir_path = tmp_dir.path().join(dir);
             fs::create_dir_all(&dir_path)?;
         } else {
             if let Some(dir_component) = Path::new(path_str).parent() {
@@ -71,16 +75,19 @@ fn test_file() {
                 path: path.to_path_buf(),
                 line: "Feed'st thy light'st flame with self-substantial fuel,".into(),
                 line_number: 6,
+                end_line_number: 6,
             },
             pargrep::Match {
                 path: path.to_path_buf(),
                 line: "Thyself thy foe, to thy sweet self too cruel.".into(),
                 line_number: 8,
+                end_line_number: 8,
             },
             pargrep::Match {
                 path: path.to_path_buf(),
                 line: "Within thine own bud buriest thy content".into(),
                 line_number: 11,
+                end_line_number: 11,
             },
         ]
     );
@@ -151,6 +158,413 @@ fn test_error() {
     }
 }
 
+fn search_lines(data: &[u8], pattern: &str, options: &pargrep::SearchOptions) -> Vec<usize> {
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    let path = tmp_dir.path().join("file");
+    fs::write(&path, data).unwrap();
+
+    let mut line_numbers = pargrep::run_with_options(&path, pattern, options)
+        .into_iter()
+        .map(|ev| match ev {
+            pargrep::Event::Match(m) => m.line_number,
+            pargrep::Event::Error(err) => panic!("unexpected error: {:?}", err),
+        })
+        .collect::<Vec<_>>();
+    line_numbers.sort();
+    line_numbers
+}
+
+#[test]
+fn test_options() {
+    let data = b"Foo bar\nfoobar\nbaz\nfoo.bar\nfoo\n";
+
+    let options = pargrep::SearchOptions::default();
+    assert_eq!(search_lines(data, "foo", &options), vec![2, 4, 5]);
+    assert_eq!(search_lines(data, "o.b", &options), vec![4]);
+
+    let options = pargrep::SearchOptions::new().with_case_insensitive(true);
+    assert_eq!(search_lines(data, "FOO", &options), vec![1, 2, 4, 5]);
+
+    let options = pargrep::SearchOptions::new().with_whole_word(true);
+    assert_eq!(search_lines(data, "foo", &options), vec![4, 5]);
+
+    let options = pargrep::SearchOptions::new().with_invert_match(true);
+    assert_eq!(search_lines(data, "foo", &options), vec![1, 3]);
+
+    let options = pargrep::SearchOptions::new().with_regex(true);
+    assert_eq!(search_lines(data, r"^ba|o\.b", &options), vec![3, 4]);
+
+    let options = pargrep::SearchOptions::new().with_max_matches_per_file(2);
+    assert_eq!(search_lines(data, "foo", &options), vec![2, 4]);
+}
+
+#[test]
+fn test_invalid_regex() {
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    let options = pargrep::SearchOptions::new().with_regex(true);
+    let events = pargrep::run_with_options(tmp_dir.path(), "(unclosed", &options);
+
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        pargrep::Event::Match(m) => panic!("unexpected match: {:?}", m),
+        pargrep::Event::Error(error) => {
+            assert_eq!(error.error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
+
+#[test]
+fn test_multiline() {
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    let path = tmp_dir.path().join("code.rs");
+    fs::write(
+        &path,
+        b"fn main() {\n    let x = 1;\n}\n\nfn helper() {}\nstruct Foo {\n}\n",
+    )
+    .unwrap();
+
+    let options = pargrep::SearchOptions::new()
+        .with_multiline(true)
+        .with_regex(true);
+    let mut matches =
+        pargrep::run_with_options(&path, r"(?m)^\w+ \w+(\(\))? \{\n(.*\n)*?\}", &options)
+            .into_iter()
+            .map(|ev| match ev {
+                pargrep::Event::Match(m) => (m.line_number, m.end_line_number, m.line),
+                pargrep::Event::Error(err) => panic!("unexpected error: {:?}", err),
+            })
+            .collect::<Vec<_>>();
+    matches.sort();
+    assert_eq!(
+        matches,
+        vec![
+            (1, 3, "fn main() {\n    let x = 1;\n}".to_string()),
+            (6, 7, "struct Foo {\n}".to_string()),
+        ]
+    );
+
+    let options = pargrep::SearchOptions::new().with_multiline(true);
+    assert_eq!(search_lines(b"a\nb\nab\n", "a\nb", &options), vec![1]);
+    assert_eq!(search_lines(b"", "", &options), Vec::<usize>::new());
+
+    let options = pargrep::SearchOptions::new()
+        .with_multiline(true)
+        .with_invert_match(true);
+    let events = pargrep::run_with_options(&path, "fn", &options);
+    assert!(matches!(
+        events.as_slice(),
+        [pargrep::Event::Error(err)] if err.error.kind() == io::ErrorKind::InvalidInput
+    ));
+}
+
+#[test]
+fn test_encodings() {
+    let options = pargrep::SearchOptions::default();
+    assert_eq!(
+        search_lines(
+            b"caf\xe9 needle\r\nplain\n\xff\xfeneedle",
+            "needle",
+            &options
+        ),
+        vec![1, 3]
+    );
+
+    let utf16 = "first\nsecond needle\r\nthird"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes);
+    let data = [0xFF, 0xFE].into_iter().chain(utf16).collect::<Vec<_>>();
+    assert!(search_lines(&data, "needle", &options).is_empty());
+
+    let options = pargrep::SearchOptions::new().with_detect_utf16(true);
+    assert_eq!(search_lines(&data, "needle", &options), vec![2]);
+
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    let path = tmp_dir.path().join("file");
+    fs::write(&path, b"\xffneedle\n").unwrap();
+    match pargrep::run(&path, "needle").as_slice() {
+        [pargrep::Event::Match(m)] => assert_eq!(m.line, "\u{FFFD}needle"),
+        _ => panic!("expected a single match"),
+    }
+}
+
+#[test]
+fn test_file_filters() {
+    let tmp_dir = make_tree(&[
+        ("src/lib.rs", b"needle\n"),
+        ("src/main.rs", b"needle\n"),
+        ("README.md", b"needle\n"),
+        ("big.rs", b"needle\nneedle\nneedle\n"),
+    ])
+    .unwrap();
+
+    let search = |options: &pargrep::SearchOptions| {
+        let mut paths = pargrep::run_with_options(tmp_dir.path(), "needle", options)
+            .into_iter()
+            .map(|ev| match ev {
+                pargrep::Event::Match(m) => m.path,
+                pargrep::Event::Error(err) => panic!("unexpected error: {:?}", err),
+            })
+            .map(|path| {
+                let path = path.strip_prefix(tmp_dir.path()).unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    };
+
+    let options = pargrep::SearchOptions::new().with_include(["*.rs"]);
+    assert_eq!(search(&options), ["big.rs", "src/lib.rs", "src/main.rs"]);
+
+    let options = pargrep::SearchOptions::new()
+        .with_include(["*.rs"])
+        .with_exclude(["main.*"])
+        .with_max_file_size(10);
+    assert_eq!(search(&options), ["src/lib.rs"]);
+
+    let options = pargrep::SearchOptions::new().with_include(["["]);
+    let events = pargrep::run_with_options(tmp_dir.path(), "needle", &options);
+    assert!(matches!(
+        events.as_slice(),
+        [pargrep::Event::Error(err)] if err.error.kind() == io::ErrorKind::InvalidInput
+    ));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_unreadable_file() {
+    let tmp_dir = make_tree(&[("a", b"needle\n"), ("b", b"needle\n")]).unwrap();
+    let dangling = tmp_dir.path().join("dangling");
+    std::os::unix::fs::symlink(tmp_dir.path().join("missing"), &dangling).unwrap();
+
+    let mut matches = 0;
+    let mut errors = vec![];
+    for event in pargrep::run(tmp_dir.path(), "needle") {
+        match event {
+            pargrep::Event::Match(_) => matches += 1,
+            pargrep::Event::Error(error) => errors.push(error),
+        }
+    }
+
+    assert_eq!(matches, 2);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, dangling);
+    assert_eq!(errors[0].error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let tmp_dir =
+        make_tree(&[("root/dir/file", b"needle\n"), ("other/file", b"needle\n")]).unwrap();
+    let root = tmp_dir.path().join("root");
+    symlink(root.join("dir/file"), root.join("file_link")).unwrap();
+    symlink(tmp_dir.path().join("other"), root.join("other_link")).unwrap();
+    symlink(tmp_dir.path().join("other"), root.join("dir/other_link")).unwrap();
+    symlink(&root, root.join("dir/loop")).unwrap();
+
+    let search = |options: &pargrep::SearchOptions| {
+        let mut matches = vec![];
+        let mut errors = vec![];
+        for event in pargrep::run_with_options(&root, "needle", options) {
+            match event {
+                pargrep::Event::Match(m) => {
+                    let path = m.path.strip_prefix(&root).unwrap();
+                    matches.push(path.to_str().unwrap().to_string());
+                }
+                pargrep::Event::Error(err) => {
+                    let path = err.path.strip_prefix(&root).unwrap();
+                    errors.push(path.to_str().unwrap().to_string());
+                }
+            }
+        }
+        matches.sort();
+        (matches, errors)
+    };
+
+    let options = pargrep::SearchOptions::default();
+    assert_eq!(
+        search(&options),
+        (vec!["dir/file".into(), "file_link".into()], vec![])
+    );
+
+    let options = pargrep::SearchOptions::new().with_skip_symlinked_files(true);
+    assert_eq!(search(&options), (vec!["dir/file".into()], vec![]));
+
+    let options = pargrep::SearchOptions::new().with_follow_symlinks(true);
+    let (matches, errors) = search(&options);
+    assert_eq!(matches.len(), 3);
+    assert!(matches.contains(&"dir/file".to_string()));
+    assert!(matches.contains(&"file_link".to_string()));
+    assert!(matches.iter().any(|path| path.ends_with("other_link/file")));
+    assert_eq!(errors, ["dir/loop"]);
+}
+
+#[test]
+fn test_streaming() {
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    for i in 0..64 {
+        fs::write(tmp_dir.path().join(i.to_string()), b"needle\nhay\nneedle\n").unwrap();
+    }
+
+    let events = pargrep::run_streaming(tmp_dir.path(), "needle");
+    assert_eq!(events.count(), 128);
+
+    let mut events = pargrep::run_streaming(tmp_dir.path(), "needle");
+    match events.next() {
+        Some(pargrep::Event::Match(m)) => assert_eq!(m.line, "needle"),
+        _ => panic!("expected a match"),
+    }
+    drop(events);
+
+    let events = pargrep::run_streaming("/sad/sdg/sdg/j/re/jta/rh/wethw/rt", "needle");
+    assert!(matches!(
+        events.collect::<Vec<_>>().as_slice(),
+        [pargrep::Event::Error(_)]
+    ));
+}
+
+#[test]
+fn test_cancellation() {
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    for i in 0..16 {
+        fs::write(tmp_dir.path().join(i.to_string()), b"needle\n".repeat(4096)).unwrap();
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let options = pargrep::SearchOptions::new().with_cancel(cancel.clone());
+
+    let events = pargrep::run_with_options(tmp_dir.path(), "needle", &options);
+    assert_eq!(events.len(), 16 * 4096);
+
+    cancel.store(true, Ordering::Relaxed);
+    let events = pargrep::run_with_options(tmp_dir.path(), "needle", &options);
+    assert!(events.is_empty());
+}
+
+#[test]
+fn test_progress() {
+    let tree_desc: TreeDesc = &[
+        ("a/1", b"needle\nhay\n"),
+        ("a/2", b"hay\n"),
+        ("a/b/3", b"needle\nneedle\n"),
+        ("c/4", b""),
+    ];
+    let tmp_dir = make_tree(tree_desc).unwrap();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let options = pargrep::SearchOptions::new().with_progress(pargrep::ProgressCallback::new(
+        Duration::ZERO,
+        {
+            let reports = reports.clone();
+            move |progress| reports.lock().unwrap().push(progress.clone())
+        },
+    ));
+    let events = pargrep::run_with_options(tmp_dir.path(), "needle", &options);
+    assert_eq!(events.len(), 3);
+
+    // The final report comes before the events end.
+    let reports = reports.lock().unwrap();
+    let (last, running) = reports.split_last().unwrap();
+    assert!(last.finished);
+    assert_eq!(last.files_scanned, 4);
+    assert_eq!(last.bytes_read, 29);
+    assert_eq!(last.matches, 3);
+    assert!(!running.is_empty());
+    for (prev, next) in reports.iter().zip(&reports[1..]) {
+        assert!(!prev.finished);
+        assert!(prev.files_scanned <= next.files_scanned);
+        assert!(prev.matches <= next.matches);
+    }
+    for progress in reports.iter() {
+        let dir = progress.current_dir.as_ref().unwrap();
+        let dir = dir.strip_prefix(tmp_dir.path()).unwrap();
+        assert!(["a", "a/b", "c"].iter().any(|d| dir == Path::new(d)));
+    }
+}
+
+#[test]
+#[cfg(all(feature = "gzip", feature = "tar"))]
+fn test_archives() {
+    use flate2::{write::GzEncoder, Compression};
+
+    let gzip = |data: &[u8]| {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    };
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in [
+        ("logs/app.log", &b"needle in tar\nhay\n"[..]),
+        ("logs/app.bin", b"needle in binary\n"),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+    let archive = builder.into_inner().unwrap();
+
+    let tmp_dir = TempDir::new("pargrep").unwrap();
+    fs::write(tmp_dir.path().join("app.log"), b"needle\n").unwrap();
+    // Rotated logs may be several gzip members in a row.
+    let rotated = [gzip(b"hay\n"), gzip(b"needle in gzip\n")].concat();
+    fs::write(tmp_dir.path().join("app.log.1.gz"), rotated).unwrap();
+    fs::write(tmp_dir.path().join("app.log.2.gz"), b"not gzip").unwrap();
+    fs::write(tmp_dir.path().join("old.tar"), &archive).unwrap();
+    fs::write(tmp_dir.path().join("old.tar.gz"), gzip(&archive)).unwrap();
+
+    let search = |options: &pargrep::SearchOptions| {
+        let mut matches = vec![];
+        let mut errors = vec![];
+        for event in pargrep::run_with_options(tmp_dir.path(), "needle", options) {
+            match event {
+                pargrep::Event::Match(m) => {
+                    let path = m.path.strip_prefix(tmp_dir.path()).unwrap();
+                    matches.push((path.to_string_lossy().into_owned(), m.line_number, m.line));
+                }
+                pargrep::Event::Error(err) => errors.push(err.path),
+            }
+        }
+        matches.sort();
+        (matches, errors)
+    };
+
+    let mut options = pargrep::SearchOptions::new().with_include(["*.log", "*.log.[0-9]"]);
+    let (matches, errors) = search(&options);
+    assert_eq!(matches, [("app.log".into(), 1, "needle".into())]);
+    assert!(errors.is_empty());
+
+    options.search_archives = true;
+    let (matches, errors) = search(&options);
+    assert_eq!(
+        matches,
+        [
+            ("app.log".into(), 1, "needle".into()),
+            ("app.log.1.gz".into(), 2, "needle in gzip".into()),
+            ("old.tar!logs/app.log".into(), 1, "needle in tar".into()),
+            ("old.tar.gz!logs/app.log".into(), 1, "needle in tar".into()),
+        ]
+    );
+    assert_eq!(errors, [tmp_dir.path().join("app.log.2.gz")]);
+
+    let options = pargrep::SearchOptions::new()
+        .with_search_archives(true)
+        .with_exclude(["*.log", "*.log.[0-9]", "*.tar"]);
+    let (matches, _) = search(&options);
+    assert_eq!(
+        matches,
+        [(
+            "old.tar.gz!logs/app.bin".into(),
+            1,
+            "needle in binary".into()
+        )]
+    );
+}
+
 #[test]
 #[cfg(not(debug_assertions))]
 fn test_performance() {
@@ -187,14 +601,15 @@ fn write_haystack(path: &Path, lines: usize, line_len: usize, needle_pos: usize)
         fs::OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(path)
             .unwrap(),
     );
     for i in 0..lines {
         if i == needle_pos {
-            writer.write(b"abacaba\n").unwrap();
+            writer.write_all(b"abacaba\n").unwrap();
         } else {
-            write!(writer, "{}\n", line).unwrap();
+            writeln!(writer, "{}", line).unwrap();
         }
     }
     writer.flush().unwrap();
@@ -213,6 +628,7 @@ fn single_run(path: &Path, pattern: &str) -> Vec<pargrep::Event> {
                     path: path.clone(),
                     line,
                     line_number: i + 1,
+                    end_line_number: i + 1,
                 }));
             }
         }
@@ -0,0 +1,146 @@
+use rand::{distributions::Bernoulli, prelude::Distribution};
+
+use crate::union_find::UnionFind;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A `width` x `height` grid for bond percolation: every cell is free, and
+/// what is open or blocked are the bonds between horizontally or vertically
+/// neighbouring cells. All bonds are open at first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BondGrid {
+    width: usize,
+    height: usize,
+    /// Whether the bond from `(x, y)` to `(x + 1, y)` is open, row by row.
+    /// The last column is always `false`.
+    right: Vec<bool>,
+    /// Whether the bond from `(x, y)` to `(x, y + 1)` is open, row by row.
+    /// The last row is always `false`.
+    down: Vec<bool>,
+}
+
+impl BondGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::from_fn(width, height, |_, _, _| true)
+    }
+
+    /// Creates a grid where every bond is open with probability `vacancy`.
+    pub fn random(width: usize, height: usize, vacancy: f64) -> Self {
+        let d = Bernoulli::new(vacancy).expect("given prob should be valid");
+        let mut rng = rand::thread_rng();
+        Self::from_fn(width, height, |_, _, _| d.sample(&mut rng))
+    }
+
+    /// Creates a grid where the bond from `(x, y)` to its right neighbour
+    /// is open if `f(x, y, Bond::Right)`, and similarly for the one below.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        mut f: impl FnMut(usize, usize, Bond) -> bool,
+    ) -> Self {
+        let mut grid = Self {
+            width,
+            height,
+            right: vec![false; width * height],
+            down: vec![false; width * height],
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let i = grid.index(x, y);
+                if x + 1 < width {
+                    grid.right[i] = f(x, y, Bond::Right);
+                }
+                if y + 1 < height {
+                    grid.down[i] = f(x, y, Bond::Down);
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the given bond of `(x, y)` is open.
+    ///
+    /// # Panics
+    ///
+    /// If the bond leads out of the grid.
+    pub fn is_open(&self, x: usize, y: usize, bond: Bond) -> bool {
+        let i = self.bond_index(x, y, bond);
+        match bond {
+            Bond::Right => self.right[i],
+            Bond::Down => self.down[i],
+        }
+    }
+
+    /// Opens or blocks the given bond of `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If the bond leads out of the grid.
+    pub fn set_open(&mut self, x: usize, y: usize, bond: Bond, open: bool) {
+        let i = self.bond_index(x, y, bond);
+        match bond {
+            Bond::Right => self.right[i] = open,
+            Bond::Down => self.down[i] = open,
+        }
+    }
+
+    /// Returns `true` if a path of open bonds joins the top row to the
+    /// bottom one. Empty grids and grids of a single row percolate.
+    pub fn percolates(&self) -> bool {
+        let n = self.width * self.height;
+        if n == 0 {
+            return true;
+        }
+
+        // Two more for the virtual top and bottom.
+        let (top, bottom) = (n, n + 1);
+        let mut cells = UnionFind::new(n + 2);
+        for x in 0..self.width {
+            cells.union(self.index(x, 0), top);
+            cells.union(self.index(x, self.height - 1), bottom);
+        }
+        for i in 0..n {
+            if self.right[i] {
+                cells.union(i, i + 1);
+            }
+            if self.down[i] {
+                cells.union(i, i + self.width);
+            }
+        }
+        cells.connected(top, bottom)
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    fn bond_index(&self, x: usize, y: usize, bond: Bond) -> usize {
+        let (nx, ny) = match bond {
+            Bond::Right => (x + 1, y),
+            Bond::Down => (x, y + 1),
+        };
+        assert!(
+            nx < self.width && ny < self.height,
+            "bond {bond:?} of ({x}, {y}) is out of the grid"
+        );
+        self.index(x, y)
+    }
+}
+
+/// The bonds a cell owns, those to the left and above being owned by its
+/// neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bond {
+    /// To `(x + 1, y)`.
+    Right,
+    /// To `(x, y + 1)`.
+    Down,
+}
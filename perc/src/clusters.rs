@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{BoolGrid, Cell, Grid};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// The clusters of free cells of a grid, see `label_clusters`. Cluster ids
+/// are numbered from 0 in the order of the clusters' first cells, row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterMap {
+    labels: Grid<Option<usize>>,
+    sizes: Vec<usize>,
+}
+
+impl ClusterMap {
+    /// The id of the cluster of `(x, y)`, `None` for a blocked cell.
+    pub fn label(&self, x: usize, y: usize) -> Option<usize> {
+        self.labels.get(x, y)
+    }
+
+    pub fn labels(&self) -> &Grid<Option<usize>> {
+        &self.labels
+    }
+
+    pub fn cluster_count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Number of cells in every cluster, by id.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// The cells of cluster `id`, row by row.
+    pub fn cells(&self, id: usize) -> impl Iterator<Item = Cell> + '_ {
+        self.labels
+            .cells()
+            .filter(move |(_, label)| **label == Some(id))
+            .map(|(cell, _)| cell)
+    }
+
+    /// Ids of the clusters touching both the top and the bottom row.
+    pub fn spanning(&self) -> Vec<usize> {
+        let (Some(top), Some(bottom)) = (self.labels.rows().next(), self.labels.rows().last())
+        else {
+            return Vec::new();
+        };
+        let mut ids = top
+            .iter()
+            .filter(|id| id.is_some() && bottom.contains(id))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    pub fn stats(&self) -> ClusterStats {
+        let mut size_distribution = BTreeMap::new();
+        for &size in &self.sizes {
+            *size_distribution.entry(size).or_insert(0) += 1;
+        }
+        let cells = self.labels.width() * self.labels.height();
+        let largest = self.sizes.iter().max().copied().unwrap_or(0);
+        ClusterStats {
+            size_distribution,
+            largest_fraction: if cells == 0 {
+                0.
+            } else {
+                largest as f64 / cells as f64
+            },
+            spanning_count: self.spanning().len(),
+        }
+    }
+}
+
+/// The usual observables of a grid's clusters, see `cluster_stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterStats {
+    /// Number of clusters of every size, by size.
+    pub size_distribution: BTreeMap<usize, usize>,
+    /// Cells in the largest cluster over all cells of the grid, 0 for a
+    /// grid without free cells.
+    pub largest_fraction: f64,
+    /// Number of clusters touching both the top and the bottom row.
+    pub spanning_count: usize,
+}
+
+/// Splits the free cells of `grid` into clusters of cells connected through
+/// `Grid::neighbours`. With a directed custom connectivity, a cluster is what
+/// its first cell reaches outside the earlier clusters.
+pub fn label_clusters(grid: &BoolGrid) -> ClusterMap {
+    let mut labels = grid.map(|_| None);
+    let mut sizes = Vec::new();
+    let mut queue = VecDeque::new();
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            if grid.get(x, y) || labels.get(x, y).is_some() {
+                continue;
+            }
+
+            let id = sizes.len();
+            let mut size = 0;
+            labels.set(x, y, Some(id));
+            queue.push_back((x, y));
+            while let Some((x, y)) = queue.pop_front() {
+                size += 1;
+                for (nx, ny) in grid.neighbours(x, y) {
+                    if labels.get(nx, ny).is_none() {
+                        labels.set(nx, ny, Some(id));
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+    }
+    ClusterMap { labels, sizes }
+}
+
+/// Shorthand for `label_clusters(grid).stats()`.
+pub fn cluster_stats(grid: &BoolGrid) -> ClusterStats {
+    label_clusters(grid).stats()
+}
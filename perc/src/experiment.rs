@@ -0,0 +1,165 @@
+use std::{
+    io::{self, Write},
+    num::NonZeroUsize,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{percolates, BoolGrid};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A sweep of percolation probability estimates over every pair of a grid
+/// size and a vacancy, each estimated from `trials` random grids spread
+/// over `threads` threads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Experiment {
+    sizes: Vec<(usize, usize)>,
+    vacancies: Vec<f64>,
+    trials: u64,
+    threads: usize,
+}
+
+impl Experiment {
+    /// An experiment over the given `(width, height)` sizes and vacancies,
+    /// with `N_TRIALS` trials per point and a thread per available CPU.
+    pub fn new(
+        sizes: impl IntoIterator<Item = (usize, usize)>,
+        vacancies: impl IntoIterator<Item = f64>,
+    ) -> Self {
+        Self {
+            sizes: sizes.into_iter().collect(),
+            vacancies: vacancies.into_iter().collect(),
+            trials: crate::N_TRIALS,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If `trials` is 0.
+    pub fn with_trials(mut self, trials: u64) -> Self {
+        assert!(trials > 0, "trials should be positive");
+        self.trials = trials;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If `threads` is 0.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "threads should be positive");
+        self.threads = threads;
+        self
+    }
+
+    /// Runs the trials of every point, sizes first: the results go through
+    /// all the vacancies of the first size, then of the second one, etc.
+    pub fn run(&self) -> Vec<ExperimentResult> {
+        let mut results = Vec::with_capacity(self.sizes.len() * self.vacancies.len());
+        for &(width, height) in &self.sizes {
+            for &vacancy in &self.vacancies {
+                results.push(self.run_point(width, height, vacancy));
+            }
+        }
+        results
+    }
+
+    /// Runs the experiment and writes the results to `writer` as CSV, see
+    /// `write_csv`.
+    pub fn run_csv(&self, writer: impl Write) -> io::Result<Vec<ExperimentResult>> {
+        let results = self.run();
+        write_csv(&results, writer)?;
+        Ok(results)
+    }
+
+    fn run_point(&self, width: usize, height: usize, vacancy: f64) -> ExperimentResult {
+        let start = Instant::now();
+        let threads = self.threads as u64;
+        let perc_count = thread::scope(|s| {
+            let handles = (0..threads)
+                .map(|i| {
+                    // The first threads take the remainder.
+                    let trials = self.trials / threads + u64::from(i < self.trials % threads);
+                    s.spawn(move || {
+                        (0..trials)
+                            .filter(|_| percolates(&BoolGrid::random(width, height, vacancy)))
+                            .count() as u64
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum::<u64>()
+        });
+
+        let n = self.trials as f64;
+        let probability = perc_count as f64 / n;
+        ExperimentResult {
+            width,
+            height,
+            vacancy,
+            trials: self.trials,
+            probability,
+            stderr: (probability * (1. - probability) / n).sqrt(),
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+/// The estimate of an `Experiment` at one size and vacancy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExperimentResult {
+    pub width: usize,
+    pub height: usize,
+    pub vacancy: f64,
+    pub trials: u64,
+    /// Fraction of the trials that percolated.
+    pub probability: f64,
+    /// Standard error of `probability`.
+    pub stderr: f64,
+    /// Wall time the trials took.
+    pub elapsed: Duration,
+}
+
+impl ExperimentResult {
+    pub const CSV_HEADER: &'static str =
+        "width,height,vacancy,trials,probability,stderr,elapsed_ms";
+
+    /// The result as a line of CSV, without the line break.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{:.3}",
+            self.width,
+            self.height,
+            self.vacancy,
+            self.trials,
+            self.probability,
+            self.stderr,
+            self.elapsed.as_secs_f64() * 1000.
+        )
+    }
+}
+
+/// Writes `results` to `writer` as CSV, starting with
+/// `ExperimentResult::CSV_HEADER`.
+pub fn write_csv(results: &[ExperimentResult], mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "{}", ExperimentResult::CSV_HEADER)?;
+    for result in results {
+        writeln!(writer, "{}", result.to_csv())?;
+    }
+    writer.flush()
+}
+
+/// `count` vacancies evenly spaced from `from` to `to`, both included.
+pub fn vacancy_range(from: f64, to: f64, count: usize) -> Vec<f64> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![from],
+        _ => {
+            let step = (to - from) / (count - 1) as f64;
+            (0..count).map(|i| from + step * i as f64).collect()
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use rand::Rng;
+
+use crate::{percolates, BoolGrid, Grid, IncrementalPercolation, N_TRIALS};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A grid of site strengths, a site being free under any cutoff at or above
+/// its strength, see `FieldGrid::cut`.
+pub type FieldGrid = Grid<f64>;
+
+impl<T> Grid<T> {
+    /// Returns the `BoolGrid` of the same size, boundary and connectivity
+    /// whose free cells are those whose values pass `is_free`.
+    pub fn threshold(&self, mut is_free: impl FnMut(&T) -> bool) -> BoolGrid {
+        self.map(|value| !is_free(value))
+    }
+}
+
+impl FieldGrid {
+    /// Creates a field of strengths uniform in `[0, 1)`, so that cutting it
+    /// at `vacancy` gives a grid distributed as `BoolGrid::random` does.
+    pub fn random(width: usize, height: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        Self::from_fn(width, height, |_, _| rng.gen())
+    }
+
+    /// The grid whose free cells are those with a strength of at most
+    /// `cutoff`. NaN strengths are never free.
+    pub fn cut(&self, cutoff: f64) -> BoolGrid {
+        self.threshold(|&strength| strength <= cutoff)
+    }
+
+    /// Shorthand for `percolates(&self.cut(cutoff))`.
+    pub fn percolates_at(&self, cutoff: f64) -> bool {
+        percolates(&self.cut(cutoff))
+    }
+
+    /// The lowest cutoff at which the field percolates: `percolates_at(c)`
+    /// holds exactly for `c` at or above it. `None` if the field doesn't
+    /// percolate even with every site but the NaN ones free, and negative
+    /// infinity for an empty field.
+    ///
+    /// Sites are opened by increasing strength in an
+    /// `IncrementalPercolation`, so custom connectivities should be
+    /// symmetric.
+    pub fn critical_cutoff(&self) -> Option<f64> {
+        let mut perc = IncrementalPercolation::new(self.width(), self.height())
+            .with_x_boundary(self.x_boundary())
+            .with_connectivity(self.connectivity().clone());
+        if perc.percolates() {
+            return Some(f64::NEG_INFINITY);
+        }
+
+        let mut sites = self
+            .cells()
+            .filter(|(_, strength)| !strength.is_nan())
+            .collect::<Vec<_>>();
+        sites.sort_unstable_by(|(_, a), (_, b)| a.total_cmp(b));
+        sites.into_iter().find_map(|((x, y), &strength)| {
+            perc.open(x, y);
+            perc.percolates().then_some(strength)
+        })
+    }
+}
+
+/// Like `evaluate_probability` for every vacancy of `vacancies` at once:
+/// each trial samples a single `FieldGrid` and finds its critical cutoff,
+/// which tells whether it percolates at every vacancy.
+pub fn evaluate_probability_curve(width: usize, height: usize, vacancies: &[f64]) -> Vec<f64> {
+    let mut perc_counts = vec![0; vacancies.len()];
+    for _ in 0..N_TRIALS {
+        let Some(critical) = FieldGrid::random(width, height).critical_cutoff() else {
+            continue;
+        };
+        for (count, &vacancy) in perc_counts.iter_mut().zip(vacancies) {
+            if vacancy >= critical {
+                *count += 1;
+            }
+        }
+    }
+    perc_counts
+        .into_iter()
+        .map(|count| count as f64 / N_TRIALS as f64)
+        .collect()
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{BoolGrid, Cell, ClusterMap, Grid};
+
+////////////////////////////////////////////////////////////////////////////////
+
+const BLOCKED: [u8; 3] = [0x30, 0x30, 0x30];
+const FREE: [u8; 3] = [0xff, 0xff, 0xff];
+const PATH: [u8; 3] = [0xe0, 0x10, 0x10];
+
+/// A picture of a grid, every cell a `scale` x `scale` square: blocked cells
+/// are dark and free ones white, or the color of their cluster if
+/// `with_clusters` is given. Cells of the `with_path` path are drawn red
+/// over everything else.
+#[derive(Clone, Debug)]
+pub struct GridImage<'a> {
+    grid: &'a BoolGrid,
+    scale: u32,
+    clusters: Option<&'a ClusterMap>,
+    path: &'a [Cell],
+}
+
+impl<'a> GridImage<'a> {
+    pub fn new(grid: &'a BoolGrid) -> Self {
+        Self {
+            grid,
+            scale: 1,
+            clusters: None,
+            path: &[],
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If `scale` is 0.
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        assert!(scale > 0, "scale should be positive");
+        self.scale = scale;
+        self
+    }
+
+    /// Colors free cells by cluster, `clusters` being those of the grid, see
+    /// `label_clusters`.
+    pub fn with_clusters(mut self, clusters: &'a ClusterMap) -> Self {
+        self.clusters = Some(clusters);
+        self
+    }
+
+    /// Highlights the cells of `path`, e.g. one `find_percolating_path`
+    /// returned.
+    pub fn with_path(mut self, path: &'a [Cell]) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.grid.width() as u32 * self.scale
+    }
+
+    pub fn height(&self) -> u32 {
+        self.grid.height() as u32 * self.scale
+    }
+
+    /// The RGB values of the pixels, 3 bytes per pixel, row by row.
+    pub fn to_rgb(&self) -> Vec<u8> {
+        let colors = self.cell_colors();
+        let scale = self.scale as usize;
+        let mut rgb = Vec::with_capacity(self.width() as usize * self.height() as usize * 3);
+        for row in colors.rows() {
+            for _ in 0..scale {
+                for color in row {
+                    for _ in 0..scale {
+                        rgb.extend_from_slice(color);
+                    }
+                }
+            }
+        }
+        rgb
+    }
+
+    /// Encodes the image as a PNG. Fails with `InvalidInput` for an empty
+    /// grid, as PNGs can't be empty.
+    pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+        if self.width() == 0 || self.height() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can't encode an empty grid",
+            ));
+        }
+        let mut encoder = png::Encoder::new(writer, self.width(), self.height());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(into_io_error)?;
+        writer
+            .write_image_data(&self.to_rgb())
+            .map_err(into_io_error)?;
+        writer.finish().map_err(into_io_error)
+    }
+
+    /// Writes the image as a PNG to the file at `path`, see `write_png`.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png(&mut writer)?;
+        writer.flush()
+    }
+
+    fn cell_colors(&self) -> Grid<[u8; 3]> {
+        let mut colors = Grid::from_fn(self.grid.width(), self.grid.height(), |x, y| {
+            if self.grid.get(x, y) {
+                BLOCKED
+            } else if let Some(id) = self.clusters.and_then(|clusters| clusters.label(x, y)) {
+                cluster_color(id)
+            } else {
+                FREE
+            }
+        });
+        for &(x, y) in self.path {
+            colors.set(x, y, PATH);
+        }
+        colors
+    }
+}
+
+/// A light color for cluster `id`. Hues are spread by the golden angle, so
+/// clusters with close ids, which tend to be close in the grid, differ.
+fn cluster_color(id: usize) -> [u8; 3] {
+    const GOLDEN_ANGLE: f64 = 137.507_764;
+    let hue = (id as f64 * GOLDEN_ANGLE) % 360. / 60.;
+    let (saturation, value) = (0.55, 0.95);
+
+    let chroma = value * saturation;
+    let x = chroma * (1. - (hue % 2. - 1.).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let m = value - chroma;
+    [r, g, b].map(|c| ((c + m) * 255.).round() as u8)
+}
+
+fn into_io_error(error: png::EncodingError) -> io::Error {
+    match error {
+        png::EncodingError::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidInput, error),
+    }
+}
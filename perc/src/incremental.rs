@@ -0,0 +1,150 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{union_find::UnionFind, BoolGrid, Boundary, Cell, Connectivity};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A grid whose sites are all blocked at first and opened one at a time.
+/// Open sites are kept in a union-find together with two virtual sites
+/// joined to the top and the bottom rows, so that every `open` and
+/// `percolates` takes amortized near-constant time.
+#[derive(Clone, Debug)]
+pub struct IncrementalPercolation {
+    grid: BoolGrid,
+    sites: UnionFind,
+    open_count: usize,
+    /// Number of open sites when the grid first percolated.
+    percolated_at: Option<usize>,
+}
+
+impl IncrementalPercolation {
+    pub fn new(width: usize, height: usize) -> Self {
+        let n = width * height;
+        Self {
+            grid: BoolGrid::filled(width, height, true),
+            // Two more for the virtual top and bottom.
+            sites: UnionFind::new(n + 2),
+            open_count: 0,
+            percolated_at: (n == 0).then_some(0),
+        }
+    }
+
+    /// See `Grid::with_x_boundary`.
+    pub fn with_x_boundary(mut self, boundary: Boundary) -> Self {
+        self.grid = self.grid.with_x_boundary(boundary);
+        self
+    }
+
+    /// See `Grid::with_connectivity`. Custom offsets should be symmetric, as
+    /// the sites are joined both ways.
+    pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.grid = self.grid.with_connectivity(connectivity);
+        self
+    }
+
+    /// The sites as a `BoolGrid`, blocked ones being `true`.
+    pub fn grid(&self) -> &BoolGrid {
+        &self.grid
+    }
+
+    pub fn is_open(&self, x: usize, y: usize) -> bool {
+        !self.grid.get(x, y)
+    }
+
+    pub fn open_count(&self) -> usize {
+        self.open_count
+    }
+
+    /// Opens the site, returning `false` if it was already open.
+    ///
+    /// # Panics
+    ///
+    /// If `x` or `y` is out of bounds.
+    pub fn open(&mut self, x: usize, y: usize) -> bool {
+        if self.is_open(x, y) {
+            return false;
+        }
+        self.grid.set(x, y, false);
+        self.open_count += 1;
+
+        let site = self.index((x, y));
+        if y == 0 {
+            self.sites.union(site, self.top());
+        }
+        if y == self.grid.height() - 1 {
+            self.sites.union(site, self.bottom());
+        }
+        let neighbours = self.grid.neighbours(x, y).collect::<Vec<_>>();
+        for cell in neighbours {
+            self.sites.union(site, self.index(cell));
+        }
+
+        if self.percolated_at.is_none() && self.sites.connected(self.top(), self.bottom()) {
+            self.percolated_at = Some(self.open_count);
+        }
+        true
+    }
+
+    /// Whether the open sites percolate, in the sense of `percolates`.
+    pub fn percolates(&self) -> bool {
+        self.percolated_at.is_some()
+    }
+
+    /// The fraction of open sites at which the grid first percolated, `None`
+    /// if it doesn't percolate yet. Empty grids percolate at 0.
+    pub fn threshold(&self) -> Option<f64> {
+        let count = self.percolated_at?;
+        let sites = self.grid.width() * self.grid.height();
+        Some(if sites == 0 {
+            0.
+        } else {
+            count as f64 / sites as f64
+        })
+    }
+
+    /// Opens the blocked sites in random order until the grid percolates and
+    /// returns the threshold. `None` if it doesn't percolate even with every
+    /// site open, as some custom connectivities can't reach the bottom row.
+    pub fn open_randomly(&mut self, rng: &mut impl Rng) -> Option<f64> {
+        let mut blocked = self
+            .grid
+            .cells()
+            .filter(|(_, blocked)| **blocked)
+            .map(|(cell, _)| cell)
+            .collect::<Vec<_>>();
+        blocked.shuffle(rng);
+        for (x, y) in blocked {
+            if self.percolates() {
+                break;
+            }
+            self.open(x, y);
+        }
+        self.threshold()
+    }
+
+    fn index(&self, (x, y): Cell) -> usize {
+        y * self.grid.width() + x
+    }
+
+    fn top(&self) -> usize {
+        self.grid.width() * self.grid.height()
+    }
+
+    fn bottom(&self) -> usize {
+        self.top() + 1
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns an estimate of the percolation threshold: the mean fraction of
+/// open sites at which a `width` x `height` grid starts to percolate when
+/// sites are opened in random order, over `trials` experiments. `None` if
+/// some experiment never percolates, see `IncrementalPercolation::open_randomly`.
+pub fn estimate_threshold(width: usize, height: usize, trials: u64) -> Option<f64> {
+    let mut rng = rand::thread_rng();
+    let total = (0..trials)
+        .map(|_| IncrementalPercolation::new(width, height).open_randomly(&mut rng))
+        .sum::<Option<f64>>()?;
+    Some(total / trials.max(1) as f64)
+}
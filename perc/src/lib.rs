@@ -1,62 +1,158 @@
 #![forbid(unsafe_code)]
 
+mod bond;
+mod clusters;
+mod experiment;
+mod field;
+#[cfg(feature = "image")]
+mod image;
+mod incremental;
+mod union_find;
+
+pub use bond::*;
+pub use clusters::*;
+pub use experiment::*;
+pub use field::*;
+#[cfg(feature = "image")]
+pub use image::*;
+pub use incremental::*;
+
 ////////////////////////////////////////////////////////////////////////////////
 
 use std::{
     collections::{HashSet, VecDeque},
-    fmt::Display,
+    fmt::{self, Display},
+    ops::{Index, IndexMut},
+    str::FromStr,
 };
 
 use rand::{distributions::Bernoulli, prelude::Distribution};
 
-type Cell = (usize, usize);
+/// `(x, y)` coordinates of a grid cell.
+pub type Cell = (usize, usize);
 
-/// Represents a grid of boolean values.
-pub struct BoolGrid {
+/// What lies beyond the edges of a grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// Nothing: edge cells have fewer neighbours.
+    #[default]
+    Open,
+    /// The opposite edge, as on a torus.
+    Periodic,
+}
+
+/// Which cells count as adjacent, by their offsets from a cell.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    /// The four horizontal and vertical neighbours.
+    #[default]
+    VonNeumann,
+    /// The eight neighbours, diagonal ones included.
+    Moore,
+    /// Cells at the given `(dx, dy)` offsets. Percolation follows them in
+    /// the given direction only, so asymmetric lists make it directed.
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Connectivity {
+    const VON_NEUMANN_OFFSETS: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+    const MOORE_OFFSETS: [(isize, isize); 8] = [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+    ];
+
+    pub fn offsets(&self) -> &[(isize, isize)] {
+        match self {
+            Self::VonNeumann => &Self::VON_NEUMANN_OFFSETS,
+            Self::Moore => &Self::MOORE_OFFSETS,
+            Self::Custom(offsets) => offsets,
+        }
+    }
+}
+
+/// A `width` x `height` grid of values, stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
     width: usize,
     height: usize,
-    data: Vec<Vec<bool>>,
+    x_boundary: Boundary,
+    connectivity: Connectivity,
+    data: Vec<T>,
 }
 
-impl BoolGrid {
-    /// Creates a new grid with all values initialized as `false`.
+/// Represents a grid of boolean values.
+pub type BoolGrid = Grid<bool>;
+
+impl<T: Default + Clone> Grid<T> {
+    /// Creates a new grid with all values initialized as `T::default()`.
     ///
     /// # Arguments
     ///
     /// * `width` - grid width.
     /// * `height` - grid height.
     pub fn new(width: usize, height: usize) -> Self {
+        Self::filled(width, height, T::default())
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    /// Creates a new grid with every value equal to `value`.
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
         Self {
             width,
             height,
-            data: vec![vec![false; height]; width],
+            x_boundary: Boundary::Open,
+            connectivity: Connectivity::VonNeumann,
+            data: vec![value; width * height],
         }
     }
 
-    /// Creates a new grid with every value initialized randomly.
+    /// Sets every value to `value`.
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+}
+
+impl<T: Copy> Grid<T> {
+    /// Returns the current value of a given cell.
+    /// The caller must ensure that `x` and `y` are valid.
     ///
     /// # Arguments
     ///
-    /// * `width` - grid width.
-    /// * `height` - grid height.
-    /// * `vacancy` - probability of any given value being equal
-    /// to `false`.
-    pub fn random(width: usize, height: usize, vacancy: f64) -> Self {
+    /// * `x` - must be >= 0 and < grid width.
+    /// * `y` - must be >= 0 and < grid height.
+    ///
+    /// # Panics
+    ///
+    /// If `x` or `y` is out of bounds, this method may panic
+    /// (or return incorrect result).
+    pub fn get(&self, x: usize, y: usize) -> T {
+        self[(x, y)]
+    }
+}
+
+impl<T> Grid<T> {
+    /// Creates a new grid with the value of every cell `(x, y)` equal to
+    /// `f(x, y)`.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(f(x, y));
+            }
+        }
         Self {
             width,
             height,
-            data: {
-                let mut data = vec![vec![false; height]; width];
-                let d = Bernoulli::new(1.0 - vacancy).expect("given prob should be valid");
-                let mut rng = rand::thread_rng();
-                for x in 0..width {
-                    for y in 0..height {
-                        data[x][y] = d.sample(&mut rng);
-                    }
-                }
-
-                data
-            },
+            x_boundary: Boundary::Open,
+            connectivity: Connectivity::VonNeumann,
+            data,
         }
     }
 
@@ -70,20 +166,26 @@ impl BoolGrid {
         self.height
     }
 
-    /// Returns the current value of a given cell.
-    /// The caller must ensure that `x` and `y` are valid.
-    ///
-    /// # Arguments
-    ///
-    /// * `x` - must be >= 0 and < grid width.
-    /// * `y` - must be >= 0 and < grid height.
-    ///
-    /// # Panics
-    ///
-    /// If `x` or `y` is out of bounds, this method may panic
-    /// (or return incorrect result).
-    pub fn get(&self, x: usize, y: usize) -> bool {
-        self.data[x][y]
+    /// Returns the grid with the left and right edges joined as `boundary`
+    /// says. Grids are created with open boundaries.
+    pub fn with_x_boundary(mut self, boundary: Boundary) -> Self {
+        self.x_boundary = boundary;
+        self
+    }
+
+    pub fn x_boundary(&self) -> Boundary {
+        self.x_boundary
+    }
+
+    /// Returns the grid with `connectivity` deciding which cells are
+    /// adjacent. Grids are created with `Connectivity::VonNeumann`.
+    pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
+    }
+
+    pub fn connectivity(&self) -> &Connectivity {
+        &self.connectivity
     }
 
     /// Sets a new value to a given cell.
@@ -98,42 +200,109 @@ impl BoolGrid {
     ///
     /// If `x` or `y` is out of bounds, this method may panic
     /// (or set value to some other unspecified cell).
-    pub fn set(&mut self, x: usize, y: usize, value: bool) {
-        self.data[x][y] = value;
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        self[(x, y)] = value;
     }
 
-    pub fn dfs_roots(&self) -> Vec<Cell> {
-        let mut roots = Vec::with_capacity(self.width);
-        let mut trunc_cnt = 0;
-        for x in 0..self.width {
-            for y in 0..self.height {
-                if y == 0 && !self.data[x][y] {
-                    roots.push((x, y));
-                    trunc_cnt += 1;
-                }
-            }
+    /// Returns a grid of the same size with `f` applied to every value.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            x_boundary: self.x_boundary,
+            connectivity: self.connectivity.clone(),
+            data: self.data.iter().map(f).collect(),
         }
-        roots.truncate(trunc_cnt);
-        roots
     }
 
-    pub fn neighbours<'a>(&'a self, x: usize, y: usize) -> impl Iterator<Item = Cell> + 'a {
-        [(-1, 0), (0, -1), (0, 1), (1, 0)]
+    /// Iterates over `((x, y), value)` for every cell, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = (Cell, &T)> + '_ {
+        self.data
             .iter()
-            .filter_map(move |(dx, dy)| {
-                let x = x as isize + dx;
-                let y = y as isize + dy;
-
-                if x >= 0
-                    && x < self.width as isize
-                    && y >= 0
-                    && y < self.height as isize
-                    && !self.data[x as usize][y as usize]
-                {
-                    return Some((x as usize, y as usize));
-                }
-                None
-            })
+            .enumerate()
+            .map(|(i, value)| ((i % self.width, i / self.width), value))
+    }
+
+    /// Iterates over the rows, from `y` == 0 to `y` == `height` - 1.
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        // `max(1)` keeps `chunks` happy for zero-width grids, which have no data.
+        self.data.chunks(self.width.max(1))
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [T]> + '_ {
+        self.data.chunks_mut(self.width.max(1))
+    }
+
+    /// Returns the in-bounds cells adjacent to `(x, y)` under the grid's
+    /// connectivity, in the order of its offsets. With a periodic x boundary,
+    /// the first and last columns are adjacent.
+    pub fn adjacent(&self, x: usize, y: usize) -> impl Iterator<Item = Cell> + '_ {
+        let offsets = self.connectivity.offsets();
+        offsets.iter().enumerate().filter_map(move |(i, &offset)| {
+            let cell = self.offset(x, y, offset)?;
+            // Narrow grids wrap onto the cell itself, or reach the same
+            // neighbour through several offsets.
+            let repeated = offsets[..i]
+                .iter()
+                .any(|&earlier| self.offset(x, y, earlier) == Some(cell));
+            (cell != (x, y) && !repeated).then_some(cell)
+        })
+    }
+
+    fn offset(&self, x: usize, y: usize, (dx, dy): (isize, isize)) -> Option<Cell> {
+        let nx = match x.checked_add_signed(dx) {
+            Some(nx) if nx < self.width => nx,
+            _ if self.x_boundary == Boundary::Periodic && x < self.width => {
+                (x as isize + dx).rem_euclid(self.width as isize) as usize
+            }
+            _ => return None,
+        };
+        let ny = y.checked_add_signed(dy)?;
+        (ny < self.height).then_some((nx, ny))
+    }
+}
+
+impl<T> Index<Cell> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): Cell) -> &T {
+        assert!(x < self.width, "x out of bounds");
+        &self.data[y * self.width + x]
+    }
+}
+
+impl<T> IndexMut<Cell> for Grid<T> {
+    fn index_mut(&mut self, (x, y): Cell) -> &mut T {
+        assert!(x < self.width, "x out of bounds");
+        &mut self.data[y * self.width + x]
+    }
+}
+
+impl BoolGrid {
+    /// Creates a new grid with every value initialized randomly.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - grid width.
+    /// * `height` - grid height.
+    /// * `vacancy` - probability of any given value being equal
+    ///   to `false`.
+    pub fn random(width: usize, height: usize, vacancy: f64) -> Self {
+        let d = Bernoulli::new(1.0 - vacancy).expect("given prob should be valid");
+        let mut rng = rand::thread_rng();
+        Self::from_fn(width, height, |_, _| d.sample(&mut rng))
+    }
+
+    pub fn dfs_roots(&self) -> Vec<Cell> {
+        (0..self.width)
+            .filter(|x| self.height > 0 && !self.get(*x, 0))
+            .map(|x| (x, 0))
+            .collect()
+    }
+
+    /// Returns the free cells adjacent to `(x, y)`.
+    pub fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = Cell> + '_ {
+        self.adjacent(x, y).filter(|(x, y)| !self.get(*x, *y))
     }
 }
 
@@ -170,26 +339,141 @@ pub fn percolates(grid: &BoolGrid) -> bool {
     false
 }
 
+/// Returns a shortest path of free cells from the top row to the bottom one,
+/// each cell adjacent to the previous one, or `None` if the grid doesn't
+/// percolate. The path of an empty grid is empty.
+pub fn find_percolating_path(grid: &BoolGrid) -> Option<Vec<Cell>> {
+    if grid.height == 0 || grid.width == 0 {
+        return Some(Vec::new());
+    }
+
+    // Roots are their own previous cells.
+    let mut previous = grid.map(|_| None);
+    let mut queue = VecDeque::new();
+    for root in grid.dfs_roots() {
+        previous[root] = Some(root);
+        queue.push_back(root);
+    }
+    while let Some(cell) = queue.pop_front() {
+        if cell.1 == grid.height - 1 {
+            let mut path = vec![cell];
+            let mut last = cell;
+            while let Some(prev) = previous[last].filter(|&prev| prev != last) {
+                path.push(prev);
+                last = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for nb in grid.neighbours(cell.0, cell.1) {
+            if previous[nb].is_none() {
+                previous[nb] = Some(cell);
+                queue.push_back(nb);
+            }
+        }
+    }
+    None
+}
+
+/// One line per column `x`, holding `#` for a blocked cell and `.` for a free
+/// one, from `y` == 0 on.
 impl Display for BoolGrid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for x in 0..self.width {
             for y in 0..self.height {
-                if self.data[x][y] {
+                if self.get(x, y) {
                     write!(f, "#")?;
                 } else {
                     write!(f, ".")?;
                 }
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
+impl BoolGrid {
+    /// Parses the format `Display` writes: a line of `#` and `.` per column,
+    /// all of the same length. Empty text gives an empty grid. Error rows and
+    /// columns are those of the text.
+    pub fn from_str_grid(text: &str) -> Result<Self, ParseGridError> {
+        let mut columns = Vec::<Vec<bool>>::new();
+        for (row, line) in text.lines().enumerate() {
+            let column = line
+                .chars()
+                .enumerate()
+                .map(|(column, ch)| match ch {
+                    '#' => Ok(true),
+                    '.' => Ok(false),
+                    ch => Err(ParseGridError::UnexpectedChar { ch, row, column }),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(first) = columns.first() {
+                if column.len() != first.len() {
+                    return Err(ParseGridError::RaggedRow {
+                        row,
+                        expected: first.len(),
+                        found: column.len(),
+                    });
+                }
+            }
+            columns.push(column);
+        }
+        let height = columns.first().map_or(0, Vec::len);
+        Ok(Self::from_fn(columns.len(), height, |x, y| columns[x][y]))
+    }
+}
+
+impl FromStr for BoolGrid {
+    type Err = ParseGridError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_grid(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseGridError {
+    UnexpectedChar {
+        ch: char,
+        row: usize,
+        column: usize,
+    },
+    /// A line of text whose length differs from the first one's.
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for ParseGridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar { ch, row, column } => {
+                write!(f, "unexpected {ch:?} at row {row}, column {column}")
+            }
+            Self::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row} has {found} cells, expected {expected}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseGridError {}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 const N_TRIALS: u64 = 10000;
 
+/// Fewest trials `evaluate_probability_adaptive` runs, so that a loose
+/// `epsilon` can't stop it after a handful of them.
+const MIN_ADAPTIVE_TRIALS: u64 = 100;
+
 /// Returns an estimate of the probability that a random grid with given
 /// `width, `height` and `vacancy` probability percolates.
 /// To compute an estimate, it runs `N_TRIALS` of random experiments,
@@ -202,5 +486,43 @@ pub fn evaluate_probability(width: usize, height: usize, vacancy: f64) -> f64 {
             perc_count += 1;
         }
     }
-    return perc_count as f64 / N_TRIALS as f64;
+    perc_count as f64 / N_TRIALS as f64
+}
+
+/// Like `evaluate_probability`, but runs trials until the half-width of the
+/// 95% Wilson score interval falls below `epsilon`, running at least 100 of
+/// them. Returns the centre of that interval, which is the raw fraction of
+/// percolating grids pulled towards 1/2, together with its half-width: the
+/// interval is `centre ± half_width`.
+///
+/// # Panics
+///
+/// If `epsilon` isn't positive.
+pub fn evaluate_probability_adaptive(
+    width: usize,
+    height: usize,
+    vacancy: f64,
+    epsilon: f64,
+) -> (f64, f64) {
+    assert!(epsilon > 0., "epsilon should be positive");
+    const Z: f64 = 1.96;
+
+    let mut perc_count = 0;
+    let mut trials = 0;
+    loop {
+        let grid = BoolGrid::random(width, height, vacancy);
+        if percolates(&grid) {
+            perc_count += 1;
+        }
+        trials += 1;
+
+        let n = trials as f64;
+        let p = perc_count as f64 / n;
+        let scale = 1. + Z * Z / n;
+        let centre = (p + Z * Z / (2. * n)) / scale;
+        let half_width = Z / scale * (p * (1. - p) / n + Z * Z / (4. * n * n)).sqrt();
+        if trials >= MIN_ADAPTIVE_TRIALS && half_width < epsilon {
+            return (centre, half_width);
+        }
+    }
 }
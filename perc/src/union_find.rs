@@ -0,0 +1,46 @@
+/// Disjoint sets of `0..n` with union by size.
+#[derive(Clone, Debug)]
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    pub(crate) fn find(&self, mut site: usize) -> usize {
+        while self.parent[site] != site {
+            site = self.parent[site];
+        }
+        site
+    }
+
+    pub(crate) fn connected(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find_compress(a), self.find_compress(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+
+    fn find_compress(&mut self, mut site: usize) -> usize {
+        while self.parent[site] != site {
+            self.parent[site] = self.parent[self.parent[site]];
+            site = self.parent[site];
+        }
+        site
+    }
+}
@@ -1,4 +1,10 @@
-use perc::{evaluate_probability, percolates, BoolGrid};
+use perc::{
+    cluster_stats, estimate_threshold, evaluate_probability, evaluate_probability_adaptive,
+    evaluate_probability_curve, find_percolating_path, label_clusters, percolates, vacancy_range,
+    write_csv, Bond, BondGrid, BoolGrid, Boundary, Connectivity, Experiment, ExperimentResult,
+    FieldGrid, Grid, IncrementalPercolation, ParseGridError,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -47,6 +53,40 @@ fn test_grid_basics() {
     assert!(!grid.get(2, 4));
 }
 
+#[test]
+fn test_generic_grid() {
+    let mut grid = Grid::from_fn(3, 2, |x, y| x + 10 * y);
+    assert_eq!(grid.get(2, 1), 12);
+    assert_eq!(grid[(1, 1)], 11);
+    assert_eq!(
+        grid.rows().collect::<Vec<_>>(),
+        [&[0, 1, 2][..], &[10, 11, 12][..]]
+    );
+    assert_eq!(
+        grid.cells()
+            .filter(|(_, v)| **v % 2 == 1)
+            .map(|(c, _)| c)
+            .collect::<Vec<_>>(),
+        [(1, 0), (1, 1)]
+    );
+
+    let mut adjacent = grid.adjacent(0, 1).collect::<Vec<_>>();
+    adjacent.sort();
+    assert_eq!(adjacent, [(0, 0), (1, 1)]);
+
+    let labels = grid.map(|v| format!("<{v}>"));
+    assert_eq!(labels[(2, 0)], "<2>");
+
+    grid.rows_mut().nth(1).unwrap()[0] = 7;
+    assert_eq!(grid.get(0, 1), 7);
+    grid.fill(5);
+    assert!(grid.cells().all(|(_, v)| *v == 5));
+    assert_eq!(Grid::filled(2, 2, 'x'), Grid::from_fn(2, 2, |_, _| 'x'));
+
+    let bools: BoolGrid = grid.map(|v| *v > 3);
+    assert!(bools.get(1, 1));
+}
+
 #[test]
 fn test_custom_grid() {
     let grid = make_grid(
@@ -85,6 +125,265 @@ fn test_percolates() {
     assert!(percolates(&BoolGrid::random(50, 50, 0.9)));
 }
 
+#[test]
+fn test_periodic_boundary() {
+    // Only percolates through the wrapped-around edge.
+    let grid = make_grid(
+        "
+            ##.#
+            ##..
+            ###.
+            .##.
+            .###
+        ",
+    );
+    assert!(!percolates(&grid));
+    let grid = grid.with_x_boundary(Boundary::Periodic);
+    assert_eq!(grid.x_boundary(), Boundary::Periodic);
+    assert!(percolates(&grid));
+    assert_eq!(grid.map(|v| !v).x_boundary(), Boundary::Periodic);
+
+    let neighbours = |grid: &BoolGrid, x, y| grid.adjacent(x, y).collect::<Vec<_>>();
+    assert_eq!(neighbours(&grid, 0, 1), [(3, 1), (0, 0), (0, 2), (1, 1)]);
+    assert_eq!(neighbours(&grid, 3, 0), [(2, 0), (3, 1), (0, 0)]);
+    assert_eq!(neighbours(&grid, 2, 1), [(1, 1), (2, 0), (2, 2), (3, 1)]);
+
+    let narrow = BoolGrid::new(2, 2).with_x_boundary(Boundary::Periodic);
+    assert_eq!(neighbours(&narrow, 0, 0), [(1, 0), (0, 1)]);
+    assert_eq!(neighbours(&narrow, 1, 0), [(0, 0), (1, 1)]);
+    let column = BoolGrid::new(1, 3).with_x_boundary(Boundary::Periodic);
+    assert_eq!(neighbours(&column, 0, 1), [(0, 0), (0, 2)]);
+}
+
+#[test]
+fn test_connectivity() {
+    // Only percolates diagonally.
+    let grid = make_grid(
+        "
+            .###
+            #.##
+            ##.#
+        ",
+    );
+    assert_eq!(grid.connectivity(), &Connectivity::VonNeumann);
+    assert!(!percolates(&grid));
+    let grid = grid.with_connectivity(Connectivity::Moore);
+    assert!(percolates(&grid));
+    assert_eq!(grid.map(|v| !v).connectivity(), &Connectivity::Moore);
+
+    let neighbours = |grid: &BoolGrid, x, y| grid.adjacent(x, y).collect::<Vec<_>>();
+    assert_eq!(neighbours(&grid, 3, 0), [(2, 0), (2, 1), (3, 1)]);
+    assert_eq!(neighbours(&grid, 1, 1).len(), 8);
+    let column = BoolGrid::new(1, 3)
+        .with_x_boundary(Boundary::Periodic)
+        .with_connectivity(Connectivity::Moore);
+    assert_eq!(neighbours(&column, 0, 1), [(0, 0), (0, 2)]);
+
+    // Down and right only.
+    let directed = Connectivity::Custom(vec![(0, 1), (1, 0), (0, 1)]);
+    let grid = make_grid(
+        "
+            #.
+            ..
+            .#
+        ",
+    );
+    assert!(percolates(&grid));
+    let grid = grid.with_connectivity(directed);
+    assert_eq!(neighbours(&grid, 0, 0), [(0, 1), (1, 0)]);
+    assert!(!percolates(&grid));
+
+    let mut perc = IncrementalPercolation::new(2, 2).with_connectivity(Connectivity::Moore);
+    perc.open(0, 0);
+    perc.open(1, 1);
+    assert!(perc.percolates());
+}
+
+#[test]
+fn test_percolating_path() {
+    let grid = make_grid(
+        "
+            #.###
+            #...#
+            ###.#
+            #...#
+            #.###
+        ",
+    );
+    assert_eq!(
+        find_percolating_path(&grid),
+        Some(vec![
+            (1, 0),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+            (1, 4)
+        ])
+    );
+    assert_eq!(find_percolating_path(&BoolGrid::new(3, 0)), Some(vec![]));
+    assert_eq!(find_percolating_path(&make_grid(".#\n#.")), None);
+    assert_eq!(
+        find_percolating_path(&make_grid(".#\n#.").with_connectivity(Connectivity::Moore)),
+        Some(vec![(0, 0), (1, 1)])
+    );
+
+    for _ in 0..100 {
+        let grid = BoolGrid::random(6, 6, 0.6);
+        let path = find_percolating_path(&grid);
+        assert_eq!(path.is_some(), percolates(&grid));
+        if let Some(path) = path {
+            assert_eq!((path[0].1, path.last().unwrap().1), (0, 5));
+            assert!(path.iter().all(|&(x, y)| !grid.get(x, y)));
+            assert!(path
+                .windows(2)
+                .all(|w| grid.adjacent(w[0].0, w[0].1).any(|c| c == w[1])));
+        }
+    }
+}
+
+#[test]
+fn test_label_clusters() {
+    let grid = make_grid(
+        "
+            ..#.
+            #.#.
+            ##..
+            .###
+        ",
+    );
+    let clusters = label_clusters(&grid);
+    assert_eq!(clusters.cluster_count(), 3);
+    assert_eq!(clusters.sizes(), [3, 4, 1]);
+    assert_eq!(clusters.label(1, 1), Some(0));
+    assert_eq!(clusters.label(2, 2), Some(1));
+    assert_eq!(clusters.label(0, 1), None);
+    assert_eq!(clusters.label(0, 3), Some(2));
+    assert_eq!(
+        clusters.cells(1).collect::<Vec<_>>(),
+        [(3, 0), (3, 1), (2, 2), (3, 2)]
+    );
+    assert_eq!(clusters.labels().width(), 4);
+    assert_eq!(clusters.spanning(), []);
+    let clusters = label_clusters(&grid.with_connectivity(Connectivity::Moore));
+    assert_eq!(clusters.sizes(), [7, 1]);
+    assert_eq!(clusters.spanning(), []);
+    assert_eq!(label_clusters(&make_grid(".#.\n..#\n#..")).spanning(), [0]);
+
+    let empty = label_clusters(&BoolGrid::new(0, 0));
+    assert_eq!((empty.cluster_count(), empty.spanning()), (0, vec![]));
+}
+
+#[test]
+fn test_cluster_stats() {
+    let grid = make_grid(
+        "
+            ..#.
+            #.#.
+            #.#.
+            .#.#
+        ",
+    );
+    let stats = cluster_stats(&grid);
+    assert_eq!(
+        stats.size_distribution.into_iter().collect::<Vec<_>>(),
+        [(1, 2), (3, 1), (4, 1)]
+    );
+    assert_eq!(stats.largest_fraction, 4. / 16.);
+    assert_eq!(stats.spanning_count, 0);
+
+    let stats = cluster_stats(&grid.with_connectivity(Connectivity::Moore));
+    assert_eq!(
+        stats.size_distribution.into_iter().collect::<Vec<_>>(),
+        [(9, 1)]
+    );
+    assert_eq!(stats.largest_fraction, 9. / 16.);
+    assert_eq!(stats.spanning_count, 1);
+
+    let stats = cluster_stats(&BoolGrid::filled(3, 2, true));
+    assert!(stats.size_distribution.is_empty());
+    assert_eq!((stats.largest_fraction, stats.spanning_count), (0., 0));
+}
+
+#[test]
+fn test_parse() {
+    // A line per column.
+    let grid = "..#\n#..\n".parse::<BoolGrid>().unwrap();
+    assert_eq!((grid.width(), grid.height()), (2, 3));
+    assert!(grid.get(0, 2) && grid.get(1, 0) && !grid.get(1, 1));
+    assert_eq!(grid.to_string(), "..#\n#..\n");
+    assert_eq!(grid, make_grid(".#\n..\n#."));
+
+    for _ in 0..10 {
+        let grid = BoolGrid::random(7, 4, 0.5);
+        assert_eq!(BoolGrid::from_str_grid(&grid.to_string()), Ok(grid));
+    }
+    assert_eq!(BoolGrid::from_str_grid(""), Ok(BoolGrid::new(0, 0)));
+    assert_eq!(
+        BoolGrid::from_str_grid("\r\n.#\n"),
+        Err(ParseGridError::RaggedRow {
+            row: 1,
+            expected: 0,
+            found: 2
+        })
+    );
+    assert_eq!(
+        BoolGrid::from_str_grid(".#\n.#.\n"),
+        Err(ParseGridError::RaggedRow {
+            row: 1,
+            expected: 2,
+            found: 3
+        })
+    );
+    let err = "..\n.x\n".parse::<BoolGrid>().unwrap_err();
+    assert_eq!(
+        err,
+        ParseGridError::UnexpectedChar {
+            ch: 'x',
+            row: 1,
+            column: 1
+        }
+    );
+    assert_eq!(err.to_string(), "unexpected 'x' at row 1, column 1");
+}
+
+#[test]
+fn test_bond_grid() {
+    let grid = BondGrid::new(3, 3);
+    assert!(grid.is_open(0, 0, Bond::Right) && grid.is_open(2, 1, Bond::Down));
+    assert!(grid.percolates());
+
+    // Only the zigzag (0, 0) -> (0, 1) -> (1, 1) -> (1, 2) is left.
+    let mut grid = BondGrid::from_fn(3, 3, |x, y, bond| {
+        matches!(
+            (x, y, bond),
+            (0, 0, Bond::Down) | (0, 1, Bond::Right) | (1, 1, Bond::Down)
+        )
+    });
+    assert!(grid.percolates());
+    grid.set_open(0, 1, Bond::Right, false);
+    assert!(!grid.percolates());
+    grid.set_open(2, 0, Bond::Down, true);
+    assert!(!grid.percolates());
+    grid.set_open(1, 1, Bond::Right, true);
+    assert!(grid.percolates());
+    assert_eq!((grid.width(), grid.height()), (3, 3));
+
+    assert!(BondGrid::random(5, 1, 0.).percolates());
+    assert!(!BondGrid::random(5, 2, 0.).percolates());
+    assert!(BondGrid::random(5, 5, 1.).percolates());
+    assert!(BondGrid::new(0, 0).percolates());
+}
+
+#[test]
+#[should_panic]
+fn test_bond_out_of_grid() {
+    BondGrid::new(3, 3).is_open(2, 0, Bond::Right);
+}
+
 #[test]
 fn test_probability() {
     for (width, height, vacancy, expected) in
@@ -102,3 +401,241 @@ fn test_probability() {
         );
     }
 }
+
+#[test]
+fn test_probability_adaptive() {
+    for (width, height, vacancy, expected) in [(10, 10, 0.57, 0.425), (3, 4, 0.5, 0.25)] {
+        let (actual, ci) = evaluate_probability_adaptive(width, height, vacancy, 0.01);
+        assert!(ci < 0.01);
+        assert!(
+            (expected - actual).abs() < 0.02,
+            "wrong answer: expected {} +- 0.02, got {} +- {}",
+            expected,
+            actual,
+            ci,
+        );
+    }
+
+    // Certain outcomes still need enough trials to rule the others out, and
+    // their intervals end at the certain value, off centre.
+    let (centre, ci) = evaluate_probability_adaptive(4, 4, 1., 0.05);
+    assert!(centre < 1. && ci > 0. && ci < 0.05);
+    assert!((centre + ci - 1.).abs() < 1e-12, "{centre} +- {ci}");
+    let (centre, ci) = evaluate_probability_adaptive(4, 4, 0., 0.05);
+    assert!(
+        centre > 0. && (centre - ci).abs() < 1e-12,
+        "{centre} +- {ci}"
+    );
+
+    // A loose epsilon doesn't stop after a handful of trials.
+    let (centre, ci) = evaluate_probability_adaptive(3, 4, 0.5, 1.);
+    assert!(ci < 0.1, "{centre} +- {ci}");
+}
+
+#[test]
+fn test_experiment() {
+    let range = vacancy_range(0.2, 0.6, 5);
+    assert_eq!(range.len(), 5);
+    for (actual, expected) in range.into_iter().zip([0.2, 0.3, 0.4, 0.5, 0.6]) {
+        assert!((actual - expected).abs() < 1e-12, "{actual}");
+    }
+    assert_eq!(vacancy_range(0.5, 1., 1), [0.5]);
+    assert!(vacancy_range(0., 1., 0).is_empty());
+
+    let experiment = Experiment::new([(10, 10), (3, 4)], [0., 0.57, 1.])
+        .with_trials(5001)
+        .with_threads(3);
+    let mut csv = Vec::new();
+    let results = experiment.run_csv(&mut csv).unwrap();
+    let points = results
+        .iter()
+        .map(|r| (r.width, r.height, r.vacancy, r.trials))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        points,
+        [
+            (10, 10, 0., 5001),
+            (10, 10, 0.57, 5001),
+            (10, 10, 1., 5001),
+            (3, 4, 0., 5001),
+            (3, 4, 0.57, 5001),
+            (3, 4, 1., 5001),
+        ]
+    );
+    assert_eq!((results[0].probability, results[0].stderr), (0., 0.));
+    assert_eq!((results[2].probability, results[2].stderr), (1., 0.));
+    let middle = results[1];
+    assert!((middle.probability - 0.425).abs() < 0.03, "{middle:?}");
+    let expected_stderr = (middle.probability * (1. - middle.probability) / 5001.).sqrt();
+    assert!((middle.stderr - expected_stderr).abs() < 1e-12);
+
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], ExperimentResult::CSV_HEADER);
+    assert!(lines[1].starts_with("10,10,0,5001,0,0,"), "{}", lines[1]);
+    assert!(lines[6].starts_with("3,4,1,5001,1,0,"), "{}", lines[6]);
+    for (line, result) in lines[1..].iter().zip(&results) {
+        assert_eq!(*line, result.to_csv());
+    }
+
+    let mut csv = Vec::new();
+    write_csv(&[], &mut csv).unwrap();
+    assert_eq!(
+        csv,
+        format!("{}\n", ExperimentResult::CSV_HEADER).as_bytes()
+    );
+}
+
+#[test]
+fn test_field() {
+    let field = FieldGrid::from_fn(3, 3, |x, y| {
+        [[0.1, 0.9, 0.5], [0.4, 0.2, 0.8], [0.6, 0.3, 0.7]][y][x]
+    });
+    assert_eq!(field.cut(0.4), make_grid(".##\n..#\n#.#"));
+    assert_eq!(field.threshold(|&s| s > 0.5), make_grid("#.#\n##.\n.#."));
+    assert!(!field.percolates_at(0.3));
+    assert!(field.percolates_at(0.4));
+    assert_eq!(field.critical_cutoff(), Some(0.4));
+
+    let field = field.with_connectivity(Connectivity::Moore);
+    assert_eq!(field.cut(0.2).connectivity(), &Connectivity::Moore);
+    assert_eq!(field.critical_cutoff(), Some(0.3));
+
+    let field = FieldGrid::from_fn(2, 2, |x, _| if x == 0 { f64::NAN } else { 0.5 });
+    assert!(field.cut(f64::INFINITY).get(0, 0));
+    assert_eq!(field.critical_cutoff(), Some(0.5));
+    assert_eq!(FieldGrid::filled(2, 2, f64::NAN).critical_cutoff(), None);
+    assert_eq!(
+        FieldGrid::new(0, 4).critical_cutoff(),
+        Some(f64::NEG_INFINITY)
+    );
+
+    for _ in 0..20 {
+        let field = FieldGrid::random(6, 5);
+        let critical = field.critical_cutoff().unwrap();
+        assert!((0. ..1.).contains(&critical));
+        assert!(field.percolates_at(critical));
+        assert!(!field.percolates_at(critical - 1e-9));
+    }
+
+    let probabilities = evaluate_probability_curve(10, 10, &[0., 0.57, 1.]);
+    assert_eq!(probabilities[0], 0.);
+    assert!((probabilities[1] - 0.425).abs() < 0.02, "{probabilities:?}");
+    assert_eq!(probabilities[2], 1.);
+}
+
+#[test]
+fn test_incremental() {
+    let mut perc = IncrementalPercolation::new(3, 3);
+    assert!(!perc.percolates());
+    assert_eq!(perc.threshold(), None);
+    for (x, y) in [(0, 0), (1, 1), (1, 2)] {
+        assert!(perc.open(x, y));
+    }
+    assert!(!perc.open(1, 1));
+    assert!(!perc.percolates());
+    perc.open(1, 0);
+    assert!(perc.percolates());
+    assert_eq!(perc.open_count(), 4);
+    assert_eq!(perc.threshold(), Some(4. / 9.));
+    perc.open(2, 2);
+    assert_eq!(perc.threshold(), Some(4. / 9.));
+    assert!(perc.is_open(2, 2) && !perc.is_open(2, 0));
+    assert_eq!(perc.grid(), &make_grid("..#\n#.#\n#.."));
+
+    // The first and last columns only touch across the periodic boundary.
+    let mut perc = IncrementalPercolation::new(4, 2).with_x_boundary(Boundary::Periodic);
+    perc.open(0, 0);
+    perc.open(3, 1);
+    assert!(!perc.percolates());
+    perc.open(3, 0);
+    assert!(perc.percolates());
+
+    assert_eq!(IncrementalPercolation::new(0, 5).threshold(), Some(0.));
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..20 {
+        let mut perc = IncrementalPercolation::new(8, 6);
+        let mut cells = perc.grid().cells().map(|(c, _)| c).collect::<Vec<_>>();
+        cells.shuffle(&mut rng);
+        for (x, y) in cells {
+            perc.open(x, y);
+            assert_eq!(perc.percolates(), percolates(perc.grid()));
+        }
+    }
+}
+
+#[test]
+fn test_threshold() {
+    let threshold = estimate_threshold(30, 30, 300).unwrap();
+    assert!((threshold - 0.5927).abs() < 0.03, "{threshold}");
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut perc = IncrementalPercolation::new(10, 10);
+    let threshold = perc.open_randomly(&mut rng).unwrap();
+    assert!(perc.percolates());
+    assert_eq!(threshold, perc.open_count() as f64 / 100.);
+
+    // Sideways only: the bottom row is out of reach.
+    let mut perc = IncrementalPercolation::new(4, 3)
+        .with_connectivity(Connectivity::Custom(vec![(1, 0), (-1, 0)]));
+    assert_eq!(perc.open_randomly(&mut rng), None);
+    assert_eq!(perc.open_count(), 12);
+    assert!(!perc.percolates());
+    let mut perc = IncrementalPercolation::new(4, 1)
+        .with_connectivity(Connectivity::Custom(vec![(1, 0), (-1, 0)]));
+    assert_eq!(perc.open_randomly(&mut rng), Some(0.25));
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_image() {
+    use perc::GridImage;
+
+    let grid = make_grid(".#.\n.#.\n..#");
+    let pixel = |rgb: &[u8], width: usize, x: usize, y: usize| {
+        let i = (y * width + x) * 3;
+        [rgb[i], rgb[i + 1], rgb[i + 2]]
+    };
+
+    let rgb = GridImage::new(&grid).to_rgb();
+    assert_eq!(rgb.len(), 9 * 3);
+    let (free, blocked) = (pixel(&rgb, 3, 0, 0), pixel(&rgb, 3, 1, 0));
+    assert_eq!(free, [0xff; 3]);
+    assert_ne!(free, blocked);
+    assert_eq!(pixel(&rgb, 3, 2, 0), free);
+
+    let clusters = label_clusters(&grid);
+    let path = find_percolating_path(&grid).unwrap();
+    let image = GridImage::new(&grid)
+        .with_scale(2)
+        .with_clusters(&clusters)
+        .with_path(&path);
+    assert_eq!((image.width(), image.height()), (6, 6));
+    let rgb = image.to_rgb();
+    let on_path = pixel(&rgb, 6, 0, 0);
+    assert_eq!(pixel(&rgb, 6, 1, 5), on_path);
+    // Off the path, in the same cluster.
+    let off_path = pixel(&rgb, 6, 3, 5);
+    assert!(![free, on_path].contains(&off_path));
+    assert_eq!(pixel(&rgb, 6, 4, 4), blocked);
+    // Another cluster, another color.
+    let other = pixel(&rgb, 6, 5, 0);
+    assert_eq!(pixel(&rgb, 6, 4, 3), other);
+    assert!(![free, blocked, on_path, off_path].contains(&other));
+
+    let mut png = Vec::new();
+    image.write_png(&mut png).unwrap();
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut decoded).unwrap();
+    assert_eq!((info.width, info.height), (6, 6));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    assert_eq!(decoded, rgb);
+
+    let error = GridImage::new(&BoolGrid::new(0, 3))
+        .write_png(Vec::new())
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
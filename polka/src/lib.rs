@@ -7,14 +7,37 @@ use std::{collections::HashSet, fmt::Display};
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Number(f64),
+    /// Pushed instead of `Number` for literals without a fraction in
+    /// integer mode, see the `int` word.
+    Integer(i64),
     Symbol(String),
+    List(Vec<Value>),
 }
 
+/// The precision of the formatter, e.g. `{:.2}`, applies to the numbers,
+/// those in lists included.
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Number(num) => write!(f, "{}", num),
+            Self::Number(num) => match f.precision() {
+                Some(precision) => write!(f, "{:.*}", precision, num),
+                None => write!(f, "{}", num),
+            },
+            Self::Integer(num) => write!(f, "{}", num),
             Self::Symbol(sym) => write!(f, "'{}", sym),
+            Self::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match f.precision() {
+                        Some(precision) => write!(f, "{:.*}", precision, item)?,
+                        None => write!(f, "{}", item)?,
+                    }
+                }
+                write!(f, "]")
+            }
         }
     }
 }
@@ -26,6 +49,8 @@ pub struct Interpreter {
     stack: Vec<Value>,
     variables: Vec<(String, Value)>,
     first: HashSet<String>,
+    integer_mode: bool,
+    precision: Option<usize>,
 }
 
 impl Interpreter {
@@ -34,6 +59,8 @@ impl Interpreter {
             stack: Vec::new(),
             variables: Vec::new(),
             first: HashSet::new(),
+            integer_mode: false,
+            precision: None,
         }
     }
 
@@ -42,14 +69,35 @@ impl Interpreter {
 
         for t in tokens {
             if t.as_bytes()[0].is_ascii_digit() {
-                self.stack.push(Value::Number(t.parse().unwrap()));
-            } else if ["+", "-", "/", "*"].contains(&t) {
-                match (self.stack.pop().unwrap(), self.stack.pop().unwrap()) {
-                    (Value::Number(a), Value::Number(b)) => {
-                        self.stack.push(Value::Number(self.operation(t, a, b)))
+                match t.parse() {
+                    Ok(n) if self.integer_mode => self.stack.push(Value::Integer(n)),
+                    _ => self.stack.push(Value::Number(t.parse().unwrap())),
+                }
+            } else if ["+", "-", "/", "*", "%"].contains(&t) {
+                let value = match (self.stack.pop().unwrap(), self.stack.pop().unwrap()) {
+                    (Value::Integer(a), Value::Integer(b)) => {
+                        Value::Integer(self.integer_operation(t, a, b))
                     }
-                    (_, _) => panic!("cannot operate on non-numeric values"),
+                    (a, b) => Value::Number(self.operation(t, Self::float(a), Self::float(b))),
+                };
+                self.stack.push(value);
+            } else if t == "int" {
+                self.integer_mode = true;
+            } else if t == "float" {
+                self.integer_mode = false;
+            } else if t == "toint" {
+                let n = Self::float(self.stack.pop().unwrap());
+                if !n.is_finite() || n.trunc() < i64::MIN as f64 || n.trunc() >= i64::MAX as f64 {
+                    panic!("{} does not fit an integer", n);
                 }
+                self.stack.push(Value::Integer(n as i64));
+            } else if t == "tofloat" {
+                let n = Self::float(self.stack.pop().unwrap());
+                self.stack.push(Value::Number(n));
+            } else if t == "precision" {
+                self.precision = Some(self.pop_index());
+            } else if t == "noprecision" {
+                self.precision = None;
             } else if t.as_bytes()[0] == b'\'' {
                 let var = t[1..].to_string();
                 self.stack.push(Value::Symbol(var.clone()));
@@ -60,7 +108,10 @@ impl Interpreter {
             } else if t == "set" {
                 let var = self.stack.pop().unwrap();
                 match var {
-                    Value::Number(_) => panic!("cannot set value to numeric value"),
+                    Value::Number(_) | Value::Integer(_) => {
+                        panic!("cannot set value to numeric value")
+                    }
+                    Value::List(_) => panic!("cannot set value to list value"),
                     Value::Symbol(var) => {
                         let value = self.stack.pop().unwrap();
                         let mut i = 0;
@@ -80,6 +131,31 @@ impl Interpreter {
                         self.stack.push(v.clone());
                     }
                 }
+            } else if t == "pack" {
+                let n = self.pop_index();
+                if n > self.stack.len() {
+                    panic!("cannot pack {} values", n);
+                }
+                let items = self.stack.split_off(self.stack.len() - n);
+                self.stack.push(Value::List(items));
+            } else if t == "unpack" {
+                let items = self.pop_list();
+                self.stack.extend(items);
+            } else if t == "len" {
+                let items = self.pop_list();
+                let len = if self.integer_mode {
+                    Value::Integer(items.len() as i64)
+                } else {
+                    Value::Number(items.len() as f64)
+                };
+                self.stack.push(len);
+            } else if t == "nth" {
+                let n = self.pop_index();
+                let mut items = self.pop_list();
+                if n >= items.len() {
+                    panic!("index {} out of range", n);
+                }
+                self.stack.push(items.swap_remove(n));
             } else {
                 panic!("unexpected token");
             }
@@ -90,13 +166,59 @@ impl Interpreter {
         &self.stack
     }
 
+    /// Digits to print after the point of numbers, set with the `precision`
+    /// word; `None` prints as few as needed.
+    pub fn precision(&self) -> Option<usize> {
+        self.precision
+    }
+
+    fn pop_index(&mut self) -> usize {
+        match self.stack.pop().unwrap() {
+            Value::Number(n) if n >= 0. && n.fract() == 0. => n as usize,
+            Value::Integer(n) if n >= 0 => n as usize,
+            _ => panic!("expected a non-negative integer"),
+        }
+    }
+
+    fn float(value: Value) -> f64 {
+        match value {
+            Value::Number(n) => n,
+            Value::Integer(n) => n as f64,
+            _ => panic!("cannot operate on non-numeric values"),
+        }
+    }
+
+    fn pop_list(&mut self) -> Vec<Value> {
+        match self.stack.pop().unwrap() {
+            Value::List(items) => items,
+            _ => panic!("expected a list"),
+        }
+    }
+
     fn operation(&self, op: &str, a: f64, b: f64) -> f64 {
         match op {
             "+" => a + b,
             "-" => a - b,
             "*" => a * b,
             "/" => a / b,
+            "%" => a.rem_euclid(b),
             _ => panic!("unexpected token"),
         }
     }
+
+    /// Division rounds towards negative infinity for a positive divisor,
+    /// and the remainder is never negative.
+    fn integer_operation(&self, op: &str, a: i64, b: i64) -> i64 {
+        let result = match op {
+            "+" => a.checked_add(b),
+            "-" => a.checked_sub(b),
+            "*" => a.checked_mul(b),
+            "/" if b == 0 => panic!("division by zero"),
+            "/" => a.checked_div_euclid(b),
+            "%" if b == 0 => panic!("division by zero"),
+            "%" => a.checked_rem_euclid(b),
+            _ => panic!("unexpected token"),
+        };
+        result.unwrap_or_else(|| panic!("integer overflow"))
+    }
 }
@@ -2,12 +2,16 @@
 
 use std::io::{stdin, stdout, BufRead, Write};
 
-fn print_values(values: &[polka::Value]) {
+fn print_values(values: &[polka::Value], precision: Option<usize>) {
+    let format = |value: &polka::Value| match precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => value.to_string(),
+    };
     print!("[");
     if let Some(value) = values.first() {
-        print!("{}", value);
+        print!("{}", format(value));
         for value in &values[1..] {
-            print!(", {}", value);
+            print!(", {}", format(value));
         }
     }
     println!("]");
@@ -20,7 +24,7 @@ fn main() {
     let mut inter = polka::Interpreter::new();
     for line in stdin().lock().lines() {
         inter.eval(&line.unwrap());
-        print_values(inter.stack());
+        print_values(inter.stack(), inter.precision());
         print!("> ");
         stdout().flush().unwrap();
     }
@@ -59,6 +59,135 @@ fn test_whitespace() {
     test(&mut inter, "3\n5\t10\r+   \n\r*", &[Value::Number(45.)]);
 }
 
+#[test]
+fn test_lists() {
+    let mut inter = Interpreter::new();
+    let list = Value::List(vec![
+        Value::Number(1.),
+        Value::Number(2.),
+        Value::Number(3.),
+    ]);
+    assert_eq!(list.to_string(), "[1, 2, 3]");
+    test(&mut inter, "1 2 3 3 pack", &[list]);
+    test(&mut inter, "'xs set $xs len", &[Value::Number(3.)]);
+    test(
+        &mut inter,
+        "$xs 0 nth",
+        &[Value::Number(3.), Value::Number(1.)],
+    );
+    test(&mut inter, "+ $xs 2 nth *", &[Value::Number(12.)]);
+    test(
+        &mut inter,
+        "$xs unpack",
+        &[
+            Value::Number(12.),
+            Value::Number(1.),
+            Value::Number(2.),
+            Value::Number(3.),
+        ],
+    );
+    test(
+        &mut inter,
+        "0 pack 'a 2 pack",
+        &[
+            Value::Number(12.),
+            Value::Number(1.),
+            Value::Number(2.),
+            Value::Number(3.),
+            Value::List(vec![Value::List(vec![]), Value::Symbol("a".to_string())]),
+        ],
+    );
+}
+
+#[test]
+fn test_integers() {
+    let mut inter = Interpreter::new();
+    test(&mut inter, "int 7 2 /", &[Value::Integer(0)]);
+    test(
+        &mut inter,
+        "2 7 / 3 7 % +",
+        &[Value::Integer(0), Value::Integer(4)],
+    );
+    // -7 / 2 and -7 % 2.
+    test(
+        &mut inter,
+        "* 2 7 0 - /",
+        &[Value::Integer(0), Value::Integer(-4)],
+    );
+    test(
+        &mut inter,
+        "+ 2 7 0 - %",
+        &[Value::Integer(-4), Value::Integer(1)],
+    );
+    test(
+        &mut inter,
+        "2.5 *",
+        &[Value::Integer(-4), Value::Number(2.5)],
+    );
+    test(
+        &mut inter,
+        "toint",
+        &[Value::Integer(-4), Value::Integer(2)],
+    );
+    test(
+        &mut inter,
+        "tofloat 1 2 2 pack len",
+        &[Value::Integer(-4), Value::Number(2.), Value::Integer(2)],
+    );
+    test(
+        &mut inter,
+        "float 1 2 2 pack len 7",
+        &[
+            Value::Integer(-4),
+            Value::Number(2.),
+            Value::Integer(2),
+            Value::Number(2.),
+            Value::Number(7.),
+        ],
+    );
+    assert_eq!(Value::Integer(-3).to_string(), "-3");
+}
+
+#[test]
+fn test_precision() {
+    let mut inter = Interpreter::new();
+    assert_eq!(inter.precision(), None);
+    inter.eval("2 precision");
+    assert_eq!(inter.precision(), Some(2));
+    inter.eval("int 3 precision noprecision");
+    assert_eq!(inter.precision(), None);
+
+    let list = Value::List(vec![
+        Value::Number(1. / 3.),
+        Value::Integer(4),
+        Value::Symbol("x".to_string()),
+    ]);
+    assert_eq!(format!("{:.2}", list), "[0.33, 4, 'x]");
+    assert_eq!(format!("{:.0}", Value::Number(2.5)), "2");
+    assert_eq!(Value::Number(0.5).to_string(), "0.5");
+}
+
+#[test]
+#[should_panic]
+fn test_integer_division_by_zero() {
+    let mut inter = Interpreter::new();
+    inter.eval("int 0 1 /");
+}
+
+#[test]
+#[should_panic]
+fn test_nth_out_of_range() {
+    let mut inter = Interpreter::new();
+    inter.eval("1 2 2 pack 2 nth");
+}
+
+#[test]
+#[should_panic]
+fn test_pack_too_many() {
+    let mut inter = Interpreter::new();
+    inter.eval("1 2 pack");
+}
+
 #[test]
 #[should_panic]
 fn test_set_type_error() {
@@ -1,5 +1,11 @@
 #![forbid(unsafe_code)]
 
+mod pqueue;
+mod pvec;
+
+pub use pqueue::PQueue;
+pub use pvec::PVec;
+
 use std::rc::Rc;
 
 // compose::begin_private(no_hint)
@@ -43,10 +49,16 @@ impl<T> PStack<T> {
     }
 
     pub fn push(&self, value: T) -> Self {
+        // compose::begin_private(unimplemented)
+        self.push_rc(Rc::new(value))
+        // compose::end_private
+    }
+
+    pub(crate) fn push_rc(&self, value: Rc<T>) -> Self {
         // compose::begin_private(unimplemented)
         Self {
             head: Some(Rc::new(Node {
-                value: Rc::new(value),
+                value,
                 next: self.head.clone(),
             })),
             len: self.len + 1,
@@ -68,6 +80,58 @@ impl<T> PStack<T> {
         // compose::end_private
     }
 
+    /// Returns the `n`-th element from the top, walking `n` nodes.
+    pub fn get(&self, n: usize) -> Option<Rc<T>> {
+        self.nth_node(n).map(|node| Rc::clone(&node.value)) // compose::private(unimplemented)
+    }
+
+    /// Returns the stack with the top `n` elements removed, sharing all of its
+    /// nodes with `self`. Skipping past the bottom yields an empty stack.
+    pub fn skip(&self, n: usize) -> Self {
+        // compose::begin_private(unimplemented)
+        if n >= self.len {
+            return Self::new();
+        }
+
+        Self {
+            head: self.nth_node(n).cloned(),
+            len: self.len - n,
+        }
+        // compose::end_private
+    }
+
+    // compose::begin_private(no_hint)
+    fn nth_node(&self, n: usize) -> Option<&Rc<Node<T>>> {
+        let mut node = self.head.as_ref();
+        for _ in 0..n {
+            node = node?.next.as_ref();
+        }
+        node
+    }
+    // compose::end_private
+
+    /// Returns a stack with the elements in reverse order.
+    ///
+    /// All nodes are freshly allocated, elements themselves are shared.
+    pub fn rev(&self) -> Self {
+        // compose::begin_private(unimplemented)
+        self.iter()
+            .fold(Self::new(), |stack, value| stack.push_rc(value))
+        // compose::end_private
+    }
+
+    /// Returns a stack with the elements of `self` placed on top of `other`.
+    ///
+    /// The nodes of `self` are copied, while `other` is shared as the tail of
+    /// the result, so the cost is O(self.len()) regardless of `other`.
+    pub fn append(&self, other: &Self) -> Self {
+        // compose::begin_private(unimplemented)
+        self.rev()
+            .iter()
+            .fold(other.clone(), |stack, value| stack.push_rc(value))
+        // compose::end_private
+    }
+
     pub fn len(&self) -> usize {
         self.len // compose::private(unimplemented)
     }
@@ -85,6 +149,24 @@ impl<T> PStack<T> {
     }
 }
 
+impl<T: Clone> PStack<T> {
+    /// Pops the top element by value.
+    ///
+    /// If this stack is the only owner of the top node and its value, the value
+    /// is moved out without cloning. Otherwise it is cloned, and other versions
+    /// sharing the node are left intact.
+    pub fn pop_owned(self) -> Option<(T, Self)> {
+        // compose::begin_private(unimplemented)
+        let len = self.len.checked_sub(1)?;
+        let (value, next) = match Rc::try_unwrap(self.head?) {
+            Ok(Node { value, next }) => (Rc::unwrap_or_clone(value), next),
+            Err(node) => (T::clone(&node.value), node.next.clone()),
+        };
+        Some((value, Self { head: next, len }))
+        // compose::end_private
+    }
+}
+
 // compose::begin_private(no_hint)
 pub struct PStackIter<T> {
     next: Option<Rc<Node<T>>>,
@@ -0,0 +1,91 @@
+use crate::PStack;
+
+use std::rc::Rc;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Persistent FIFO queue built on top of two [`PStack`]s.
+///
+/// Elements are pushed onto `back` and popped from `front`. When `front` runs
+/// out, `back` is reversed into a new `front`. The reversal is O(n), but each
+/// element is moved at most once per version, so for a single line of versions
+/// operations are amortized O(1).
+pub struct PQueue<T> {
+    // compose::begin_private
+    front: PStack<T>,
+    back: PStack<T>,
+    // compose::end_private
+}
+
+impl<T> Default for PQueue<T> {
+    fn default() -> Self {
+        // compose::begin_private(unimplemented)
+        Self {
+            front: PStack::new(),
+            back: PStack::new(),
+        }
+        // compose::end_private
+    }
+}
+
+impl<T> Clone for PQueue<T> {
+    fn clone(&self) -> Self {
+        // compose::begin_private(unimplemented)
+        Self {
+            front: self.front.clone(),
+            back: self.back.clone(),
+        }
+        // compose::end_private
+    }
+}
+
+impl<T> PQueue<T> {
+    pub fn new() -> Self {
+        Self::default() // compose::private(unimplemented)
+    }
+
+    pub fn push_back(&self, value: T) -> Self {
+        // compose::begin_private(unimplemented)
+        Self::normalized(self.front.clone(), self.back.push(value))
+        // compose::end_private
+    }
+
+    pub fn pop_front(&self) -> Option<(Rc<T>, Self)> {
+        // compose::begin_private(unimplemented)
+        self.front
+            .pop()
+            .map(|(value, front)| (value, Self::normalized(front, self.back.clone())))
+        // compose::end_private
+    }
+
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len() // compose::private(unimplemented)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.front.is_empty() // compose::private(unimplemented)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Rc<T>> {
+        // compose::begin_private(unimplemented)
+        let mut back = self.back.iter().collect::<Vec<_>>();
+        back.reverse();
+        self.front.iter().chain(back)
+        // compose::end_private
+    }
+
+    // compose::begin_private(no_hint)
+    // Keeps `front` non-empty while the queue is non-empty, so that the head
+    // is always available without reversing.
+    fn normalized(front: PStack<T>, back: PStack<T>) -> Self {
+        if !front.is_empty() {
+            return Self { front, back };
+        }
+
+        Self {
+            front: back.rev(),
+            back: PStack::new(),
+        }
+    }
+    // compose::end_private
+}
@@ -0,0 +1,188 @@
+use std::rc::Rc;
+
+// compose::begin_private(no_hint)
+////////////////////////////////////////////////////////////////////////////////
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+enum Node<T> {
+    Branch(Vec<Rc<Node<T>>>),
+    Leaf(Vec<Rc<T>>),
+}
+
+impl<T> Node<T> {
+    // Builds a chain of single-child branches down to a leaf holding `value`.
+    fn path(level: usize, value: Rc<T>) -> Self {
+        if level == 0 {
+            Node::Leaf(vec![value])
+        } else {
+            Node::Branch(vec![Rc::new(Self::path(level - BITS, value))])
+        }
+    }
+
+    fn push(&self, level: usize, index: usize, value: Rc<T>) -> Self {
+        match self {
+            Node::Leaf(values) => {
+                let mut values = values.clone();
+                values.push(value);
+                Node::Leaf(values)
+            }
+            Node::Branch(children) => {
+                let mut children = children.clone();
+                let pos = (index >> level) & MASK;
+                if let Some(child) = children.get_mut(pos) {
+                    *child = Rc::new(child.push(level - BITS, index, value));
+                } else {
+                    children.push(Rc::new(Self::path(level - BITS, value)));
+                }
+                Node::Branch(children)
+            }
+        }
+    }
+
+    fn update(&self, level: usize, index: usize, value: Rc<T>) -> Self {
+        let pos = (index >> level) & MASK;
+        match self {
+            Node::Leaf(values) => {
+                let mut values = values.clone();
+                values[pos] = value;
+                Node::Leaf(values)
+            }
+            Node::Branch(children) => {
+                let mut children = children.clone();
+                children[pos] = Rc::new(children[pos].update(level - BITS, index, value));
+                Node::Branch(children)
+            }
+        }
+    }
+}
+
+// compose::end_private
+////////////////////////////////////////////////////////////////////////////////
+
+/// Persistent vector backed by a 32-way trie.
+///
+/// `get`, `update` and `push` touch a single root-to-leaf path, so they run in
+/// O(log32 n) and copy at most that many nodes; the rest is shared with the
+/// original version.
+pub struct PVec<T> {
+    // compose::begin_private
+    root: Option<Rc<Node<T>>>,
+    shift: usize,
+    len: usize,
+    // compose::end_private
+}
+
+impl<T> Default for PVec<T> {
+    fn default() -> Self {
+        // compose::begin_private(unimplemented)
+        Self {
+            root: None,
+            shift: 0,
+            len: 0,
+        }
+        // compose::end_private
+    }
+}
+
+impl<T> Clone for PVec<T> {
+    fn clone(&self) -> Self {
+        // compose::begin_private(unimplemented)
+        Self {
+            root: self.root.clone(),
+            shift: self.shift,
+            len: self.len,
+        }
+        // compose::end_private
+    }
+}
+
+impl<T> PVec<T> {
+    pub fn new() -> Self {
+        Self::default() // compose::private(unimplemented)
+    }
+
+    pub fn get(&self, index: usize) -> Option<Rc<T>> {
+        // compose::begin_private(unimplemented)
+        if index >= self.len {
+            return None;
+        }
+
+        let mut node = self.root.as_deref()?;
+        let mut level = self.shift;
+        loop {
+            match node {
+                Node::Branch(children) => {
+                    node = &children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+                Node::Leaf(values) => return Some(Rc::clone(&values[index & MASK])),
+            }
+        }
+        // compose::end_private
+    }
+
+    /// Returns a vector with the element at `index` replaced by `value`, or
+    /// `None` if `index` is out of bounds.
+    pub fn update(&self, index: usize, value: T) -> Option<Self> {
+        // compose::begin_private(unimplemented)
+        if index >= self.len {
+            return None;
+        }
+
+        let root = self.root.as_ref()?;
+        Some(Self {
+            root: Some(Rc::new(root.update(self.shift, index, Rc::new(value)))),
+            shift: self.shift,
+            len: self.len,
+        })
+        // compose::end_private
+    }
+
+    pub fn push(&self, value: T) -> Self {
+        // compose::begin_private(unimplemented)
+        let value = Rc::new(value);
+        let (root, shift) = match &self.root {
+            None => (Node::Leaf(vec![value]), 0),
+            Some(root) if self.len == WIDTH << self.shift => {
+                let shift = self.shift + BITS;
+                let sibling = Rc::new(Node::path(self.shift, value));
+                (Node::Branch(vec![root.clone(), sibling]), shift)
+            }
+            Some(root) => (root.push(self.shift, self.len, value), self.shift),
+        };
+
+        Self {
+            root: Some(Rc::new(root)),
+            shift,
+            len: self.len + 1,
+        }
+        // compose::end_private
+    }
+
+    pub fn len(&self) -> usize {
+        self.len // compose::private(unimplemented)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0 // compose::private(unimplemented)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Rc<T>> {
+        // compose::begin_private(unimplemented)
+        let vec = self.clone();
+        (0..vec.len).map(move |i| vec.get(i).unwrap())
+        // compose::end_private
+    }
+}
+
+impl<T> FromIterator<T> for PVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        // compose::begin_private(unimplemented)
+        iter.into_iter()
+            .fold(Self::new(), |vec, value| vec.push(value))
+        // compose::end_private
+    }
+}
@@ -1,4 +1,6 @@
-use pstack::PStack;
+use pstack::{PQueue, PStack, PVec};
+
+use std::{cell::Cell, rc::Rc};
 
 #[test]
 fn test_simple() {
@@ -20,6 +22,7 @@ fn test_simple() {
 }
 
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn test_persistence() {
     let mut stacks = vec![PStack::new()];
     for i in 0..100 {
@@ -100,3 +103,178 @@ fn test_iter_parallel() {
         assert_eq!(iter_two.next().as_deref().copied(), Some(200 - i - 1));
     }
 }
+
+#[test]
+fn test_queue() {
+    let mut queue = PQueue::new();
+    assert!(queue.is_empty());
+
+    for i in 0..10 {
+        queue = queue.push_back(i);
+        assert_eq!(queue.len(), i + 1);
+    }
+
+    let snapshot = queue.clone();
+    for i in 0..5 {
+        let (front, tail) = queue.pop_front().unwrap();
+        assert_eq!(*front, i);
+        queue = tail;
+    }
+    for i in 10..15 {
+        queue = queue.push_back(i);
+    }
+
+    assert_eq!(
+        queue.iter().map(|x| *x).collect::<Vec<_>>(),
+        (5..15).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        snapshot.iter().map(|x| *x).collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+
+    for i in 5..15 {
+        let (front, tail) = queue.pop_front().unwrap();
+        assert_eq!(*front, i);
+        queue = tail;
+    }
+    assert!(queue.is_empty());
+    assert!(queue.pop_front().is_none());
+}
+
+#[test]
+fn test_pop_owned() {
+    struct Counted(i32, Rc<Cell<usize>>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.1.set(self.1.get() + 1);
+            Self(self.0, self.1.clone())
+        }
+    }
+
+    let clones = Rc::new(Cell::new(0));
+    let mut stack = PStack::new();
+    for i in 0..10 {
+        stack = stack.push(Counted(i, clones.clone()));
+    }
+
+    let shared = stack.clone();
+    let (top, mut stack) = stack.pop_owned().unwrap();
+    assert_eq!(top.0, 9);
+    assert_eq!(clones.get(), 1);
+    assert_eq!(shared.len(), 10);
+    assert_eq!(shared.iter().next().unwrap().0, 9);
+    drop(shared);
+
+    for i in (0..9).rev() {
+        let (top, tail) = stack.pop_owned().unwrap();
+        assert_eq!(top.0, i);
+        stack = tail;
+    }
+    assert_eq!(clones.get(), 1);
+    assert!(stack.is_empty());
+    assert!(stack.pop_owned().is_none());
+}
+
+#[test]
+fn test_rev_append() {
+    let mut left = PStack::new();
+    let mut right = PStack::new();
+    for i in 0..5 {
+        left = left.push(i);
+        right = right.push(i + 5);
+    }
+
+    let rev = left.rev();
+    assert_eq!(rev.len(), 5);
+    assert_eq!(rev.iter().map(|x| *x).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    assert_eq!(left.iter().map(|x| *x).collect::<Vec<_>>(), [4, 3, 2, 1, 0]);
+
+    let joined = left.append(&right);
+    assert_eq!(joined.len(), 10);
+    assert_eq!(
+        joined.iter().map(|x| *x).collect::<Vec<_>>(),
+        [4, 3, 2, 1, 0, 9, 8, 7, 6, 5]
+    );
+    assert!(Rc::ptr_eq(
+        &joined.iter().nth(5).unwrap(),
+        &right.iter().next().unwrap()
+    ));
+
+    assert!(PStack::<i32>::new().rev().is_empty());
+    assert_eq!(PStack::new().append(&right).len(), 5);
+    assert_eq!(left.append(&PStack::new()).len(), 5);
+}
+
+#[test]
+fn test_get_skip() {
+    let mut stack = PStack::new();
+    for i in 0..10 {
+        stack = stack.push(i);
+    }
+
+    for n in 0..10 {
+        assert_eq!(stack.get(n).as_deref().copied(), Some(9 - n));
+    }
+    assert!(stack.get(10).is_none());
+
+    let suffix = stack.skip(3);
+    assert_eq!(suffix.len(), 7);
+    assert_eq!(
+        suffix.iter().map(|x| *x).collect::<Vec<_>>(),
+        [6, 5, 4, 3, 2, 1, 0]
+    );
+    assert!(Rc::ptr_eq(&suffix.get(0).unwrap(), &stack.get(3).unwrap()));
+
+    assert_eq!(stack.skip(0).len(), 10);
+    assert!(stack.skip(10).is_empty());
+    assert!(stack.skip(100).is_empty());
+}
+
+#[test]
+fn test_vec() {
+    let mut versions = vec![PVec::new()];
+    for i in 0..2000 {
+        let vec = versions.last().unwrap().push(i);
+        assert_eq!(vec.len(), i + 1);
+        versions.push(vec);
+    }
+
+    for (len, vec) in versions.iter().enumerate() {
+        assert_eq!(vec.len(), len);
+        for i in 0..len {
+            assert_eq!(vec.get(i).as_deref().copied(), Some(i));
+        }
+        assert!(vec.get(len).is_none());
+    }
+
+    let vec = versions.last().unwrap();
+    let mut updated = vec.clone();
+    for i in (0..2000).step_by(7) {
+        updated = updated.update(i, i * 10).unwrap();
+    }
+    assert!(updated.update(2000, 0).is_none());
+
+    for i in 0..2000 {
+        assert_eq!(*vec.get(i).unwrap(), i);
+        let expected = if i % 7 == 0 { i * 10 } else { i };
+        assert_eq!(*updated.get(i).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_vec_iter() {
+    let vec = (0..100).collect::<PVec<_>>();
+    let longer = vec.push(100);
+
+    assert_eq!(
+        vec.iter().map(|x| *x).collect::<Vec<_>>(),
+        (0..100).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        longer.iter().map(|x| *x).collect::<Vec<_>>(),
+        (0..101).collect::<Vec<_>>()
+    );
+    assert!(PVec::<i32>::new().iter().next().is_none());
+}
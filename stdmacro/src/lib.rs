@@ -1,37 +1,160 @@
 #![forbid(unsafe_code)]
 
+/// Number of comma-separated expressions, usable in const contexts.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __count {
+    ($($elem:expr),* $(,)?) => {
+        <[()]>::len(&[$($crate::__count!(@unit $elem)),*])
+    };
+    (@unit $elem:expr) => {
+        ()
+    };
+}
+
+/// `deque![a, b, ...]`, `deque![elem; n]`, or `deque!(cap: n)` and
+/// `deque!(cap: n; a, b, ...)` to reserve room for `n` elements.
 #[macro_export]
 macro_rules! deque {
-    ($($elem:expr),*) => {{
-            let mut deq = ::std::collections::VecDeque::with_capacity(1);
+    (cap: $cap:expr) => {
+        ::std::collections::VecDeque::with_capacity($cap)
+    };
+    (cap: $cap:expr; $($elem:expr),* $(,)?) => {{
+            let mut deq = ::std::collections::VecDeque::with_capacity($cap);
             $(deq.push_back($elem);)*
             deq
     }};
-    ($elem:expr; $cap:literal) => {{
-            let mut deq = ::std::collections::VecDeque::with_capacity($cap);
-            deq.resize($cap, $elem);
+    ($($elem:expr),* $(,)?) => {
+        $crate::deque!(cap: $crate::__count!($($elem),*); $($elem),*)
+    };
+    ($elem:expr; $len:expr) => {{
+            let len = $len;
+            let mut deq = ::std::collections::VecDeque::with_capacity(len);
+            deq.resize(len, $elem);
             deq
     }};
 }
 
+/// `sorted_vec![a, b, ...]`, or with a prefix selecting the order:
+/// `by_key = f;` or `by = cmp;`, optionally followed by `, dedup` to drop all
+/// but the first of equal elements. `dedup;` alone uses the natural order.
 #[macro_export]
 macro_rules! sorted_vec {
     () => {
         Vec::new()
     };
-    ($($elem:expr),*) => {{
-        let mut vec = ::std::vec::Vec::new();
+    (dedup; $($elem:expr),* $(,)?) => {{
+        let mut vec = $crate::sorted_vec![$($elem),*];
+        vec.dedup();
+        vec
+    }};
+    (by_key = $key:expr; $($elem:expr),* $(,)?) => {{
+        let mut vec = $crate::sorted_vec!(@collect $($elem),*);
+        vec.sort_by_key($key);
+        vec
+    }};
+    (by_key = $key:expr, dedup; $($elem:expr),* $(,)?) => {{
+        let key = $key;
+        let mut vec = $crate::sorted_vec!(@collect $($elem),*);
+        vec.sort_by_key(&key);
+        vec.dedup_by(|a, b| key(&*a) == key(&*b));
+        vec
+    }};
+    (by = $cmp:expr; $($elem:expr),* $(,)?) => {{
+        let mut vec = $crate::sorted_vec!(@collect $($elem),*);
+        vec.sort_by($cmp);
+        vec
+    }};
+    (by = $cmp:expr, dedup; $($elem:expr),* $(,)?) => {{
+        let cmp = $cmp;
+        let mut vec = $crate::sorted_vec!(@collect $($elem),*);
+        vec.sort_by(&cmp);
+        vec.dedup_by(|a, b| cmp(&*a, &*b) == ::std::cmp::Ordering::Equal);
+        vec
+    }};
+    (@collect $($elem:expr),*) => {{
+        let mut vec = ::std::vec::Vec::with_capacity($crate::__count!($($elem),*));
         $(vec.push($elem);)*
+        vec
+    }};
+    ($($elem:expr),*) => {{
+        let mut vec = $crate::sorted_vec!(@collect $($elem),*);
         vec.sort_unstable();
         vec
     }};
 }
 
+/// `map! { k => v, ... }`, or `map!(cap: n; k => v, ...)` to reserve room
+/// for `n` entries instead of just the listed ones.
 #[macro_export]
 macro_rules! map {
+    (cap: $cap:expr; $($k:expr => $v:expr),* $(,)?) => {{
+            let mut map = ::std::collections::HashMap::with_capacity($cap);
+            $(map.insert($k, $v);)*
+            map
+    }};
+    ($($k:expr => $v:expr),* $(,)?) => {
+        $crate::map!(cap: $crate::__count!($($k),*); $($k => $v),*)
+    };
+}
+
+#[macro_export]
+macro_rules! btree_map {
     ($($k:expr => $v:expr),* $(,)?) => {{
-            let mut map = ::std::collections::HashMap::new();
+            let mut map = ::std::collections::BTreeMap::new();
             $(map.insert($k, $v);)*
             map
     }};
 }
+
+#[macro_export]
+macro_rules! hash_set {
+    ($($elem:expr),* $(,)?) => {{
+            let mut set = ::std::collections::HashSet::with_capacity($crate::__count!($($elem),*));
+            $(set.insert($elem);)*
+            set
+    }};
+}
+
+#[macro_export]
+macro_rules! btree_set {
+    ($($elem:expr),* $(,)?) => {{
+            let mut set = ::std::collections::BTreeSet::new();
+            $(set.insert($elem);)*
+            set
+    }};
+}
+
+/// Concatenates string-like values into a `String` allocated exactly once.
+#[macro_export]
+macro_rules! concat_strings {
+    ($($part:expr),* $(,)?) => {
+        $crate::join!(""; $($part),*)
+    };
+}
+
+/// `join!(sep; a, b, ...)` joins string-like values with `sep` into a
+/// `String` allocated exactly once.
+#[macro_export]
+macro_rules! join {
+    ($sep:expr; $($part:expr),* $(,)?) => {
+        match (
+            ::std::convert::AsRef::<str>::as_ref(&$sep),
+            [$(::std::convert::AsRef::<str>::as_ref(&$part)),*],
+        ) {
+            (sep, parts) => {
+                let parts: [&str; $crate::__count!($($part),*)] = parts;
+                let len = parts.iter().map(|part| part.len()).sum::<usize>()
+                    + sep.len() * parts.len().saturating_sub(1);
+                let mut string = ::std::string::String::with_capacity(len);
+                for (i, part) in parts.into_iter().enumerate() {
+                    if i > 0 {
+                        string.push_str(sep);
+                    }
+                    string.push_str(part);
+                }
+                string
+            }
+        }
+    };
+}
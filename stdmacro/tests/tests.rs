@@ -1,9 +1,12 @@
 use ::std::{
-    collections::{HashMap as __HashMap, VecDeque as __VecDeque},
+    collections::{
+        BTreeMap as __BTreeMap, BTreeSet as __BTreeSet, HashMap as __HashMap, HashSet as __HashSet,
+        VecDeque as __VecDeque,
+    },
     vec as __vec,
 };
 
-use stdmacro::{deque, map, sorted_vec};
+use stdmacro::{btree_map, btree_set, concat_strings, deque, hash_set, join, map, sorted_vec};
 
 #[allow(unused)]
 macro_rules! vec {
@@ -13,7 +16,10 @@ macro_rules! vec {
 #[allow(unused)]
 mod std {
     mod collections {
+        pub struct BTreeMap;
+        pub struct BTreeSet;
         pub struct HashMap;
+        pub struct HashSet;
         pub struct Vec;
         pub struct VecDeque;
     }
@@ -43,6 +49,19 @@ fn test_deque() {
 
     let d3: __VecDeque<i32> = deque![];
     assert_eq!(d3, __VecDeque::new());
+    assert_eq!(d3.capacity(), 0);
+
+    const N: usize = 4;
+    let d5 = deque![7; N * 2];
+    assert_eq!(d5.len(), 8);
+    assert!(d5.iter().all(|x| *x == 7));
+
+    let d6: __VecDeque<i32> = deque!(cap: N);
+    assert!(d6.is_empty() && d6.capacity() >= N);
+
+    let d7 = deque!(cap: 16; 1, 2,);
+    assert_eq!(d7, [1, 2]);
+    assert!(d7.capacity() >= 16);
 
     let mut d4 = deque![Wrapper(0), Wrapper(10), Wrapper(135)];
     assert_eq!(d4.pop_back().unwrap().0, 135);
@@ -60,6 +79,9 @@ fn test_sorted_vec() {
 
     let v2 = sorted_vec![Comparable(5), Comparable(10), Comparable(7)];
     assert!(v2 == __vec![Comparable(5), Comparable(7), Comparable(10)]);
+
+    let v3 = sorted_vec!(dedup; 3, 1, 3, 2, 1);
+    assert_eq!(v3, __vec![1, 2, 3]);
 }
 
 #[test]
@@ -78,4 +100,99 @@ fn test_map() {
 
     let m3: __HashMap<String, i32> = map! {};
     assert_eq!(__HashMap::<String, i32>::new(), m3);
+
+    let m4 = map!(cap: 100; 1 => 2, 3 => 4,);
+    assert_eq!(m4.len(), 2);
+    assert!(m4.capacity() >= 100);
+
+    let m5: __HashMap<i32, i32> = map!(cap: 10;);
+    assert!(m5.is_empty() && m5.capacity() >= 10);
+}
+
+#[test]
+fn test_btree_map() {
+    let m = btree_map! {
+        "foo" => 10,
+        "bar" => 20,
+    };
+    assert_eq!(
+        m.into_iter().collect::<Vec<_>>(),
+        [("bar", 20), ("foo", 10)]
+    );
+
+    let m2 = btree_map! {
+        Comparable(220) => Wrapper(30)
+    };
+    assert_eq!(m2[&Comparable(220)].0, 30);
+
+    let m3: __BTreeMap<String, i32> = btree_map! {};
+    assert_eq!(__BTreeMap::<String, i32>::new(), m3);
+}
+
+#[test]
+fn test_hash_set() {
+    let s = hash_set![1, 2, 3, 2,];
+    assert_eq!(s, __HashSet::from([1, 2, 3]));
+
+    let s2 = hash_set![Hashable(5)];
+    assert!(s2.contains(&Hashable(5)));
+
+    let s3: __HashSet<i32> = hash_set![];
+    assert!(s3.is_empty());
+}
+
+#[test]
+fn test_btree_set() {
+    let s = btree_set![3, 1, 2, 1,];
+    assert_eq!(s.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+
+    let s2 = btree_set![Comparable(7), Comparable(5)];
+    assert_eq!(s2.first().unwrap().0, 5);
+
+    let s3: __BTreeSet<i32> = btree_set![];
+    assert_eq!(s3, __BTreeSet::new());
+}
+
+#[test]
+fn test_preallocation() {
+    assert_eq!(deque![1, 2, 3, 4, 5].capacity(), 5);
+    assert_eq!(sorted_vec![3, 1, 2].capacity(), 3);
+
+    let m = map! { 1 => 1, 2 => 2, 3 => 3 };
+    assert!(m.capacity() >= 3);
+    let s = hash_set![1, 2, 3, 4];
+    assert!(s.capacity() >= 4);
+}
+
+#[test]
+fn test_sorted_vec_ordering() {
+    let by_key = sorted_vec!(by_key = |p: &(i32, &str)| p.0; (2, "b"), (1, "a"), (2, "c"));
+    assert_eq!(by_key, __vec![(1, "a"), (2, "b"), (2, "c")]);
+
+    let by_key = sorted_vec!(by_key = |p: &(i32, &str)| p.0, dedup; (2, "b"), (1, "a"), (2, "c"),);
+    assert_eq!(by_key, __vec![(1, "a"), (2, "b")]);
+
+    let by = sorted_vec!(by = |a: &i32, b: &i32| b.cmp(a); 1, 3, 2);
+    assert_eq!(by, __vec![3, 2, 1]);
+
+    let by = sorted_vec!(by = |a: &Wrapper, b: &Wrapper| a.0.cmp(&b.0), dedup; Wrapper(2), Wrapper(1), Wrapper(2));
+    assert_eq!(by.iter().map(|w| w.0).collect::<Vec<_>>(), [1, 2]);
+
+    let words = sorted_vec!(by_key = String::len; "ccc".into(), "a".into(), "bb".into());
+    assert_eq!(words, __vec!["a", "bb", "ccc"]);
+}
+
+#[test]
+fn test_string_building() {
+    let name = String::from("world");
+    let s = concat_strings!("hello, ", name, format!("{}", '!'));
+    assert_eq!(s, "hello, world!");
+    assert_eq!(s.capacity(), s.len());
+    assert_eq!(concat_strings!(), "");
+
+    let j = join!(", "; "a", String::from("bb"), &name,);
+    assert_eq!(j, "a, bb, world");
+    assert_eq!(j.capacity(), j.len());
+    assert_eq!(join!(String::from("-"); "x"), "x");
+    assert_eq!(join!("-";), "");
 }
@@ -0,0 +1,92 @@
+use std::thread;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{ProxyError, Result};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Faults injected on the relay path, which turns the proxy into a test
+/// harness for unreliable networks. Everything is disabled by default.
+#[derive(Clone, Debug, Default)]
+pub struct ChaosConfig {
+    /// Delay before relaying every chunk of data.
+    pub latency: Duration,
+    /// Additional random delay, uniformly distributed in `0..=jitter`.
+    pub jitter: Duration,
+    /// Probability for every relayed byte to get a random bit flipped.
+    pub corrupt_probability: f64,
+    /// Probability for every relayed chunk to abort the connection instead.
+    pub reset_probability: f64,
+    /// Seed for reproducible runs, random if not set.
+    pub seed: Option<u64>,
+}
+
+impl ChaosConfig {
+    /// Fails unless both probabilities are in `[0, 1]`.
+    pub(crate) fn validate(&self) -> Result<()> {
+        for (name, probability) in [
+            ("corrupt_probability", self.corrupt_probability),
+            ("reset_probability", self.reset_probability),
+        ] {
+            if !(0. ..=1.).contains(&probability) {
+                return Err(ProxyError::InvalidConfig(format!(
+                    "{name} should be in [0, 1], got {probability}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) enum Fault {
+    None,
+    Reset,
+}
+
+pub(crate) struct Chaos {
+    config: ChaosConfig,
+    rng: StdRng,
+}
+
+impl Chaos {
+    /// `stream_id` tells apart the relays of a run, so that they don't all
+    /// replay the same fault sequence for a fixed seed.
+    pub(crate) fn new(config: &ChaosConfig, stream_id: u64) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed ^ stream_id.rotate_left(32)),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            config: config.clone(),
+            rng,
+        }
+    }
+
+    /// Applies the faults to a chunk that is about to be relayed.
+    pub(crate) fn apply(&mut self, chunk: &mut [u8]) -> Fault {
+        let mut delay = self.config.latency;
+        if !self.config.jitter.is_zero() {
+            delay += self.rng.gen_range(Duration::ZERO..=self.config.jitter);
+        }
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+
+        if self.rng.gen_bool(self.config.reset_probability) {
+            return Fault::Reset;
+        }
+
+        if self.config.corrupt_probability > 0. {
+            for byte in chunk {
+                if self.rng.gen_bool(self.config.corrupt_probability) {
+                    *byte ^= 1 << self.rng.gen_range(0..8);
+                }
+            }
+        }
+
+        Fault::None
+    }
+}
@@ -0,0 +1,29 @@
+use std::{io, net::SocketAddr};
+
+use thiserror::Error;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Error, Debug)]
+pub enum ProxyError {
+    #[error("failed to resolve address: {0}")]
+    Resolve(#[source] io::Error),
+    #[error("failed to bind to {addrs:?}: {source}")]
+    Bind {
+        addrs: Vec<SocketAddr>,
+        source: io::Error,
+    },
+    #[error("failed to connect to destination {destination}: {source}")]
+    Connect {
+        destination: SocketAddr,
+        source: io::Error,
+    },
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    #[error("no route for {}", .0.as_deref().unwrap_or("a client without a host name"))]
+    NoRoute(Option<String>),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ProxyError>;
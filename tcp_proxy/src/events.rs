@@ -0,0 +1,182 @@
+use std::fmt;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// A step in the life of a client connection. Every accepted connection
+/// ends with exactly one `Closed`, whether it got to the destination or not.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    Accepted {
+        id: u64,
+        client: SocketAddr,
+    },
+    Connected {
+        id: u64,
+        server: SocketAddr,
+    },
+    Closed {
+        id: u64,
+        /// Time since the connection was accepted.
+        duration: Duration,
+        bytes_client_to_server: u64,
+        bytes_server_to_client: u64,
+        reason: CloseReason,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// Both sides finished sending.
+    Eof,
+    IdleTimeout,
+    /// Closed by `ProxyHandle::shutdown`.
+    Shutdown,
+    Error(String),
+}
+
+impl ConnectionEvent {
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Accepted { id, .. } | Self::Connected { id, .. } | Self::Closed { id, .. } => *id,
+        }
+    }
+
+    /// The event as a single-line JSON object, e.g.
+    /// `{"event":"accepted","id":0,"client":"127.0.0.1:51234"}`.
+    pub fn to_json(&self) -> String {
+        format!("{{{}}}", self.json_fields())
+    }
+
+    fn json_fields(&self) -> String {
+        match self {
+            Self::Accepted { id, client } => {
+                format!(r#""event":"accepted","id":{id},"client":"{client}""#)
+            }
+            Self::Connected { id, server } => {
+                format!(r#""event":"connected","id":{id},"server":"{server}""#)
+            }
+            Self::Closed {
+                id,
+                duration,
+                bytes_client_to_server,
+                bytes_server_to_client,
+                reason,
+            } => {
+                let reason = match reason {
+                    CloseReason::Eof => r#""reason":"eof""#.to_string(),
+                    CloseReason::IdleTimeout => r#""reason":"idle_timeout""#.to_string(),
+                    CloseReason::Shutdown => r#""reason":"shutdown""#.to_string(),
+                    CloseReason::Error(error) => {
+                        format!(r#""reason":"error","error":{}"#, json_string(error))
+                    }
+                };
+                format!(
+                    "\"event\":\"closed\",\"id\":{id},\"duration_ms\":{},\
+                    \"bytes_client_to_server\":{bytes_client_to_server},\
+                    \"bytes_server_to_client\":{bytes_server_to_client},{reason}",
+                    duration.as_millis()
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accepted { id, client } => write!(f, "Connection {id} accepted from {client}"),
+            Self::Connected { id, server } => write!(f, "Connection {id} connected to {server}"),
+            Self::Closed {
+                id,
+                duration,
+                bytes_client_to_server,
+                bytes_server_to_client,
+                reason,
+            } => write!(
+                f,
+                "Connection {id} closed after {duration:?} ({reason}), \
+                client -> server: {bytes_client_to_server} bytes, \
+                server -> client: {bytes_server_to_client} bytes",
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eof => f.write_str("end of stream"),
+            Self::IdleTimeout => f.write_str("idle timeout"),
+            Self::Shutdown => f.write_str("proxy shutdown"),
+            Self::Error(error) => write!(f, "error: {error}"),
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Where the proxy sends its [`ConnectionEvent`]s, see
+/// [`ProxyConfig::event_log`](crate::ProxyConfig::event_log). The sink is
+/// called from the connection threads.
+#[derive(Clone)]
+pub struct EventLog {
+    sink: Arc<dyn Fn(&ConnectionEvent) + Send + Sync>,
+}
+
+impl EventLog {
+    pub fn new(sink: impl Fn(&ConnectionEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+        }
+    }
+
+    /// Writes every event to `writer` as a line of JSON, see
+    /// [`ConnectionEvent::to_json`], with a `timestamp_ms` field added.
+    /// Write errors are ignored.
+    pub fn json_lines(writer: impl Write + Send + 'static) -> Self {
+        let writer = Mutex::new(writer);
+        Self::new(move |event| {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let line = format!("{{\"timestamp_ms\":{timestamp},{}}}\n", event.json_fields());
+            let mut writer = writer.lock().unwrap();
+            let _ = writer
+                .write_all(line.as_bytes())
+                .and_then(|_| writer.flush());
+        })
+    }
+
+    pub(crate) fn emit(&self, event: &ConnectionEvent) {
+        (self.sink)(event)
+    }
+}
+
+impl fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventLog").finish_non_exhaustive()
+    }
+}
@@ -1,68 +1,800 @@
 #![forbid(unsafe_code)]
 
-use std::net::{TcpListener, TcpStream};
-use std::thread;
+mod chaos;
+mod error;
+mod events;
+mod proxy_protocol;
+mod routing;
+mod socks5;
+mod stats;
+
+pub use chaos::ChaosConfig;
+pub use error::{ProxyError, Result};
+pub use events::{CloseReason, ConnectionEvent, EventLog};
+pub use proxy_protocol::{read_proxy_header, ProxyHeader, ProxyProtocol};
+pub use routing::RoutingTable;
+pub use socks5::Socks5Config;
+pub use stats::{ConnectionStats, ProxyStats};
+
+use chaos::{Chaos, Fault};
+use stats::{Direction, Traffic};
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use log::{error, info};
 
-const LOCAL_HOST: &str = "127.0.0.1";
+const RELAY_BUFFER_SIZE: usize = 8 * 1024;
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
-pub fn run_proxy(port: u32, destination: String) {
-    let listener = TcpListener::bind(format!("{LOCAL_HOST}:{port}")).unwrap();
+////////////////////////////////////////////////////////////////////////////////
 
-    info!("Proxy is listening on port: {}", port);
+#[derive(Clone, Debug, Default)]
+pub struct ProxyConfig {
+    /// If set, aggregate stats are logged with this period.
+    pub stats_interval: Option<Duration>,
+    /// Limit on establishing a connection to a single destination address.
+    pub connect_timeout: Option<Duration>,
+    /// If set, a connection is closed once no bytes have been relayed in
+    /// either direction for this long.
+    pub idle_timeout: Option<Duration>,
+    /// If set, every connection to the destination starts with a PROXY
+    /// protocol header carrying the client address.
+    pub send_proxy_protocol: Option<ProxyProtocol>,
+    /// Expect clients to start with a PROXY protocol header (i.e. the proxy
+    /// itself is behind a load balancer). The addresses from the header are
+    /// then passed on instead of the client's own ones.
+    pub accept_proxy_protocol: bool,
+    /// Fault injection on the relay path.
+    pub chaos: Option<ChaosConfig>,
+    /// If set, failed connections to the destination are retried, so that
+    /// clients survive a short upstream restart.
+    pub reconnect: Option<Backoff>,
+    /// Receives the [`ConnectionEvent`]s. Without it they are logged at the
+    /// info level.
+    pub event_log: Option<EventLog>,
+}
+
+/// Exponential backoff: the `n`-th retry happens after
+/// `min(initial_delay * 2^n, max_delay)`.
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    pub retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            retries: 5,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Backoff {
+    fn delay(&self, retry: u32) -> Duration {
+        self.initial_delay
+            .checked_mul(1 << retry.min(31))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+pub fn run_proxy(bind: impl ToSocketAddrs, destination: impl ToSocketAddrs) -> Result<()> {
+    run_proxy_with_config(bind, destination, ProxyConfig::default())
+}
+
+pub fn run_proxy_with_config(
+    bind: impl ToSocketAddrs,
+    destination: impl ToSocketAddrs,
+    config: ProxyConfig,
+) -> Result<()> {
+    spawn_proxy_with_config(bind, destination, config)?.wait();
+    Ok(())
+}
+
+/// Starts the proxy in a background thread and returns a handle to control it.
+///
+/// Both `bind` and `destination` are resolved once, at startup. If either
+/// resolves to several addresses, they are tried in order.
+pub fn spawn_proxy(
+    bind: impl ToSocketAddrs,
+    destination: impl ToSocketAddrs,
+) -> Result<ProxyHandle> {
+    spawn_proxy_with_config(bind, destination, ProxyConfig::default())
+}
+
+pub fn spawn_proxy_with_config(
+    bind: impl ToSocketAddrs,
+    destination: impl ToSocketAddrs,
+    config: ProxyConfig,
+) -> Result<ProxyHandle> {
+    let destination = Destination::Fixed(resolve(destination)?.into());
+    spawn(bind, destination, config)
+}
+
+pub fn run_socks5_proxy(
+    bind: impl ToSocketAddrs,
+    socks: Socks5Config,
+    config: ProxyConfig,
+) -> Result<()> {
+    spawn_socks5_proxy(bind, socks, config)?.wait();
+    Ok(())
+}
+
+/// Starts a SOCKS5 proxy: instead of a fixed destination, every client
+/// chooses its own target during the handshake.
+pub fn spawn_socks5_proxy(
+    bind: impl ToSocketAddrs,
+    socks: Socks5Config,
+    config: ProxyConfig,
+) -> Result<ProxyHandle> {
+    spawn(bind, Destination::Socks5(Arc::new(socks)), config)
+}
+
+pub fn run_routing_proxy(
+    bind: impl ToSocketAddrs,
+    routes: RoutingTable,
+    config: ProxyConfig,
+) -> Result<()> {
+    spawn_routing_proxy(bind, routes, config)?.wait();
+    Ok(())
+}
+
+/// Starts a proxy that fronts several backends: the destination is looked up
+/// in `routes` by the HTTP `Host` header or the TLS SNI the client sends
+/// first. Protocols where the server speaks first need a default route and
+/// an `idle_timeout`: the client is routed once it has been silent that long.
+pub fn spawn_routing_proxy(
+    bind: impl ToSocketAddrs,
+    routes: RoutingTable,
+    config: ProxyConfig,
+) -> Result<ProxyHandle> {
+    spawn(bind, Destination::Routed(Arc::new(routes)), config)
+}
+
+#[derive(Clone)]
+enum Destination {
+    Fixed(Arc<[SocketAddr]>),
+    Socks5(Arc<Socks5Config>),
+    Routed(Arc<RoutingTable>),
+}
+
+fn spawn(
+    bind: impl ToSocketAddrs,
+    destination: Destination,
+    config: ProxyConfig,
+) -> Result<ProxyHandle> {
+    if let Some(chaos) = &config.chaos {
+        chaos.validate()?;
+    }
+    let addrs = resolve(bind)?;
+    let listener = match TcpListener::bind(&addrs[..]) {
+        Ok(listener) => listener,
+        Err(source) => return Err(ProxyError::Bind { addrs, source }),
+    };
+    let local_addr = listener.local_addr()?;
+
+    info!("Proxy is listening on {local_addr}");
+
+    let shared = Arc::new(Shared {
+        config,
+        ..Default::default()
+    });
+    let acceptor = {
+        let shared = shared.clone();
+        thread::Builder::new()
+            .name("proxy-acceptor".into())
+            .spawn(move || accept_connections(listener, destination, shared))?
+    };
+    let reporter = match shared.config.stats_interval {
+        Some(interval) => {
+            let shared = shared.clone();
+            Some(
+                thread::Builder::new()
+                    .name("proxy-stats".into())
+                    .spawn(move || report_stats(&shared, interval))?,
+            )
+        }
+        None => None,
+    };
+
+    Ok(ProxyHandle {
+        local_addr,
+        shared,
+        acceptor,
+        reporter,
+    })
+}
+
+fn resolve(addr: impl ToSocketAddrs) -> Result<Vec<SocketAddr>> {
+    let addrs = addr
+        .to_socket_addrs()
+        .map_err(ProxyError::Resolve)?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(ProxyError::Resolve(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "address resolved to nothing",
+        )));
+    }
+    Ok(addrs)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Handle to a running proxy.
+///
+/// Dropping the handle leaves the proxy running in the background.
+pub struct ProxyHandle {
+    local_addr: SocketAddr,
+    shared: Arc<Shared>,
+    acceptor: JoinHandle<Vec<JoinHandle<()>>>,
+    reporter: Option<JoinHandle<()>>,
+}
+
+impl ProxyHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stats(&self) -> ProxyStats {
+        self.shared.stats()
+    }
+
+    /// Stops accepting new connections and waits for the worker threads.
+    ///
+    /// With `drain_timeout` set, in-flight connections are given that much time
+    /// to finish on their own. Whatever is still open afterwards (or right away,
+    /// without a timeout) is closed forcibly.
+    pub fn shutdown(self, drain_timeout: Option<Duration>) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+
+        // Wake the acceptor up, it is blocked in accept().
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(match wake_addr {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        let _ = TcpStream::connect(wake_addr);
+
+        let workers = self.acceptor.join().unwrap();
+        info!("Proxy stopped accepting connections");
+
+        if let Some(timeout) = drain_timeout {
+            self.shared.drain(timeout);
+        }
+        self.shared.close_all();
+
+        join_workers(workers);
+        if let Some(reporter) = self.reporter {
+            reporter.thread().unpark();
+            reporter.join().unwrap();
+        }
+        info!("Proxy is shut down");
+    }
+
+    /// Blocks the current thread for as long as the proxy is running.
+    pub fn wait(self) {
+        join_workers(self.acceptor.join().unwrap());
+    }
+}
+
+fn join_workers(workers: Vec<JoinHandle<()>>) {
+    for worker in workers {
+        if worker.join().is_err() {
+            error!("Connection thread panicked");
+        }
+    }
+}
+
+fn report_stats(shared: &Shared, interval: Duration) {
+    let mut next = Instant::now() + interval;
+    while !shared.shutdown.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now < next {
+            thread::park_timeout(next - now);
+            continue;
+        }
+        info!("Stats: {}", shared.stats());
+        next += interval;
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Shared {
+    config: ProxyConfig,
+    shutdown: AtomicBool,
+    connections: Mutex<Connections>,
+    drained: Condvar,
+}
+
+#[derive(Default)]
+struct Connections {
+    next_id: u64,
+    active: HashMap<u64, Connection>,
+    /// Clients that haven't been connected to a destination yet.
+    handshaking: HashMap<u64, TcpStream>,
+    closed: bool,
+    accepted: u64,
+    connect_failures: u64,
+    completed: u64,
+    completed_duration: Duration,
+    bytes_client_to_server: u64,
+    bytes_server_to_client: u64,
+}
+
+struct Connection {
+    client: TcpStream,
+    server: TcpStream,
+    started: Instant,
+    traffic: Arc<Traffic>,
+}
+
+impl Connection {
+    fn stats(&self) -> io::Result<ConnectionStats> {
+        Ok(ConnectionStats {
+            client: self.client.peer_addr()?,
+            server: self.server.peer_addr()?,
+            duration: self.started.elapsed(),
+            bytes_client_to_server: self.traffic.get(Direction::ClientToServer),
+            bytes_server_to_client: self.traffic.get(Direction::ServerToClient),
+        })
+    }
+}
+
+impl Shared {
+    /// Remembers the client so that a handshake it never completes doesn't
+    /// hold up shutdown. Returns `None` if the proxy has already closed all
+    /// the connections.
+    fn register_handshake<'a>(
+        &'a self,
+        id: u64,
+        client: &TcpStream,
+    ) -> io::Result<Option<Handshake<'a>>> {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.closed {
+            return Ok(None);
+        }
+        connections.handshaking.insert(id, client.try_clone()?);
+        Ok(Some(Handshake { shared: self, id }))
+    }
+
+    /// Remembers the stream pair so that it can be closed on shutdown.
+    /// Returns `None` if the proxy has already closed all the connections.
+    fn register<'a>(
+        &'a self,
+        id: u64,
+        client: &TcpStream,
+        server: &TcpStream,
+        traffic: &Arc<Traffic>,
+    ) -> io::Result<Option<Registration<'a>>> {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.closed {
+            return Ok(None);
+        }
+
+        connections.active.insert(
+            id,
+            Connection {
+                client: client.try_clone()?,
+                server: server.try_clone()?,
+                started: Instant::now(),
+                traffic: traffic.clone(),
+            },
+        );
+        Ok(Some(Registration {
+            shared: self,
+            id,
+            traffic: traffic.clone(),
+        }))
+    }
+
+    fn emit(&self, event: ConnectionEvent) {
+        match &self.config.event_log {
+            Some(log) => log.emit(&event),
+            None => info!("{event}"),
+        }
+    }
+
+    fn stats(&self) -> ProxyStats {
+        let connections = self.connections.lock().unwrap();
+        let mut stats = ProxyStats {
+            accepted_connections: connections.accepted,
+            connect_failures: connections.connect_failures,
+            completed_connections: connections.completed,
+            completed_duration: connections.completed_duration,
+            bytes_client_to_server: connections.bytes_client_to_server,
+            bytes_server_to_client: connections.bytes_server_to_client,
+            active: Vec::with_capacity(connections.active.len()),
+        };
+        for connection in connections.active.values() {
+            // Sockets that are being torn down have no peer anymore, skip them.
+            if let Ok(connection) = connection.stats() {
+                stats.bytes_client_to_server += connection.bytes_client_to_server;
+                stats.bytes_server_to_client += connection.bytes_server_to_client;
+                stats.active.push(connection);
+            }
+        }
+        stats
+    }
+
+    fn drain(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let mut connections = self.connections.lock().unwrap();
+        while !connections.active.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            connections = self
+                .drained
+                .wait_timeout(connections, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn close_all(&self) {
+        let mut connections = self.connections.lock().unwrap();
+        connections.closed = true;
+        for client in connections.handshaking.values() {
+            let _ = client.shutdown(Shutdown::Both);
+        }
+        for connection in connections.active.values() {
+            let _ = connection.client.shutdown(Shutdown::Both);
+            let _ = connection.server.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+struct Handshake<'a> {
+    shared: &'a Shared,
+    id: u64,
+}
+
+impl Drop for Handshake<'_> {
+    fn drop(&mut self) {
+        let mut connections = self.shared.connections.lock().unwrap();
+        connections.handshaking.remove(&self.id);
+    }
+}
+
+struct Registration<'a> {
+    shared: &'a Shared,
+    id: u64,
+    traffic: Arc<Traffic>,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let mut connections = self.shared.connections.lock().unwrap();
+        if let Some(connection) = connections.active.remove(&self.id) {
+            connections.completed += 1;
+            connections.completed_duration += connection.started.elapsed();
+            connections.bytes_client_to_server += self.traffic.get(Direction::ClientToServer);
+            connections.bytes_server_to_client += self.traffic.get(Direction::ServerToClient);
+        }
+        if connections.active.is_empty() {
+            self.shared.drained.notify_all();
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+fn accept_connections(
+    listener: TcpListener,
+    destination: Destination,
+    shared: Arc<Shared>,
+) -> Vec<JoinHandle<()>> {
+    let mut workers: Vec<JoinHandle<()>> = Vec::new();
+
+    loop {
+        let accepted = listener.accept();
+        if shared.shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        workers.retain(|worker| !worker.is_finished());
+
+        match accepted {
+            Ok((stream, client)) => {
+                let id = {
+                    let mut connections = shared.connections.lock().unwrap();
+                    connections.accepted += 1;
+                    connections.next_id += 1;
+                    connections.next_id - 1
+                };
 
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
                 let destination = destination.clone();
-                thread::spawn(move || {
-                    handle_connection(stream, &destination);
+                let shared = shared.clone();
+                let worker = thread::Builder::new().spawn(move || {
+                    shared.emit(ConnectionEvent::Accepted { id, client });
+                    let traffic = Arc::new(Traffic::new());
+                    let reason =
+                        match handle_connection(stream, id, &traffic, &destination, &shared) {
+                            Ok(reason) => reason,
+                            Err(e) => {
+                                error!("Error handling connection from {client}: {e}");
+                                CloseReason::Error(e.to_string())
+                            }
+                        };
+                    shared.emit(ConnectionEvent::Closed {
+                        id,
+                        duration: traffic.elapsed(),
+                        bytes_client_to_server: traffic.get(Direction::ClientToServer),
+                        bytes_server_to_client: traffic.get(Direction::ServerToClient),
+                        reason,
+                    });
                 });
+                match worker {
+                    Ok(worker) => workers.push(worker),
+                    Err(e) => error!("Error spawning connection thread: {e}"),
+                }
             }
             Err(e) => {
                 error!("Error accepting client connection: {e}");
             }
         }
     }
+
+    workers
+}
+
+/// Relays the connection until it's over, returning why it was closed.
+fn handle_connection(
+    client: TcpStream,
+    id: u64,
+    traffic: &Arc<Traffic>,
+    destination: &Destination,
+    shared: &Shared,
+) -> Result<CloseReason> {
+    // Don't wait forever for a handshake from a dead client.
+    client.set_read_timeout(shared.config.idle_timeout)?;
+    let Some(handshake) = shared.register_handshake(id, &client)? else {
+        return Ok(CloseReason::Shutdown);
+    };
+
+    let header = if shared.config.accept_proxy_protocol {
+        read_proxy_header(&mut &client)?
+    } else {
+        Some(ProxyHeader {
+            source: client.peer_addr()?,
+            destination: client.local_addr()?,
+        })
+    };
+
+    // Bytes already read from the client while choosing the destination.
+    let mut sniffed = Vec::new();
+    let mut server = match destination {
+        Destination::Fixed(addrs) => connect_to_destination(addrs, shared)?,
+        Destination::Routed(routes) => {
+            let routing::Sniffed { host, data } = routing::sniff(&mut &client)?;
+            let Some(addrs) = routes.lookup(host.as_deref()) else {
+                return Err(ProxyError::NoRoute(host));
+            };
+            sniffed = data;
+            connect_to_destination(addrs, shared)?
+        }
+        Destination::Socks5(socks) => {
+            let target = socks5::accept(&mut &client, socks)?;
+            let server = target
+                .resolve()
+                .map_err(ProxyError::Resolve)
+                .and_then(|addrs| connect_to_destination(&addrs, shared));
+            match server {
+                Ok(server) => {
+                    socks5::reply(&mut &client, Ok(server.local_addr()?))?;
+                    server
+                }
+                Err(e) => {
+                    socks5::reply(&mut &client, Err(&e))?;
+                    return Err(e);
+                }
+            }
+        }
+    };
+    shared.emit(ConnectionEvent::Connected {
+        id,
+        server: server.peer_addr()?,
+    });
+
+    if let Some(protocol) = shared.config.send_proxy_protocol {
+        server.write_all(&protocol.encode(header.as_ref()))?;
+    }
+    server.write_all(&sniffed)?;
+
+    if let Some(idle_timeout) = shared.config.idle_timeout {
+        // Reads wake up periodically to check whether the whole connection
+        // has been idle for long enough.
+        let poll_interval = (idle_timeout / 4).max(Duration::from_millis(1));
+        for stream in [&client, &server] {
+            stream.set_read_timeout(Some(poll_interval))?;
+            stream.set_write_timeout(Some(idle_timeout))?;
+        }
+    }
+
+    let Some(registration) = shared.register(id, &client, &server, traffic)? else {
+        return Ok(CloseReason::Shutdown);
+    };
+    drop(handshake);
+    traffic.add(Direction::ClientToServer, sniffed.len() as u64);
+
+    let relay = |direction| Relay {
+        direction,
+        traffic: registration.traffic.clone(),
+        idle_timeout: shared.config.idle_timeout,
+        chaos: shared.config.chaos.as_ref().map(|config| {
+            let stream_id = registration.id << 1 | direction as u64;
+            Chaos::new(config, stream_id)
+        }),
+    };
+    let client_to_server = relay(Direction::ClientToServer).spawn(&client, &server)?;
+    let server_to_client = relay(Direction::ServerToClient).spawn(&server, &client)?;
+    let client_to_server = client_to_server.join().unwrap();
+    let server_to_client = server_to_client.join().unwrap();
+
+    // The streams may have been already closed on proxy shutdown.
+    let _ = client.shutdown(Shutdown::Both);
+    let _ = server.shutdown(Shutdown::Both);
+
+    let ends = [client_to_server?, server_to_client?];
+    Ok(if shared.shutdown.load(Ordering::SeqCst) {
+        CloseReason::Shutdown
+    } else if ends.contains(&RelayEnd::Idle) {
+        CloseReason::IdleTimeout
+    } else {
+        CloseReason::Eof
+    })
 }
 
-fn handle_connection(connection: TcpStream, destination: &str) {
-    match TcpStream::connect(destination) {
-        Ok(server_stream) => {
-            info!("Connected to destination: {destination}");
+fn connect_to_destination(destination: &[SocketAddr], shared: &Shared) -> Result<TcpStream> {
+    let res = connect_with_retries(destination, shared);
+    if res.is_err() {
+        shared.connections.lock().unwrap().connect_failures += 1;
+    }
+    res
+}
 
-            let (mut client_reader, mut client_writer) = (
-                connection.try_clone().unwrap(),
-                connection.try_clone().unwrap(),
-            );
-            let (mut server_reader, mut server_writer) = (
-                server_stream.try_clone().unwrap(),
-                server_stream.try_clone().unwrap(),
-            );
+fn connect_with_retries(destination: &[SocketAddr], shared: &Shared) -> Result<TcpStream> {
+    let mut retry = 0;
+    loop {
+        let err = match connect(destination, shared.config.connect_timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => err,
+        };
 
-            let client_to_server = thread::spawn(move || {
-                std::io::copy(&mut client_reader, &mut server_writer).unwrap();
-                info!("Client -> server");
-            });
+        let backoff = match &shared.config.reconnect {
+            Some(backoff) if retry < backoff.retries => backoff,
+            _ => return Err(err),
+        };
+        let delay = backoff.delay(retry);
+        retry += 1;
+        info!("{err}, retrying in {delay:?} ({retry}/{})", backoff.retries);
 
-            let server_to_client = thread::spawn(move || {
-                std::io::copy(&mut server_reader, &mut client_writer).unwrap();
-                info!("Server -> client");
-            });
+        // Sleep in slices so that shutdown doesn't wait for the whole delay.
+        let deadline = Instant::now() + delay;
+        loop {
+            if shared.shutdown.load(Ordering::SeqCst) {
+                return Err(err);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            thread::sleep((deadline - now).min(SHUTDOWN_POLL_INTERVAL));
+        }
+    }
+}
+
+/// Connects to the first reachable address, reporting the last failure if
+/// there is none.
+fn connect(destination: &[SocketAddr], timeout: Option<Duration>) -> Result<TcpStream> {
+    let mut last_err = None;
+    for &addr in destination {
+        let stream = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match stream {
+            Ok(stream) => return Ok(stream),
+            Err(source) => {
+                last_err = Some(ProxyError::Connect {
+                    destination: addr,
+                    source,
+                })
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        ProxyError::Resolve(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "address resolved to nothing",
+        ))
+    }))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RelayEnd {
+    Eof,
+    Idle,
+}
+
+/// One direction of a connection.
+struct Relay {
+    direction: Direction,
+    traffic: Arc<Traffic>,
+    idle_timeout: Option<Duration>,
+    chaos: Option<Chaos>,
+}
 
-            client_to_server.join().unwrap();
-            server_to_client.join().unwrap();
+impl Relay {
+    /// Copies `from` into `to` in a separate thread, counting the bytes. On
+    /// error both streams are closed, so that the opposite direction doesn't
+    /// hang forever.
+    fn spawn(
+        mut self,
+        from: &TcpStream,
+        to: &TcpStream,
+    ) -> io::Result<JoinHandle<io::Result<RelayEnd>>> {
+        let (mut reader, mut writer) = (from.try_clone()?, to.try_clone()?);
+        thread::Builder::new().spawn(move || {
+            let res = self.copy(&mut reader, &mut writer);
+            if res.is_err() {
+                let _ = reader.shutdown(Shutdown::Both);
+                let _ = writer.shutdown(Shutdown::Both);
+            }
+            res
+        })
+    }
+
+    fn copy(&mut self, reader: &mut TcpStream, writer: &mut TcpStream) -> io::Result<RelayEnd> {
+        let mut buf = [0; RELAY_BUFFER_SIZE];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(RelayEnd::Eof),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    match self.idle_timeout {
+                        Some(timeout) if self.traffic.idle_time() >= timeout => {
+                            let _ = reader.shutdown(Shutdown::Both);
+                            let _ = writer.shutdown(Shutdown::Both);
+                            return Ok(RelayEnd::Idle);
+                        }
+                        _ => continue,
+                    }
+                }
+                Err(e) => return Err(e),
+            };
+
+            if let Some(chaos) = &mut self.chaos {
+                if let Fault::Reset = chaos.apply(&mut buf[..n]) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "connection reset by chaos mode",
+                    ));
+                }
+            }
 
-            connection
-                .shutdown(std::net::Shutdown::Both)
-                .expect("shutdown call failed");
-            info!("Client stream stutted down");
-            server_stream
-                .shutdown(std::net::Shutdown::Both)
-                .expect("shutdown call failed");
-            info!("Server stream stutted down");
+            writer.write_all(&buf[..n])?;
+            self.traffic.add(self.direction, n as u64);
         }
-        Err(e) => error!("Error connecting to destination: {e}"),
     }
 }
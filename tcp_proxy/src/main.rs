@@ -1,16 +1,159 @@
 #![forbid(unsafe_code)]
 
+use std::fs::OpenOptions;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
 use clap::Parser;
 use simplelog::*;
-use tcp_proxy::run_proxy;
+use tcp_proxy::{
+    run_proxy_with_config, run_routing_proxy, run_socks5_proxy, Backoff, ChaosConfig, EventLog,
+    ProxyConfig, ProxyProtocol, RoutingTable, Socks5Config,
+};
 
 #[derive(Parser)]
 struct Opts {
+    #[clap(short, long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    bind: IpAddr,
+
     #[clap(short, long, default_value = "0")]
-    port: u32,
+    port: u16,
+
+    /// Destination, or the default one with `--route`
+    #[clap(short, long, required_unless_present_any = ["socks5", "route"])]
+    dest: Option<String>,
+
+    /// Route clients by HTTP Host header or TLS SNI, as `host=destination`.
+    /// May be repeated, `*.example.com` matches all subdomains
+    #[clap(long, value_parser = parse_route, conflicts_with = "socks5")]
+    route: Vec<(String, String)>,
+
+    /// Act as a SOCKS5 server instead of relaying to a fixed destination
+    #[clap(long, conflicts_with = "dest")]
+    socks5: bool,
+
+    /// SOCKS5 user as `name:password`, may be repeated. Without users no
+    /// authentication is required
+    #[clap(long, value_parser = parse_user)]
+    socks5_user: Vec<(String, String)>,
+
+    /// Log connection stats every N seconds
+    #[clap(long)]
+    stats_interval: Option<u64>,
+
+    /// Timeout for connecting to the destination, in seconds
+    #[clap(long)]
+    connect_timeout: Option<u64>,
+
+    /// Close connections without traffic for N seconds
+    #[clap(long)]
+    idle_timeout: Option<u64>,
+
+    /// Retry connecting to the destination up to N times, with exponential backoff
+    #[clap(long, default_value = "0")]
+    reconnect_retries: u32,
+
+    /// Initial reconnect delay, in milliseconds
+    #[clap(long, default_value = "100")]
+    reconnect_delay_ms: u64,
+
+    /// Send a PROXY protocol header (version 1 or 2) to the destination
+    #[clap(long, value_parser = parse_proxy_protocol)]
+    send_proxy_protocol: Option<ProxyProtocol>,
+
+    /// Expect a PROXY protocol header from clients
+    #[clap(long)]
+    accept_proxy_protocol: bool,
+
+    /// Chaos mode: delay every chunk by N milliseconds
+    #[clap(long, default_value = "0")]
+    chaos_latency_ms: u64,
+
+    /// Chaos mode: additional random delay of up to N milliseconds
+    #[clap(long, default_value = "0")]
+    chaos_jitter_ms: u64,
+
+    /// Chaos mode: probability to corrupt every byte
+    #[clap(long, default_value = "0")]
+    chaos_corrupt: f64,
+
+    /// Chaos mode: probability to reset the connection on every chunk
+    #[clap(long, default_value = "0")]
+    chaos_reset: f64,
+
+    /// Chaos mode: seed for reproducible faults
+    #[clap(long)]
+    chaos_seed: Option<u64>,
+
+    /// Append connection events as JSON lines to this file, `-` for stdout
+    #[clap(long)]
+    event_log: Option<PathBuf>,
+}
+
+impl Opts {
+    fn chaos(&self) -> Option<ChaosConfig> {
+        let chaos = ChaosConfig {
+            latency: Duration::from_millis(self.chaos_latency_ms),
+            jitter: Duration::from_millis(self.chaos_jitter_ms),
+            corrupt_probability: self.chaos_corrupt,
+            reset_probability: self.chaos_reset,
+            seed: self.chaos_seed,
+        };
+        let enabled = !chaos.latency.is_zero()
+            || !chaos.jitter.is_zero()
+            || chaos.corrupt_probability > 0.
+            || chaos.reset_probability > 0.;
+        enabled.then_some(chaos)
+    }
+
+    fn event_log(&self) -> io::Result<Option<EventLog>> {
+        let Some(path) = &self.event_log else {
+            return Ok(None);
+        };
+        if path.as_os_str() == "-" {
+            return Ok(Some(EventLog::json_lines(io::stdout())));
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Some(EventLog::json_lines(file)))
+    }
+}
+
+fn parse_user(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((name, password)) => Ok((name.to_string(), password.to_string())),
+        None => Err("expected `name:password`".to_string()),
+    }
+}
+
+fn parse_route(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((host, dest)) => Ok((host.to_string(), dest.to_string())),
+        None => Err("expected `host=destination`".to_string()),
+    }
+}
+
+fn routing_table(
+    routes: &[(String, String)],
+    default: Option<&str>,
+) -> tcp_proxy::Result<RoutingTable> {
+    let mut table = RoutingTable::new();
+    for (host, dest) in routes {
+        table.add_route(host, dest.as_str())?;
+    }
+    if let Some(dest) = default {
+        table.set_default(dest)?;
+    }
+    Ok(table)
+}
 
-    #[clap(short, long)]
-    dest: String,
+fn parse_proxy_protocol(s: &str) -> Result<ProxyProtocol, String> {
+    match s {
+        "1" => Ok(ProxyProtocol::V1),
+        "2" => Ok(ProxyProtocol::V2),
+        _ => Err(format!("unknown PROXY protocol version: {s}")),
+    }
 }
 
 fn main() {
@@ -23,5 +166,41 @@ fn main() {
     .unwrap();
 
     let opts = Opts::parse();
-    run_proxy(opts.port, opts.dest);
+    let event_log = match opts.event_log() {
+        Ok(event_log) => event_log,
+        Err(e) => {
+            log::error!("Failed to open the event log: {e}");
+            std::process::exit(1);
+        }
+    };
+    let config = ProxyConfig {
+        stats_interval: opts.stats_interval.map(Duration::from_secs),
+        connect_timeout: opts.connect_timeout.map(Duration::from_secs),
+        idle_timeout: opts.idle_timeout.map(Duration::from_secs),
+        send_proxy_protocol: opts.send_proxy_protocol,
+        accept_proxy_protocol: opts.accept_proxy_protocol,
+        chaos: opts.chaos(),
+        reconnect: (opts.reconnect_retries > 0).then(|| Backoff {
+            retries: opts.reconnect_retries,
+            initial_delay: Duration::from_millis(opts.reconnect_delay_ms),
+            ..Default::default()
+        }),
+        event_log,
+    };
+    let bind = SocketAddr::new(opts.bind, opts.port);
+    let res = match opts.dest {
+        _ if !opts.route.is_empty() => routing_table(&opts.route, opts.dest.as_deref())
+            .and_then(|routes| run_routing_proxy(bind, routes, config)),
+        Some(dest) => run_proxy_with_config(bind, dest, config),
+        None => {
+            let socks = Socks5Config {
+                users: opts.socks5_user.into_iter().collect(),
+            };
+            run_socks5_proxy(bind, socks, config)
+        }
+    };
+    if let Err(e) = res {
+        log::error!("{e}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,214 @@
+//! HAProxy [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt)
+//! headers, which let a backend see the address of the original client.
+
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+////////////////////////////////////////////////////////////////////////////////
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+const V2_LOCAL: u8 = 0x20;
+const V2_PROXY: u8 = 0x21;
+const V2_UNSPEC: u8 = 0x00;
+const V2_TCP4: u8 = 0x11;
+const V2_TCP6: u8 = 0x21;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxyProtocol {
+    /// Human-readable text header.
+    V1,
+    /// Binary header.
+    V2,
+}
+
+/// Addresses of the original connection, as seen by the first proxy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProxyHeader {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+}
+
+impl ProxyProtocol {
+    /// Encodes the header. `None` stands for a connection with unknown
+    /// addresses (`UNKNOWN` in v1, `LOCAL` in v2).
+    pub fn encode(self, header: Option<&ProxyHeader>) -> Vec<u8> {
+        // Both addresses must be of the same family.
+        let header = header.map(|header| match (header.source, header.destination) {
+            (SocketAddr::V4(_), SocketAddr::V4(_)) | (SocketAddr::V6(_), SocketAddr::V6(_)) => {
+                *header
+            }
+            (source, destination) => ProxyHeader {
+                source: to_ipv6(source),
+                destination: to_ipv6(destination),
+            },
+        });
+
+        match self {
+            ProxyProtocol::V1 => encode_v1(header),
+            ProxyProtocol::V2 => encode_v2(header),
+        }
+    }
+}
+
+fn to_ipv6(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) => SocketAddr::new(ip.to_ipv6_mapped().into(), addr.port()),
+        IpAddr::V6(_) => addr,
+    }
+}
+
+fn encode_v1(header: Option<ProxyHeader>) -> Vec<u8> {
+    match header {
+        None => b"PROXY UNKNOWN\r\n".to_vec(),
+        Some(ProxyHeader {
+            source,
+            destination,
+        }) => format!(
+            "PROXY {} {} {} {} {}\r\n",
+            if source.is_ipv4() { "TCP4" } else { "TCP6" },
+            source.ip(),
+            destination.ip(),
+            source.port(),
+            destination.port(),
+        )
+        .into_bytes(),
+    }
+}
+
+fn encode_v2(header: Option<ProxyHeader>) -> Vec<u8> {
+    let mut buf = V2_SIGNATURE.to_vec();
+    let Some(ProxyHeader {
+        source,
+        destination,
+    }) = header
+    else {
+        buf.extend([V2_LOCAL, V2_UNSPEC, 0, 0]);
+        return buf;
+    };
+
+    let mut addrs = Vec::new();
+    let family = match (source.ip(), destination.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            addrs.extend(src.octets());
+            addrs.extend(dst.octets());
+            V2_TCP4
+        }
+        (IpAddr::V6(src), IpAddr::V6(dst)) => {
+            addrs.extend(src.octets());
+            addrs.extend(dst.octets());
+            V2_TCP6
+        }
+        _ => unreachable!("addresses are of the same family"),
+    };
+    addrs.extend(source.port().to_be_bytes());
+    addrs.extend(destination.port().to_be_bytes());
+
+    buf.extend([V2_PROXY, family]);
+    buf.extend((addrs.len() as u16).to_be_bytes());
+    buf.extend(addrs);
+    buf
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Reads a v1 or v2 header from the beginning of the stream, consuming
+/// exactly its bytes. Returns `None` if the header carries no addresses.
+pub fn read_proxy_header(reader: &mut impl Read) -> io::Result<Option<ProxyHeader>> {
+    let mut prefix = [0; 5];
+    reader.read_exact(&mut prefix)?;
+
+    if prefix[..] == V1_PREFIX[..5] {
+        read_v1(reader, &prefix)
+    } else if prefix[..] == V2_SIGNATURE[..5] {
+        read_v2(reader, &prefix)
+    } else {
+        Err(invalid("missing PROXY protocol header"))
+    }
+}
+
+fn read_v1(reader: &mut impl Read, prefix: &[u8]) -> io::Result<Option<ProxyHeader>> {
+    let mut line = prefix.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() == V1_MAX_LEN {
+            return Err(invalid("PROXY v1 header is too long"));
+        }
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+
+    let line = std::str::from_utf8(&line[V1_PREFIX.len()..line.len() - 2])
+        .map_err(|_| invalid("PROXY v1 header is not ASCII"))?;
+    let parts = line.split(' ').collect::<Vec<_>>();
+    let parse_ip = |s: &str| s.parse::<IpAddr>().map_err(|_| invalid("invalid address"));
+    let parse_port = |s: &str| s.parse::<u16>().map_err(|_| invalid("invalid port"));
+
+    match parts[..] {
+        ["UNKNOWN", ..] => Ok(None),
+        [proto @ ("TCP4" | "TCP6"), src, dst, src_port, dst_port] => {
+            let (src, dst) = (parse_ip(src)?, parse_ip(dst)?);
+            let expected_v4 = proto == "TCP4";
+            if src.is_ipv4() != expected_v4 || dst.is_ipv4() != expected_v4 {
+                return Err(invalid("address doesn't match the protocol"));
+            }
+            Ok(Some(ProxyHeader {
+                source: SocketAddr::new(src, parse_port(src_port)?),
+                destination: SocketAddr::new(dst, parse_port(dst_port)?),
+            }))
+        }
+        _ => Err(invalid("malformed PROXY v1 header")),
+    }
+}
+
+fn read_v2(reader: &mut impl Read, prefix: &[u8]) -> io::Result<Option<ProxyHeader>> {
+    let mut header = [0; 16];
+    header[..prefix.len()].copy_from_slice(prefix);
+    reader.read_exact(&mut header[prefix.len()..])?;
+    if header[..12] != V2_SIGNATURE[..] {
+        return Err(invalid("invalid PROXY v2 signature"));
+    }
+
+    let (ver_cmd, family) = (header[12], header[13]);
+    let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+    let mut addrs = vec![0; len];
+    reader.read_exact(&mut addrs)?;
+
+    if ver_cmd >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    match ver_cmd & 0x0f {
+        0 => return Ok(None),
+        1 => (),
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+
+    // Both TCP and UDP over IP are accepted, other families carry no
+    // socket addresses.
+    let ip_len = match family >> 4 {
+        1 => 4,
+        2 => 16,
+        _ => return Ok(None),
+    };
+    if addrs.len() < 2 * ip_len + 4 {
+        return Err(invalid("PROXY v2 addresses are truncated"));
+    }
+
+    let ip = |bytes: &[u8]| -> IpAddr {
+        match ip_len {
+            4 => Ipv4Addr::from(<[u8; 4]>::try_from(bytes).unwrap()).into(),
+            _ => Ipv6Addr::from(<[u8; 16]>::try_from(bytes).unwrap()).into(),
+        }
+    };
+    let port = |offset: usize| u16::from_be_bytes([addrs[offset], addrs[offset + 1]]);
+    Ok(Some(ProxyHeader {
+        source: SocketAddr::new(ip(&addrs[..ip_len]), port(2 * ip_len)),
+        destination: SocketAddr::new(ip(&addrs[ip_len..2 * ip_len]), port(2 * ip_len + 2)),
+    }))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,236 @@
+//! Picks the destination by the host name the client asks for: the `Host`
+//! header of an HTTP/1.x request or the SNI extension of a TLS ClientHello.
+
+use crate::{resolve, Result};
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+////////////////////////////////////////////////////////////////////////////////
+
+const SNIFF_BUFFER_SIZE: usize = 1024;
+
+const HTTP_MAX_HEAD_LEN: usize = 8 * 1024;
+const HTTP_MAX_METHOD_LEN: usize = 16;
+
+const TLS_RECORD_HEADER_LEN: usize = 5;
+const TLS_MAX_RECORD_LEN: usize = 16 * 1024;
+const TLS_CONTENT_HANDSHAKE: u8 = 0x16;
+const TLS_CLIENT_HELLO: u8 = 0x01;
+const TLS_EXT_SERVER_NAME: u16 = 0x0000;
+const TLS_SNI_HOST_NAME: u8 = 0x00;
+
+/// Host name to destination. Names are matched case-insensitively, and a
+/// `*.example.com` route matches every subdomain of `example.com` without a
+/// more specific route.
+#[derive(Clone, Debug, Default)]
+pub struct RoutingTable {
+    routes: HashMap<String, Arc<[SocketAddr]>>,
+    default: Option<Arc<[SocketAddr]>>,
+}
+
+impl RoutingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes `host` to `destination`, replacing the previous route. The
+    /// destination is resolved right away.
+    pub fn add_route(&mut self, host: &str, destination: impl ToSocketAddrs) -> Result<&mut Self> {
+        self.routes
+            .insert(normalize(host), resolve(destination)?.into());
+        Ok(self)
+    }
+
+    /// Destination for clients whose host has no route or can't be detected.
+    /// Without it such clients are disconnected.
+    pub fn set_default(&mut self, destination: impl ToSocketAddrs) -> Result<&mut Self> {
+        self.default = Some(resolve(destination)?.into());
+        Ok(self)
+    }
+
+    pub(crate) fn lookup(&self, host: Option<&str>) -> Option<&[SocketAddr]> {
+        if let Some(host) = host {
+            let host = normalize(host);
+            if let Some(addrs) = self.routes.get(&host) {
+                return Some(addrs);
+            }
+            let mut name = host.as_str();
+            while let Some((_, parent)) = name.split_once('.') {
+                if let Some(addrs) = self.routes.get(&format!("*.{parent}")) {
+                    return Some(addrs);
+                }
+                name = parent;
+            }
+        }
+        self.default.as_deref()
+    }
+}
+
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// What was read from the client while looking for the host name. The data
+/// must reach the destination before the rest of the stream.
+pub(crate) struct Sniffed {
+    pub host: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// Reads the beginning of the stream until the host name is found or the
+/// protocol is known to carry none. A read timeout counts as the latter.
+pub(crate) fn sniff(reader: &mut impl Read) -> io::Result<Sniffed> {
+    let mut data = Vec::new();
+    let mut buf = [0; SNIFF_BUFFER_SIZE];
+    loop {
+        if let Some(host) = parse_host(&data) {
+            return Ok(Sniffed { host, data });
+        }
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(Sniffed { host: None, data }),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(Sniffed { host: None, data })
+            }
+            Err(e) => return Err(e),
+        };
+        data.extend_from_slice(&buf[..n]);
+    }
+}
+
+/// Returns `None` if more data is needed to decide.
+fn parse_host(data: &[u8]) -> Option<Option<String>> {
+    match *data.first()? {
+        TLS_CONTENT_HANDSHAKE => parse_tls(data),
+        _ => parse_http(data),
+    }
+}
+
+fn parse_http(data: &[u8]) -> Option<Option<String>> {
+    let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+        // Don't wait for the end of the headers of something that isn't HTTP.
+        return (data.len() >= HTTP_MAX_HEAD_LEN || !may_be_http(data)).then_some(None);
+    };
+    let Ok(head) = std::str::from_utf8(&data[..end]) else {
+        return Some(None);
+    };
+
+    let mut lines = head.split("\r\n");
+    if !lines.next()?.contains(" HTTP/1.") {
+        return Some(None);
+    }
+    Some(lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("host")
+            .then(|| strip_port(value.trim()).to_string())
+    }))
+}
+
+/// Whether `data` may start with an HTTP method: a token of uppercase letters
+/// followed by a space.
+fn may_be_http(data: &[u8]) -> bool {
+    let method_len = data.iter().take_while(|b| b.is_ascii_uppercase()).count();
+    match data.get(method_len) {
+        None => method_len <= HTTP_MAX_METHOD_LEN,
+        Some(b' ') => method_len > 0,
+        Some(_) => false,
+    }
+}
+
+fn strip_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.parse::<u16>().is_ok() => name,
+        _ => host,
+    }
+}
+
+fn parse_tls(data: &[u8]) -> Option<Option<String>> {
+    let header = data.get(..TLS_RECORD_HEADER_LEN)?;
+    let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+    if len > TLS_MAX_RECORD_LEN {
+        return Some(None);
+    }
+    let record = data.get(TLS_RECORD_HEADER_LEN..TLS_RECORD_HEADER_LEN + len)?;
+    Some(server_name(record))
+}
+
+/// Extracts the host name from a ClientHello. Messages that don't fit into a
+/// single record are not supported.
+fn server_name(record: &[u8]) -> Option<String> {
+    let mut record = Bytes(record);
+    if record.u8()? != TLS_CLIENT_HELLO {
+        return None;
+    }
+    let len = record.u24()?;
+    let mut hello = Bytes(record.take(len)?);
+
+    hello.take(2 + 32)?; // Version and random.
+    hello.vec8()?; // Session id.
+    hello.vec16()?; // Cipher suites.
+    hello.vec8()?; // Compression methods.
+    let mut extensions = Bytes(hello.vec16()?);
+    while !extensions.0.is_empty() {
+        let (kind, extension) = (extensions.u16()?, extensions.vec16()?);
+        if kind != TLS_EXT_SERVER_NAME {
+            continue;
+        }
+        let mut names = Bytes(Bytes(extension).vec16()?);
+        while !names.0.is_empty() {
+            let (kind, name) = (names.u8()?, names.vec16()?);
+            if kind == TLS_SNI_HOST_NAME {
+                return String::from_utf8(name.to_vec()).ok();
+            }
+        }
+    }
+    None
+}
+
+/// Reader of big-endian TLS fields.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.take(2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        let bytes = self.take(3)?;
+        Some(usize::from(bytes[0]) << 16 | usize::from(bytes[1]) << 8 | usize::from(bytes[2]))
+    }
+
+    fn vec8(&mut self) -> Option<&'a [u8]> {
+        let len = self.u8()?;
+        self.take(len.into())
+    }
+
+    fn vec16(&mut self) -> Option<&'a [u8]> {
+        let len = self.u16()?;
+        self.take(len.into())
+    }
+}
@@ -0,0 +1,200 @@
+//! Server side of [SOCKS5](https://www.rfc-editor.org/rfc/rfc1928), with
+//! [username/password](https://www.rfc-editor.org/rfc/rfc1929) authentication.
+
+use crate::ProxyError;
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+////////////////////////////////////////////////////////////////////////////////
+
+const VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_PASSWORD: u8 = 0x02;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xff;
+
+const CMD_CONNECT: u8 = 0x01;
+
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+const REP_SUCCEEDED: u8 = 0x00;
+const REP_GENERAL_FAILURE: u8 = 0x01;
+const REP_NETWORK_UNREACHABLE: u8 = 0x03;
+const REP_HOST_UNREACHABLE: u8 = 0x04;
+const REP_CONNECTION_REFUSED: u8 = 0x05;
+const REP_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REP_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+#[derive(Clone, Debug, Default)]
+pub struct Socks5Config {
+    /// Username to password. If empty, clients are accepted without
+    /// authentication, otherwise they must log in as one of these users.
+    pub users: HashMap<String, String>,
+}
+
+/// Address requested by the client.
+pub(crate) enum Target {
+    Ip(SocketAddr),
+    Domain(String, u16),
+}
+
+impl Target {
+    pub(crate) fn resolve(&self) -> io::Result<Vec<SocketAddr>> {
+        match self {
+            Target::Ip(addr) => Ok(vec![*addr]),
+            Target::Domain(host, port) => Ok((host.as_str(), *port).to_socket_addrs()?.collect()),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Negotiates authentication and reads the CONNECT request.
+pub(crate) fn accept(
+    stream: &mut (impl Read + Write),
+    config: &Socks5Config,
+) -> io::Result<Target> {
+    let [version, n_methods] = read_array(stream)?;
+    if version != VERSION {
+        return Err(invalid("unsupported SOCKS version"));
+    }
+    let mut methods = vec![0; n_methods as usize];
+    stream.read_exact(&mut methods)?;
+
+    let method = if config.users.is_empty() {
+        METHOD_NO_AUTH
+    } else {
+        METHOD_PASSWORD
+    };
+    if !methods.contains(&method) {
+        stream.write_all(&[VERSION, METHOD_NONE_ACCEPTABLE])?;
+        return Err(invalid("no acceptable SOCKS authentication method"));
+    }
+    stream.write_all(&[VERSION, method])?;
+
+    if method == METHOD_PASSWORD {
+        authenticate(stream, config)?;
+    }
+
+    let [version, command, _, atyp] = read_array(stream)?;
+    if version != VERSION {
+        return Err(invalid("unsupported SOCKS version"));
+    }
+    let target = match atyp {
+        ATYP_IPV4 => {
+            let ip = Ipv4Addr::from(read_array::<4>(stream)?);
+            Target::Ip(SocketAddr::new(ip.into(), read_port(stream)?))
+        }
+        ATYP_IPV6 => {
+            let ip = Ipv6Addr::from(read_array::<16>(stream)?);
+            Target::Ip(SocketAddr::new(ip.into(), read_port(stream)?))
+        }
+        ATYP_DOMAIN => {
+            let host = String::from_utf8(read_string(stream)?)
+                .map_err(|_| invalid("SOCKS domain name is not UTF-8"))?;
+            Target::Domain(host, read_port(stream)?)
+        }
+        _ => {
+            write_reply(stream, REP_ADDRESS_NOT_SUPPORTED, None)?;
+            return Err(invalid("unsupported SOCKS address type"));
+        }
+    };
+
+    if command != CMD_CONNECT {
+        write_reply(stream, REP_COMMAND_NOT_SUPPORTED, None)?;
+        return Err(invalid("unsupported SOCKS command"));
+    }
+
+    Ok(target)
+}
+
+fn authenticate(stream: &mut (impl Read + Write), config: &Socks5Config) -> io::Result<()> {
+    let [version] = read_array(stream)?;
+    if version != AUTH_VERSION {
+        return Err(invalid("unsupported SOCKS authentication version"));
+    }
+    let username = read_string(stream)?;
+    let password = read_string(stream)?;
+
+    let authorized = std::str::from_utf8(&username)
+        .ok()
+        .and_then(|username| config.users.get(username))
+        .is_some_and(|expected| expected.as_bytes() == password);
+    if !authorized {
+        stream.write_all(&[AUTH_VERSION, 0x01])?;
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "SOCKS authentication failed",
+        ));
+    }
+    stream.write_all(&[AUTH_VERSION, 0x00])
+}
+
+/// Reports the outcome of the CONNECT request. On success `bound` is the
+/// proxy's address on the connection to the target.
+pub(crate) fn reply(
+    stream: &mut impl Write,
+    result: std::result::Result<SocketAddr, &ProxyError>,
+) -> io::Result<()> {
+    match result {
+        Ok(bound) => write_reply(stream, REP_SUCCEEDED, Some(bound)),
+        Err(err) => {
+            let code = match err {
+                ProxyError::Resolve(_) => REP_HOST_UNREACHABLE,
+                ProxyError::Connect { source, .. } => match source.kind() {
+                    io::ErrorKind::ConnectionRefused => REP_CONNECTION_REFUSED,
+                    io::ErrorKind::NetworkUnreachable => REP_NETWORK_UNREACHABLE,
+                    io::ErrorKind::HostUnreachable | io::ErrorKind::TimedOut => {
+                        REP_HOST_UNREACHABLE
+                    }
+                    _ => REP_GENERAL_FAILURE,
+                },
+                _ => REP_GENERAL_FAILURE,
+            };
+            write_reply(stream, code, None)
+        }
+    }
+}
+
+fn write_reply(stream: &mut impl Write, code: u8, bound: Option<SocketAddr>) -> io::Result<()> {
+    let bound = bound.unwrap_or_else(|| SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0));
+    let mut buf = vec![VERSION, code, 0];
+    match bound.ip() {
+        IpAddr::V4(ip) => {
+            buf.push(ATYP_IPV4);
+            buf.extend(ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            buf.push(ATYP_IPV6);
+            buf.extend(ip.octets());
+        }
+    }
+    buf.extend(bound.port().to_be_bytes());
+    stream.write_all(&buf)
+}
+
+fn read_array<const N: usize>(stream: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_port(stream: &mut impl Read) -> io::Result<u16> {
+    Ok(u16::from_be_bytes(read_array(stream)?))
+}
+
+fn read_string(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let [len] = read_array(stream)?;
+    let mut buf = vec![0; len as usize];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Snapshot of the proxy counters, see [`ProxyHandle::stats`](crate::ProxyHandle::stats).
+///
+/// Byte counters include both completed and currently active connections.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyStats {
+    pub accepted_connections: u64,
+    pub connect_failures: u64,
+    pub completed_connections: u64,
+    /// Total lifetime of all completed connections.
+    pub completed_duration: Duration,
+    pub bytes_client_to_server: u64,
+    pub bytes_server_to_client: u64,
+    pub active: Vec<ConnectionStats>,
+}
+
+impl ProxyStats {
+    pub fn active_connections(&self) -> usize {
+        self.active.len()
+    }
+}
+
+impl fmt::Display for ProxyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "active: {}, accepted: {}, completed: {}, connect failures: {}, \
+            client -> server: {} bytes, server -> client: {} bytes",
+            self.active_connections(),
+            self.accepted_connections,
+            self.completed_connections,
+            self.connect_failures,
+            self.bytes_client_to_server,
+            self.bytes_server_to_client,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionStats {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub duration: Duration,
+    pub bytes_client_to_server: u64,
+    pub bytes_server_to_client: u64,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub(crate) enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::ClientToServer => "Client -> server",
+            Direction::ServerToClient => "Server -> client",
+        })
+    }
+}
+
+/// Live byte counters of a single connection, updated by the relay threads.
+pub(crate) struct Traffic {
+    started: Instant,
+    client_to_server: AtomicU64,
+    server_to_client: AtomicU64,
+    // Time of the last relayed chunk in either direction, since `started`.
+    last_activity_ms: AtomicU64,
+}
+
+impl Traffic {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            client_to_server: AtomicU64::new(0),
+            server_to_client: AtomicU64::new(0),
+            last_activity_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn add(&self, direction: Direction, bytes: u64) {
+        self.counter(direction).fetch_add(bytes, Ordering::Relaxed);
+        self.last_activity_ms
+            .fetch_max(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub(crate) fn idle_time(&self) -> Duration {
+        let last_activity = Duration::from_millis(self.last_activity_ms.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last_activity)
+    }
+
+    pub(crate) fn get(&self, direction: Direction) -> u64 {
+        self.counter(direction).load(Ordering::Relaxed)
+    }
+
+    fn counter(&self, direction: Direction) -> &AtomicU64 {
+        match direction {
+            Direction::ClientToServer => &self.client_to_server,
+            Direction::ServerToClient => &self.server_to_client,
+        }
+    }
+}
@@ -22,13 +22,21 @@ fn start_proxy() -> (TcpListener, Child, String) {
     let server_str = format!("127.0.0.1:{}", server.local_addr().unwrap().port());
 
     let proxy_proc = Command::new(BINARY_PATH)
-        .args(&["-p", &port_str, "-d", &server_str])
+        .args(["-p", &port_str, "-d", &server_str])
         .spawn()
         .unwrap();
     thread::sleep(time::Duration::from_millis(10));
     (server, proxy_proc, format!("127.0.0.1:{}", port))
 }
 
+fn wait_until(mut condition: impl FnMut() -> bool) {
+    let start = time::Instant::now();
+    while !condition() {
+        assert!(start.elapsed() < time::Duration::from_secs(5), "timed out");
+        thread::sleep(time::Duration::from_millis(1));
+    }
+}
+
 #[test]
 fn test_ping_pong() {
     let (server, mut proxy, proxy_addr) = start_proxy();
@@ -197,3 +205,782 @@ fn test_two_clients() {
     server_thread.join().unwrap();
     proxy.kill().unwrap();
 }
+
+#[test]
+fn test_shutdown() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = tcp_proxy::spawn_proxy("127.0.0.1:0", server.local_addr().unwrap()).unwrap();
+    let proxy_addr = proxy.local_addr();
+
+    let mut client = TcpStream::connect(proxy_addr).unwrap();
+    client.write_all(b"ping").unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+
+    let start = time::Instant::now();
+    proxy.shutdown(Some(time::Duration::from_millis(100)));
+    assert!(start.elapsed() >= time::Duration::from_millis(100));
+
+    assert_eq!(client.read(&mut read_buffer).unwrap(), 0);
+    assert_eq!(connection.read(&mut read_buffer).unwrap(), 0);
+    assert!(TcpStream::connect(proxy_addr).is_err());
+}
+
+#[test]
+fn test_shutdown_drained() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = tcp_proxy::spawn_proxy("127.0.0.1:0", server.local_addr().unwrap()).unwrap();
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let server_thread = thread::spawn(move || {
+        let (mut connection, _) = server.accept().unwrap();
+        connection.write_all(b"pong").unwrap();
+    });
+
+    let mut read_buffer: [u8; 4] = [0; 4];
+    client.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"pong");
+    server_thread.join().unwrap();
+    drop(client);
+
+    let start = time::Instant::now();
+    proxy.shutdown(Some(time::Duration::from_secs(10)));
+    assert!(start.elapsed() < time::Duration::from_secs(5));
+}
+
+#[test]
+fn test_shutdown_during_handshake() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = tcp_proxy::ProxyConfig {
+        accept_proxy_protocol: true,
+        ..Default::default()
+    };
+    let proxy =
+        tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", server.local_addr().unwrap(), config)
+            .unwrap();
+
+    // Never sends the PROXY header.
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    wait_until(|| proxy.stats().accepted_connections == 1);
+
+    let start = time::Instant::now();
+    proxy.shutdown(None);
+    assert!(start.elapsed() < time::Duration::from_secs(5));
+    assert_eq!(client.read(&mut [0; 4]).unwrap(), 0);
+}
+
+#[test]
+fn test_bind_error() {
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = taken.local_addr().unwrap();
+
+    match tcp_proxy::spawn_proxy(addr, "127.0.0.1:1") {
+        Err(tcp_proxy::ProxyError::Bind { addrs, .. }) => assert_eq!(addrs, [addr]),
+        Err(e) => panic!("expected bind error, got {e}"),
+        Ok(_) => panic!("expected bind error, got Ok"),
+    }
+}
+
+#[test]
+#[ignore = "needs an IPv6 loopback"]
+fn test_ipv6() {
+    let server = TcpListener::bind("[::1]:0").unwrap();
+    let proxy = tcp_proxy::spawn_proxy("[::1]:0", server.local_addr().unwrap()).unwrap();
+    assert!(proxy.local_addr().is_ipv6());
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(b"ping").unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_unreachable_destination() {
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let destination = unused.local_addr().unwrap().to_string();
+    drop(unused);
+
+    let proxy = tcp_proxy::spawn_proxy("127.0.0.1:0", destination).unwrap();
+    for _ in 0..3 {
+        let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+        let mut read_buffer = [0; 4];
+        assert_eq!(client.read(&mut read_buffer).unwrap(), 0);
+    }
+    assert_eq!(proxy.stats().connect_failures, 3);
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_stats() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = tcp_proxy::spawn_proxy("127.0.0.1:0", server.local_addr().unwrap()).unwrap();
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(b"ping").unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    connection.write_all(b"pong!").unwrap();
+    let mut read_buffer: [u8; 5] = [0; 5];
+    client.read_exact(&mut read_buffer).unwrap();
+
+    // Counters are updated right after the write, give the relay a moment.
+    wait_until(|| proxy.stats().bytes_server_to_client == 5);
+    let stats = proxy.stats();
+    assert_eq!(stats.accepted_connections, 1);
+    assert_eq!(stats.active_connections(), 1);
+    assert_eq!(stats.active[0].client, client.local_addr().unwrap());
+    assert_eq!(stats.active[0].bytes_client_to_server, 4);
+    assert_eq!(stats.active[0].bytes_server_to_client, 5);
+    assert_eq!(stats.bytes_client_to_server, 4);
+    assert_eq!(stats.bytes_server_to_client, 5);
+
+    drop(client);
+    drop(connection);
+    wait_until(|| proxy.stats().active_connections() == 0);
+
+    let stats = proxy.stats();
+    assert_eq!(stats.completed_connections, 1);
+    assert_eq!(stats.bytes_client_to_server, 4);
+    assert_eq!(stats.bytes_server_to_client, 5);
+    assert_eq!(stats.connect_failures, 0);
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_event_log() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let config = tcp_proxy::ProxyConfig {
+        event_log: Some(tcp_proxy::EventLog::new({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event.clone())
+        })),
+        ..Default::default()
+    };
+    let proxy =
+        tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", server.local_addr().unwrap(), config)
+            .unwrap();
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(b"ping").unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    connection.write_all(b"pong!").unwrap();
+    let mut read_buffer: [u8; 5] = [0; 5];
+    client.read_exact(&mut read_buffer).unwrap();
+    let client_addr = client.local_addr().unwrap();
+    drop(client);
+    drop(connection);
+    wait_until(|| events.lock().unwrap().len() == 3);
+
+    let events = events.lock().unwrap();
+    assert_eq!(
+        events[..2],
+        [
+            tcp_proxy::ConnectionEvent::Accepted {
+                id: 0,
+                client: client_addr,
+            },
+            tcp_proxy::ConnectionEvent::Connected {
+                id: 0,
+                server: server.local_addr().unwrap(),
+            },
+        ]
+    );
+    match &events[2] {
+        tcp_proxy::ConnectionEvent::Closed {
+            id: 0,
+            bytes_client_to_server: 4,
+            bytes_server_to_client: 5,
+            reason: tcp_proxy::CloseReason::Eof,
+            ..
+        } => {}
+        event => panic!("unexpected event: {event:?}"),
+    }
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_event_log_json() {
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let destination = unused.local_addr().unwrap();
+    drop(unused);
+
+    let buffer = Buffer::default();
+    let config = tcp_proxy::ProxyConfig {
+        event_log: Some(tcp_proxy::EventLog::json_lines(buffer.clone())),
+        ..Default::default()
+    };
+    let proxy = tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", destination, config).unwrap();
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let mut read_buffer = [0; 4];
+    assert_eq!(client.read(&mut read_buffer).unwrap(), 0);
+    let lines = || {
+        let data = buffer.0.lock().unwrap();
+        str::from_utf8(&data)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    wait_until(|| lines().len() == 2);
+
+    let lines = lines();
+    let client_addr = client.local_addr().unwrap();
+    assert!(lines[0].starts_with(r#"{"timestamp_ms":"#));
+    assert!(lines[0].ends_with(&format!(
+        r#","event":"accepted","id":0,"client":"{client_addr}"}}"#
+    )));
+    assert!(lines[1].contains(r#""event":"closed","id":0,"#));
+    assert!(lines[1].contains(&format!(
+        r#""reason":"error","error":"failed to connect to destination {destination}: "#
+    )));
+    proxy.shutdown(None);
+
+    let event = tcp_proxy::ConnectionEvent::Closed {
+        id: 7,
+        duration: time::Duration::from_millis(1500),
+        bytes_client_to_server: 1,
+        bytes_server_to_client: 2,
+        reason: tcp_proxy::CloseReason::Error("bad \"header\"\n".into()),
+    };
+    assert_eq!(
+        event.to_json(),
+        r#"{"event":"closed","id":7,"duration_ms":1500,"bytes_client_to_server":1,"#.to_string()
+            + r#""bytes_server_to_client":2,"reason":"error","error":"bad \"header\"\n"}"#
+    );
+}
+
+#[test]
+fn test_idle_timeout() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = tcp_proxy::ProxyConfig {
+        idle_timeout: Some(time::Duration::from_millis(200)),
+        ..Default::default()
+    };
+    let proxy =
+        tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", server.local_addr().unwrap(), config)
+            .unwrap();
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+
+    // Keep the connection busy for longer than the timeout.
+    let mut read_buffer: [u8; 4] = [0; 4];
+    for _ in 0..5 {
+        client.write_all(b"ping").unwrap();
+        connection.read_exact(&mut read_buffer).unwrap();
+        thread::sleep(time::Duration::from_millis(100));
+    }
+
+    let start = time::Instant::now();
+    assert_eq!(client.read(&mut read_buffer).unwrap(), 0);
+    assert_eq!(connection.read(&mut read_buffer).unwrap(), 0);
+    assert!(start.elapsed() < time::Duration::from_secs(2));
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_proxy_protocol_roundtrip() {
+    use tcp_proxy::{read_proxy_header, ProxyHeader, ProxyProtocol};
+
+    let v4 = ProxyHeader {
+        source: "192.168.0.1:56324".parse().unwrap(),
+        destination: "10.0.0.1:443".parse().unwrap(),
+    };
+    let v6 = ProxyHeader {
+        source: "[2001:db8::1]:56324".parse().unwrap(),
+        destination: "[2001:db8::2]:443".parse().unwrap(),
+    };
+
+    assert_eq!(
+        ProxyProtocol::V1.encode(Some(&v4)),
+        b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n"
+    );
+    assert_eq!(ProxyProtocol::V1.encode(None), b"PROXY UNKNOWN\r\n");
+
+    for protocol in [ProxyProtocol::V1, ProxyProtocol::V2] {
+        for header in [v4, v6] {
+            let mut data = protocol.encode(Some(&header));
+            data.extend(b"payload");
+            let mut reader = &data[..];
+            assert_eq!(read_proxy_header(&mut reader).unwrap(), Some(header));
+            assert_eq!(reader, b"payload");
+        }
+
+        let data = protocol.encode(None);
+        assert_eq!(read_proxy_header(&mut &data[..]).unwrap(), None);
+    }
+
+    assert!(read_proxy_header(&mut &b"GET / HTTP/1.1\r\n"[..]).is_err());
+    assert!(read_proxy_header(&mut &b"PROXY TCP4 1.2.3.4\r\n"[..]).is_err());
+}
+
+#[test]
+fn test_proxy_protocol_chain() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let inner = tcp_proxy::spawn_proxy_with_config(
+        "127.0.0.1:0",
+        server.local_addr().unwrap(),
+        tcp_proxy::ProxyConfig {
+            accept_proxy_protocol: true,
+            send_proxy_protocol: Some(tcp_proxy::ProxyProtocol::V1),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let outer = tcp_proxy::spawn_proxy_with_config(
+        "127.0.0.1:0",
+        inner.local_addr(),
+        tcp_proxy::ProxyConfig {
+            send_proxy_protocol: Some(tcp_proxy::ProxyProtocol::V2),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let mut client = TcpStream::connect(outer.local_addr()).unwrap();
+    client.write_all(b"ping").unwrap();
+
+    let (mut connection, _) = server.accept().unwrap();
+    let header = tcp_proxy::read_proxy_header(&mut connection)
+        .unwrap()
+        .unwrap();
+    assert_eq!(header.source, client.local_addr().unwrap());
+    assert_eq!(header.destination, outer.local_addr());
+
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+
+    outer.shutdown(None);
+    inner.shutdown(None);
+}
+
+fn spawn_chaos_proxy(
+    server: &TcpListener,
+    chaos: tcp_proxy::ChaosConfig,
+) -> tcp_proxy::ProxyHandle {
+    let config = tcp_proxy::ProxyConfig {
+        chaos: Some(chaos),
+        ..Default::default()
+    };
+    tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", server.local_addr().unwrap(), config).unwrap()
+}
+
+#[test]
+fn test_chaos_latency() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = spawn_chaos_proxy(
+        &server,
+        tcp_proxy::ChaosConfig {
+            latency: time::Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+
+    let start = time::Instant::now();
+    client.write_all(b"ping").unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+    assert!(start.elapsed() >= time::Duration::from_millis(100));
+
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_chaos_corrupt() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = spawn_chaos_proxy(
+        &server,
+        tcp_proxy::ChaosConfig {
+            corrupt_probability: 1.,
+            seed: Some(42),
+            ..Default::default()
+        },
+    );
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+
+    client.write_all(b"ping").unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    for (got, sent) in read_buffer.iter().zip(b"ping") {
+        assert_eq!((got ^ sent).count_ones(), 1);
+    }
+
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_chaos_reset() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = spawn_chaos_proxy(
+        &server,
+        tcp_proxy::ChaosConfig {
+            reset_probability: 1.,
+            ..Default::default()
+        },
+    );
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+
+    client.write_all(b"ping").unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    assert_eq!(connection.read(&mut read_buffer).unwrap(), 0);
+    assert!(matches!(client.read(&mut read_buffer), Ok(0) | Err(_)));
+
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_chaos_invalid_probability() {
+    for probability in [f64::NAN, -0.5, 1.5] {
+        for chaos in [
+            tcp_proxy::ChaosConfig {
+                reset_probability: probability,
+                ..Default::default()
+            },
+            tcp_proxy::ChaosConfig {
+                corrupt_probability: probability,
+                ..Default::default()
+            },
+        ] {
+            let config = tcp_proxy::ProxyConfig {
+                chaos: Some(chaos),
+                ..Default::default()
+            };
+            assert!(matches!(
+                tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", "127.0.0.1:1", config),
+                Err(tcp_proxy::ProxyError::InvalidConfig(_))
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_reconnect() {
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_addr = unused.local_addr().unwrap();
+    drop(unused);
+
+    let config = tcp_proxy::ProxyConfig {
+        reconnect: Some(tcp_proxy::Backoff {
+            retries: 10,
+            initial_delay: time::Duration::from_millis(20),
+            max_delay: time::Duration::from_millis(200),
+        }),
+        ..Default::default()
+    };
+    let proxy = tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", server_addr, config).unwrap();
+
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(b"ping").unwrap();
+
+    // The upstream comes up only after the first attempts have failed.
+    thread::sleep(time::Duration::from_millis(100));
+    let server = TcpListener::bind(server_addr).unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+    assert_eq!(proxy.stats().connect_failures, 0);
+
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_reconnect_gives_up() {
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_addr = unused.local_addr().unwrap();
+    drop(unused);
+
+    let config = tcp_proxy::ProxyConfig {
+        reconnect: Some(tcp_proxy::Backoff {
+            retries: 3,
+            initial_delay: time::Duration::from_millis(10),
+            max_delay: time::Duration::from_millis(10),
+        }),
+        ..Default::default()
+    };
+    let proxy = tcp_proxy::spawn_proxy_with_config("127.0.0.1:0", server_addr, config).unwrap();
+
+    let start = time::Instant::now();
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let mut read_buffer = [0; 4];
+    assert_eq!(client.read(&mut read_buffer).unwrap(), 0);
+    assert!(start.elapsed() >= time::Duration::from_millis(30));
+    assert_eq!(proxy.stats().connect_failures, 1);
+
+    proxy.shutdown(None);
+}
+
+fn socks5_connect(
+    proxy: &tcp_proxy::ProxyHandle,
+    credentials: Option<(&str, &str)>,
+    request: &[u8],
+) -> (TcpStream, [u8; 10]) {
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    let mut reply: [u8; 2] = [0; 2];
+    match credentials {
+        None => {
+            client.write_all(&[5, 1, 0]).unwrap();
+            client.read_exact(&mut reply).unwrap();
+            assert_eq!(reply, [5, 0]);
+        }
+        Some((user, password)) => {
+            client.write_all(&[5, 2, 0, 2]).unwrap();
+            client.read_exact(&mut reply).unwrap();
+            assert_eq!(reply, [5, 2]);
+
+            let mut auth = vec![1, user.len() as u8];
+            auth.extend(user.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend(password.as_bytes());
+            client.write_all(&auth).unwrap();
+            client.read_exact(&mut reply).unwrap();
+            if reply != [1, 0] {
+                return (client, [0; 10]);
+            }
+        }
+    }
+
+    client.write_all(request).unwrap();
+    let mut reply: [u8; 10] = [0; 10];
+    client.read_exact(&mut reply).unwrap();
+    (client, reply)
+}
+
+fn socks5_request_ipv4(addr: std::net::SocketAddr) -> Vec<u8> {
+    let std::net::SocketAddr::V4(addr) = addr else {
+        unreachable!()
+    };
+    let mut request = vec![5, 1, 0, 1];
+    request.extend(addr.ip().octets());
+    request.extend(addr.port().to_be_bytes());
+    request
+}
+
+#[test]
+fn test_socks5() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = tcp_proxy::spawn_socks5_proxy(
+        "127.0.0.1:0",
+        tcp_proxy::Socks5Config::default(),
+        Default::default(),
+    )
+    .unwrap();
+
+    let request = socks5_request_ipv4(server.local_addr().unwrap());
+    let (mut client, reply) = socks5_connect(&proxy, None, &request);
+    assert_eq!(reply[..4], [5, 0, 0, 1]);
+
+    client.write_all(b"ping").unwrap();
+    let (mut connection, _) = server.accept().unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+    connection.write_all(b"pong").unwrap();
+    client.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"pong");
+
+    // Domain names are resolved by the proxy.
+    let mut request = vec![5, 1, 0, 3, 9];
+    request.extend(b"localhost");
+    request.extend(server.local_addr().unwrap().port().to_be_bytes());
+    let (_client, reply) = socks5_connect(&proxy, None, &request);
+    assert_eq!(reply[1], 0);
+
+    // Nobody listens there.
+    let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+    let request = socks5_request_ipv4(unused.local_addr().unwrap());
+    drop(unused);
+    let (_client, reply) = socks5_connect(&proxy, None, &request);
+    assert_eq!(reply[1], 5);
+
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_socks5_auth() {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let socks = tcp_proxy::Socks5Config {
+        users: [("alice".to_string(), "secret".to_string())].into(),
+    };
+    let proxy = tcp_proxy::spawn_socks5_proxy("127.0.0.1:0", socks, Default::default()).unwrap();
+    let request = socks5_request_ipv4(server.local_addr().unwrap());
+
+    let (_client, reply) = socks5_connect(&proxy, Some(("alice", "secret")), &request);
+    assert_eq!(reply[1], 0);
+
+    let (mut client, _) = socks5_connect(&proxy, Some(("alice", "wrong")), &request);
+    let mut read_buffer = [0; 1];
+    assert_eq!(client.read(&mut read_buffer).unwrap(), 0);
+
+    // No-auth is not acceptable when users are configured.
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(&[5, 1, 0]).unwrap();
+    let mut reply: [u8; 2] = [0; 2];
+    client.read_exact(&mut reply).unwrap();
+    assert_eq!(reply, [5, 0xff]);
+
+    proxy.shutdown(None);
+}
+
+fn read_request(connection: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        connection.read_exact(&mut byte).unwrap();
+        request.push(byte[0]);
+    }
+    String::from_utf8(request).unwrap()
+}
+
+/// A minimal ClientHello record, with the SNI extension if `host` is set.
+fn client_hello(host: Option<&str>) -> Vec<u8> {
+    let mut extensions = vec![0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]; // ec_point_formats
+    if let Some(host) = host {
+        let mut names = vec![0x00];
+        names.extend((host.len() as u16).to_be_bytes());
+        names.extend(host.as_bytes());
+        extensions.extend([0x00, 0x00]);
+        extensions.extend((names.len() as u16 + 2).to_be_bytes());
+        extensions.extend((names.len() as u16).to_be_bytes());
+        extensions.extend(names);
+    }
+
+    let mut hello = vec![0x03, 0x03];
+    hello.extend([0x42; 32]);
+    hello.push(0); // session id
+    hello.extend([0x00, 0x02, 0x13, 0x01]);
+    hello.extend([0x01, 0x00]);
+    hello.extend((extensions.len() as u16).to_be_bytes());
+    hello.extend(extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend(hello);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend((handshake.len() as u16).to_be_bytes());
+    record.extend(handshake);
+    record
+}
+
+#[test]
+fn test_routing_http() {
+    let (api, web) = (
+        TcpListener::bind("127.0.0.1:0").unwrap(),
+        TcpListener::bind("127.0.0.1:0").unwrap(),
+    );
+    let mut routes = tcp_proxy::RoutingTable::new();
+    routes
+        .add_route("api.example.com", api.local_addr().unwrap())
+        .unwrap()
+        .add_route("*.example.com", web.local_addr().unwrap())
+        .unwrap();
+    let proxy = tcp_proxy::spawn_routing_proxy("127.0.0.1:0", routes, Default::default()).unwrap();
+
+    // The request is split so that the proxy has to wait for the headers.
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(b"GET / HTTP/1.1\r\nhost: ").unwrap();
+    thread::sleep(time::Duration::from_millis(10));
+    client.write_all(b"API.example.com:8080\r\n\r\n").unwrap();
+    let (mut connection, _) = api.accept().unwrap();
+    assert_eq!(
+        read_request(&mut connection),
+        "GET / HTTP/1.1\r\nhost: API.example.com:8080\r\n\r\n"
+    );
+    connection.write_all(b"pong").unwrap();
+    let mut read_buffer: [u8; 4] = [0; 4];
+    client.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"pong");
+
+    let request = "POST /submit HTTP/1.1\r\nHost: www.example.com\r\nContent-Length: 4\r\n\r\n";
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(request.as_bytes()).unwrap();
+    client.write_all(b"ping").unwrap();
+    let (mut connection, _) = web.accept().unwrap();
+    assert_eq!(read_request(&mut connection), request);
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(&read_buffer, b"ping");
+
+    // No route and no default one.
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: example.org\r\n\r\n")
+        .unwrap();
+    assert!(matches!(client.read(&mut read_buffer), Ok(0) | Err(_)));
+
+    // Sniffed bytes are counted as relayed.
+    let expected = (46 + request.len() + 4) as u64;
+    wait_until(|| proxy.stats().bytes_client_to_server == expected);
+    assert_eq!(proxy.stats().accepted_connections, 3);
+    proxy.shutdown(None);
+}
+
+#[test]
+fn test_routing_tls() {
+    let (tls, fallback) = (
+        TcpListener::bind("127.0.0.1:0").unwrap(),
+        TcpListener::bind("127.0.0.1:0").unwrap(),
+    );
+    let mut routes = tcp_proxy::RoutingTable::new();
+    routes
+        .add_route("secure.example.com", tls.local_addr().unwrap())
+        .unwrap()
+        .set_default(fallback.local_addr().unwrap())
+        .unwrap();
+    let proxy = tcp_proxy::spawn_routing_proxy("127.0.0.1:0", routes, Default::default()).unwrap();
+
+    let hello = client_hello(Some("secure.example.com"));
+    let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+    client.write_all(&hello[..7]).unwrap();
+    thread::sleep(time::Duration::from_millis(10));
+    client.write_all(&hello[7..]).unwrap();
+    let (mut connection, _) = tls.accept().unwrap();
+    let mut read_buffer = vec![0; hello.len()];
+    connection.read_exact(&mut read_buffer).unwrap();
+    assert_eq!(read_buffer, hello);
+
+    // Without SNI and for anything that is neither TLS nor HTTP the default
+    // route is taken.
+    for first_bytes in [client_hello(None), b"\x00ping".to_vec()] {
+        let mut client = TcpStream::connect(proxy.local_addr()).unwrap();
+        client.write_all(&first_bytes).unwrap();
+        let (mut connection, _) = fallback.accept().unwrap();
+        let mut read_buffer = vec![0; first_bytes.len()];
+        connection.read_exact(&mut read_buffer).unwrap();
+        assert_eq!(read_buffer, first_bytes);
+    }
+
+    proxy.shutdown(None);
+}
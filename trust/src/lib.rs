@@ -1,5 +1,19 @@
 #![forbid(unsafe_code)]
 
+mod registry;
+mod tournament;
+
+pub use registry::*;
+pub use tournament::*;
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter, Write},
+    rc::Rc,
+};
+
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+
 ////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -10,21 +24,161 @@ pub enum RoundOutcome {
     BothCheated,
 }
 
+impl RoundOutcome {
+    /// Points gained by the left and right agents under the default payoffs.
+    pub fn payoffs(self) -> (f64, f64) {
+        Payoffs::default().get(self)
+    }
+}
+
+impl Display for RoundOutcome {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(match self {
+            Self::BothCooperated => "both cooperated",
+            Self::LeftCheated => "left cheated",
+            Self::RightCheated => "right cheated",
+            Self::BothCheated => "both cheated",
+        })
+    }
+}
+
+/// Payoff matrix, named as usual for the prisoner's dilemma. The default one
+/// gives 2 each for cooperation, 3 to a cheater and -1 to the cheated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Payoffs {
+    /// Both cooperated.
+    pub reward: f64,
+    /// Cheated a cooperator.
+    pub temptation: f64,
+    /// Cooperated with a cheater.
+    pub sucker: f64,
+    /// Both cheated.
+    pub punishment: f64,
+}
+
+impl Default for Payoffs {
+    fn default() -> Self {
+        Self {
+            reward: 2.,
+            temptation: 3.,
+            sucker: -1.,
+            punishment: 0.,
+        }
+    }
+}
+
+impl Payoffs {
+    /// Points gained by the left and right agents.
+    pub fn get(&self, outcome: RoundOutcome) -> (f64, f64) {
+        match outcome {
+            RoundOutcome::BothCooperated => (self.reward, self.reward),
+            RoundOutcome::LeftCheated => (self.temptation, self.sucker),
+            RoundOutcome::RightCheated => (self.sucker, self.temptation),
+            RoundOutcome::BothCheated => (self.punishment, self.punishment),
+        }
+    }
+}
+
+pub type Round = (Play, Play, RoundOutcome);
+
+type Observer = Box<dyn FnMut(&RoundRecord)>;
+
 pub struct Game {
     left: Box<dyn Agent>,
     right: Box<dyn Agent>,
+    history: Vec<Round>,
+    payoffs: Payoffs,
+    discount: f64,
+    observers: Vec<Observer>,
 }
 
 impl Game {
     pub fn new(left: Box<dyn Agent>, right: Box<dyn Agent>) -> Self {
-        Self { left, right }
+        Self {
+            left,
+            right,
+            history: Vec::new(),
+            payoffs: Payoffs::default(),
+            discount: 1.,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Calls `observer` after every round played from now on, with the
+    /// scores after it.
+    pub fn add_observer(&mut self, observer: impl FnMut(&RoundRecord) + 'static) -> &mut Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    pub fn with_payoffs(mut self, payoffs: Payoffs) -> Self {
+        self.payoffs = payoffs;
+        self
+    }
+
+    /// Weighs the payoffs of the `k`-th round (counting from zero) by
+    /// `discount^k`. Panics unless `0 <= discount <= 1`.
+    pub fn with_discount(mut self, discount: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&discount),
+            "discount out of range: {discount}"
+        );
+        self.discount = discount;
+        self
+    }
+
+    pub fn payoffs(&self) -> &Payoffs {
+        &self.payoffs
+    }
+
+    pub fn discount(&self) -> f64 {
+        self.discount
+    }
+
+    /// Left play, right play and outcome of every round so far.
+    pub fn history(&self) -> &[Round] {
+        &self.history
+    }
+
+    /// Steps through the history with the running scores after each round.
+    pub fn replay(&self) -> Replay<'_> {
+        Replay {
+            rounds: self.history.iter(),
+            payoffs: self.payoffs,
+            discount: self.discount,
+            played: 0,
+            left_score: 0.,
+            right_score: 0.,
+        }
+    }
+
+    /// A table of the plays in every round so far with the running scores,
+    /// one line per round under a header line.
+    pub fn render_round_log(&self) -> String {
+        let mut log = format!(
+            "{:>5}  {:<9}  {:<9}  {:>10}  {:>11}\n",
+            "round", "left", "right", "left score", "right score"
+        );
+        for (i, record) in self.replay().enumerate() {
+            writeln!(
+                log,
+                "{:>5}  {:<9}  {:<9}  {:>10}  {:>11}",
+                i + 1,
+                record.left,
+                record.right,
+                record.left_score,
+                record.right_score
+            )
+            .unwrap();
+        }
+        log
     }
 
-    pub fn left_score(&self) -> i32 {
+    pub fn left_score(&self) -> f64 {
         self.left.score()
     }
 
-    pub fn right_score(&self) -> i32 {
+    pub fn right_score(&self) -> f64 {
         self.right.score()
     }
 
@@ -32,33 +186,259 @@ impl Game {
         let left_action = self.left.action(self.right.last_play());
         let right_action = self.right.action(self.left.last_play());
 
-        match left_action {
-            Play::Cheat => match right_action {
-                Play::Cheat => RoundOutcome::BothCheated,
-                Play::Cooperate => {
-                    self.left.upd_score(3);
-                    self.right.upd_score(-1);
-                    RoundOutcome::LeftCheated
-                }
-            },
-            Play::Cooperate => match right_action {
-                Play::Cheat => {
-                    self.left.upd_score(-1);
-                    self.right.upd_score(3);
-                    RoundOutcome::RightCheated
-                }
-                Play::Cooperate => {
-                    self.left.upd_score(2);
-                    self.right.upd_score(2);
-                    RoundOutcome::BothCooperated
-                }
+        let outcome = match (left_action, right_action) {
+            (Play::Cheat, Play::Cheat) => RoundOutcome::BothCheated,
+            (Play::Cheat, Play::Cooperate) => RoundOutcome::LeftCheated,
+            (Play::Cooperate, Play::Cheat) => RoundOutcome::RightCheated,
+            (Play::Cooperate, Play::Cooperate) => RoundOutcome::BothCooperated,
+        };
+        let (left_gain, right_gain) = self.payoffs.get(outcome);
+        let weight = self.discount.powi(self.history.len() as i32);
+        self.left.upd_score(weight * left_gain);
+        self.right.upd_score(weight * right_gain);
+        self.history.push((left_action, right_action, outcome));
+
+        if !self.observers.is_empty() {
+            let record = RoundRecord {
+                left: left_action,
+                right: right_action,
+                outcome,
+                left_score: self.left_score(),
+                right_score: self.right_score(),
+            };
+            for observer in &mut self.observers {
+                observer(&record);
+            }
+        }
+        outcome
+    }
+
+    /// Statistics over the history so far.
+    pub fn stats(&self) -> GameStats {
+        let rounds = self.history.len();
+        let cooperation_rate = |plays: usize| match rounds {
+            0 => 0.,
+            _ => plays as f64 / rounds as f64,
+        };
+        let (mut left_cooperated, mut right_cooperated) = (0, 0);
+        let (mut streak, mut longest_streak) = (0, 0);
+        for &(left, right, _) in &self.history {
+            left_cooperated += usize::from(left == Play::Cooperate);
+            right_cooperated += usize::from(right == Play::Cooperate);
+            streak = match (left, right) {
+                (Play::Cooperate, Play::Cooperate) => streak + 1,
+                _ => 0,
+            };
+            longest_streak = longest_streak.max(streak);
+        }
+
+        GameStats {
+            rounds,
+            left_cooperation_rate: cooperation_rate(left_cooperated),
+            right_cooperation_rate: cooperation_rate(right_cooperated),
+            longest_cooperation_streak: longest_streak,
+            first_defection: self
+                .history
+                .iter()
+                .position(|&(left, right, _)| left == Play::Cheat || right == Play::Cheat),
+            score_differential: self.left_score() - self.right_score(),
+        }
+    }
+
+    /// Lazily plays up to `n` rounds, yielding their outcomes.
+    pub fn rounds(&mut self, n: usize) -> Rounds<'_> {
+        Rounds {
+            game: self,
+            remaining: n,
+        }
+    }
+
+    /// Plays `n` rounds and sums them up.
+    pub fn play_rounds(&mut self, n: usize) -> GameSummary {
+        self.play(Termination::Rounds(n))
+    }
+
+    /// Plays until `termination` says to stop and sums up the rounds played.
+    pub fn play(&mut self, termination: Termination) -> GameSummary {
+        match termination {
+            Termination::Rounds(n) => self.play_while(|_, played| played < n),
+            Termination::Continuation { probability, seed } => {
+                assert!(
+                    (0.0..1.0).contains(&probability),
+                    "continuation probability out of range: {probability}"
+                );
+                let mut rng = StdRng::seed_from_u64(seed);
+                self.play_while(|_, played| played == 0 || rng.gen_bool(probability))
+            }
+            Termination::ScoreThreshold {
+                threshold,
+                max_rounds,
+            } => self.play_while(|game, played| {
+                played < max_rounds && game.left_score().max(game.right_score()) < threshold
+            }),
+        }
+    }
+
+    fn play_while(&mut self, mut go_on: impl FnMut(&Self, usize) -> bool) -> GameSummary {
+        let mut summary = GameSummary::default();
+        while go_on(self, summary.rounds) {
+            let outcome = self.play_round();
+            summary.rounds += 1;
+            *match outcome {
+                RoundOutcome::BothCooperated => &mut summary.both_cooperated,
+                RoundOutcome::LeftCheated => &mut summary.left_cheated,
+                RoundOutcome::RightCheated => &mut summary.right_cheated,
+                RoundOutcome::BothCheated => &mut summary.both_cheated,
+            } += 1;
+        }
+        summary.left_score = self.left_score();
+        summary.right_score = self.right_score();
+        summary
+    }
+}
+
+/// When a game is over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    /// After a fixed number of rounds.
+    Rounds(usize),
+    /// After every round the game goes on with `probability`, which must be
+    /// below 1. The first round is always played. Equal seeds give games of
+    /// equal length; the games of a `Tournament` get seeds of their own, see
+    /// `Termination::for_game`.
+    Continuation { probability: f64, seed: u64 },
+    /// As soon as either agent has at least `threshold` points, or after
+    /// `max_rounds` in case nobody gets there.
+    ScoreThreshold { threshold: f64, max_rounds: usize },
+}
+
+impl Termination {
+    /// The termination of the `index`-th of a series of games, which for
+    /// `Continuation` has a seed derived from `seed` and `index`. The 0th
+    /// game keeps `self`, and the others are unchanged.
+    pub fn for_game(self, index: u64) -> Self {
+        match self {
+            Self::Continuation { probability, seed } => Self::Continuation {
+                probability,
+                // Spreads consecutive indices far apart.
+                seed: seed.wrapping_add(index.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
             },
+            termination => termination,
+        }
+    }
+}
+
+pub struct Rounds<'a> {
+    game: &'a mut Game,
+    remaining: usize,
+}
+
+impl Iterator for Rounds<'_> {
+    type Item = RoundOutcome;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
         }
+        self.remaining -= 1;
+        Some(self.game.play_round())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Rounds<'_> {}
+
+/// Outcome counts of the rounds played by `Game::play_rounds`, together with
+/// the agents' total scores afterwards.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GameSummary {
+    pub rounds: usize,
+    pub both_cooperated: usize,
+    pub left_cheated: usize,
+    pub right_cheated: usize,
+    pub both_cheated: usize,
+    pub left_score: f64,
+    pub right_score: f64,
+}
+
+/// See `Game::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GameStats {
+    pub rounds: usize,
+    /// Share of the rounds each agent cooperated in, 0 for an empty history.
+    pub left_cooperation_rate: f64,
+    pub right_cooperation_rate: f64,
+    /// Most consecutive rounds in which both cooperated.
+    pub longest_cooperation_streak: usize,
+    /// Number of rounds before somebody cheated for the first time, `None` if
+    /// nobody did.
+    pub first_defection: Option<usize>,
+    /// Left score minus right score.
+    pub score_differential: f64,
+}
+
+/// Plays and outcome of a round, with the scores after it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoundRecord {
+    pub left: Play,
+    pub right: Play,
+    pub outcome: RoundOutcome,
+    pub left_score: f64,
+    pub right_score: f64,
+}
+
+pub struct Replay<'a> {
+    rounds: std::slice::Iter<'a, Round>,
+    payoffs: Payoffs,
+    discount: f64,
+    played: i32,
+    left_score: f64,
+    right_score: f64,
+}
+
+impl Iterator for Replay<'_> {
+    type Item = RoundRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(left, right, outcome) = self.rounds.next()?;
+        let (left_gain, right_gain) = self.payoffs.get(outcome);
+        let weight = self.discount.powi(self.played);
+        self.played += 1;
+        self.left_score += weight * left_gain;
+        self.right_score += weight * right_gain;
+        Some(RoundRecord {
+            left,
+            right,
+            outcome,
+            left_score: self.left_score,
+            right_score: self.right_score,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rounds.size_hint()
     }
 }
 
+impl ExactSizeIterator for Replay<'_> {}
+
 ////////////////////////////////////////////////////////////////////////////////
-pub trait Agent: Action + Score {}
+pub trait Agent: Action + Score {
+    /// Forgets the score and everything seen in past games, as if the agent
+    /// was just constructed.
+    fn reset(&mut self);
+
+    fn clone_box(&self) -> Box<dyn Agent>;
+}
+
+impl Clone for Box<dyn Agent> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
 
 pub trait Action {
     fn last_play(&self) -> Play;
@@ -66,36 +446,66 @@ pub trait Action {
 }
 
 pub trait Score {
-    fn score(&self) -> i32;
-    fn upd_score(&mut self, value: i32);
+    fn score(&self) -> f64;
+    fn upd_score(&mut self, value: f64);
 }
 
 // just playing around with declarative macros to get rid off the boilerplate
 macro_rules! impl_score {
     ($($agent:ident),+) => {
         $(impl Score for $agent {
-            fn score(&self) -> i32 {
+            fn score(&self) -> f64 {
                 self.score
             }
 
-            fn upd_score(&mut self, value: i32) {
+            fn upd_score(&mut self, value: f64) {
                 self.score += value;
             }
         })+
     };
 }
 
+// agents whose initial state is their `Default`
+macro_rules! impl_agent {
+    ($($agent:ident),+) => {
+        $(impl Agent for $agent {
+            fn reset(&mut self) {
+                *self = Self::default();
+            }
+
+            fn clone_box(&self) -> Box<dyn Agent> {
+                Box::new(self.clone())
+            }
+        })+
+    };
+}
+
+impl_agent!(
+    DetectiveAgent,
+    CopycatAgent,
+    GrudgerAgent,
+    CheatingAgent,
+    CooperatingAgent,
+    PavlovAgent,
+    TitForTwoTatsAgent
+);
+
 impl_score!(
     DetectiveAgent,
     CopycatAgent,
     GrudgerAgent,
     CheatingAgent,
-    CooperatingAgent
+    CooperatingAgent,
+    RandomAgent,
+    PavlovAgent,
+    TitForTwoTatsAgent,
+    HumanAgent,
+    GenerousCopycatAgent
 );
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct CheatingAgent {
-    score: i32,
+    score: f64,
 }
 
 impl CheatingAgent {
@@ -104,7 +514,6 @@ impl CheatingAgent {
     }
 }
 
-impl Agent for CheatingAgent {}
 impl Action for CheatingAgent {
     fn last_play(&self) -> Play {
         Play::Cheat
@@ -117,9 +526,9 @@ impl Action for CheatingAgent {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct CooperatingAgent {
-    score: i32,
+    score: f64,
 }
 
 impl CooperatingAgent {
@@ -128,7 +537,6 @@ impl CooperatingAgent {
     }
 }
 
-impl Agent for CooperatingAgent {}
 impl Action for CooperatingAgent {
     fn last_play(&self) -> Play {
         Play::Cooperate
@@ -142,9 +550,9 @@ impl Action for CooperatingAgent {
 ////////////////////////////////////////////////////////////////////////////////
 
 // always cooperates until first betrayal, then always cheats
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct GrudgerAgent {
-    score: i32,
+    score: f64,
     not_first_play: bool,
     cheated_once: Play,
 }
@@ -155,7 +563,6 @@ impl GrudgerAgent {
     }
 }
 
-impl Agent for GrudgerAgent {}
 impl Action for GrudgerAgent {
     fn last_play(&self) -> Play {
         Play::Cooperate
@@ -177,9 +584,9 @@ impl Action for GrudgerAgent {
 ////////////////////////////////////////////////////////////////////////////////
 
 // cooperates first, then repeats the last turn of opponent
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct CopycatAgent {
-    score: i32,
+    score: f64,
     not_first_play: bool,
     last_play: Play,
 }
@@ -190,7 +597,6 @@ impl CopycatAgent {
     }
 }
 
-impl Agent for CopycatAgent {}
 impl Action for CopycatAgent {
     fn last_play(&self) -> Play {
         self.last_play
@@ -210,9 +616,9 @@ impl Action for CopycatAgent {
 ////////////////////////////////////////////////////////////////////////////////
 
 // begins with sequence "cooperate", "cheat", "cooperate", "cooperate". If opponent never cheated, then always cheats. Otherwise, plays as copycat agent
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct DetectiveAgent {
-    score: i32,
+    score: f64,
     counter: u32,
     last_action: Play,
     cheated: bool,
@@ -224,7 +630,6 @@ impl DetectiveAgent {
     }
 }
 
-impl Agent for DetectiveAgent {}
 impl Action for DetectiveAgent {
     fn last_play(&self) -> Play {
         self.last_action
@@ -260,11 +665,283 @@ impl Action for DetectiveAgent {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+
+// win-stay, lose-shift: cooperates first, then repeats its own last play if the
+// opponent cooperated and switches it otherwise
+#[derive(Clone, Default)]
+pub struct PavlovAgent {
+    score: f64,
+    not_first_play: bool,
+    last_play: Play,
+}
+
+impl PavlovAgent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Action for PavlovAgent {
+    fn last_play(&self) -> Play {
+        self.last_play
+    }
+
+    fn action(&mut self, last_play: Play) -> Play {
+        if !self.not_first_play {
+            self.not_first_play = true;
+            self.last_play = Play::Cooperate;
+            return self.last_play;
+        }
+
+        if let Play::Cheat = last_play {
+            self.last_play = match self.last_play {
+                Play::Cheat => Play::Cooperate,
+                Play::Cooperate => Play::Cheat,
+            };
+        }
+        self.last_play
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// cooperates unless the opponent cheated twice in a row
+#[derive(Clone, Default)]
+pub struct TitForTwoTatsAgent {
+    score: f64,
+    not_first_play: bool,
+    cheats_in_row: u32,
+    last_play: Play,
+}
+
+impl TitForTwoTatsAgent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Action for TitForTwoTatsAgent {
+    fn last_play(&self) -> Play {
+        self.last_play
+    }
+
+    fn action(&mut self, last_play: Play) -> Play {
+        if !self.not_first_play {
+            self.not_first_play = true;
+            return Play::Cooperate;
+        }
+
+        self.cheats_in_row = match last_play {
+            Play::Cheat => self.cheats_in_row + 1,
+            Play::Cooperate => 0,
+        };
+        self.last_play = if self.cheats_in_row >= 2 {
+            Play::Cheat
+        } else {
+            Play::Cooperate
+        };
+        self.last_play
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// cooperates with probability `p` regardless of the opponent
+#[derive(Clone)]
+pub struct RandomAgent {
+    score: f64,
+    p: f64,
+    rng: Box<dyn CloneRng>,
+    last_play: Play,
+}
+
+/// An RNG that can be cloned behind a `Box`.
+trait CloneRng: RngCore {
+    fn clone_box(&self) -> Box<dyn CloneRng>;
+}
+
+impl<R: RngCore + Clone + 'static> CloneRng for R {
+    fn clone_box(&self) -> Box<dyn CloneRng> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn CloneRng> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl RandomAgent {
+    /// Panics unless `0 <= p <= 1`. Clones of the agent continue from a copy
+    /// of the RNG state, so they play the same sequence.
+    pub fn new(p: f64, rng: impl RngCore + Clone + 'static) -> Self {
+        assert!((0.0..=1.0).contains(&p), "probability out of range: {p}");
+        Self {
+            score: 0.,
+            p,
+            rng: Box::new(rng),
+            last_play: Play::default(),
+        }
+    }
+
+    pub fn from_seed(p: f64, seed: u64) -> Self {
+        Self::new(p, StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Agent for RandomAgent {
+    /// Keeps `p` and the RNG state, which are not learned from games.
+    fn reset(&mut self) {
+        self.score = 0.;
+        self.last_play = Play::default();
+    }
+
+    fn clone_box(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+}
+
+impl Action for RandomAgent {
+    fn last_play(&self) -> Play {
+        self.last_play
+    }
+
+    fn action(&mut self, _: Play) -> Play {
+        self.last_play = if self.rng.gen_bool(self.p) {
+            Play::Cooperate
+        } else {
+            Play::Cheat
+        };
+        self.last_play
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// plays as copycat agent, but answers a cheat with cooperation with probability
+// `forgiveness`, which keeps noisy games from falling into mutual retaliation
+#[derive(Clone)]
+pub struct GenerousCopycatAgent {
+    score: f64,
+    forgiveness: f64,
+    rng: Box<dyn CloneRng>,
+    not_first_play: bool,
+    last_play: Play,
+}
+
+impl GenerousCopycatAgent {
+    /// Panics unless `0 <= forgiveness <= 1`.
+    pub fn new(forgiveness: f64, rng: impl RngCore + Clone + 'static) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&forgiveness),
+            "probability out of range: {forgiveness}"
+        );
+        Self {
+            score: 0.,
+            forgiveness,
+            rng: Box::new(rng),
+            not_first_play: false,
+            last_play: Play::default(),
+        }
+    }
+
+    pub fn from_seed(forgiveness: f64, seed: u64) -> Self {
+        Self::new(forgiveness, StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Agent for GenerousCopycatAgent {
+    /// Keeps `forgiveness` and the RNG state.
+    fn reset(&mut self) {
+        self.score = 0.;
+        self.not_first_play = false;
+        self.last_play = Play::default();
+    }
+
+    fn clone_box(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+}
+
+impl Action for GenerousCopycatAgent {
+    fn last_play(&self) -> Play {
+        self.last_play
+    }
+
+    fn action(&mut self, last_play: Play) -> Play {
+        if !self.not_first_play {
+            self.not_first_play = true;
+            return Play::Cooperate;
+        }
+
+        self.last_play = match last_play {
+            Play::Cheat if self.rng.gen_bool(self.forgiveness) => Play::Cooperate,
+            play => play,
+        };
+        self.last_play
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// asks `choose` for every play, passing the opponent's last one, so that a
+// person (or anything else outside the engine) can take part in a game
+#[derive(Clone)]
+pub struct HumanAgent {
+    score: f64,
+    choose: Rc<RefCell<dyn FnMut(Play) -> Play>>,
+    last_play: Play,
+}
+
+impl HumanAgent {
+    /// Clones of the agent share `choose`.
+    pub fn new(choose: impl FnMut(Play) -> Play + 'static) -> Self {
+        Self {
+            score: 0.,
+            choose: Rc::new(RefCell::new(choose)),
+            last_play: Play::default(),
+        }
+    }
+}
+
+impl Agent for HumanAgent {
+    fn reset(&mut self) {
+        self.score = 0.;
+        self.last_play = Play::default();
+    }
+
+    fn clone_box(&self) -> Box<dyn Agent> {
+        Box::new(self.clone())
+    }
+}
+
+impl Action for HumanAgent {
+    fn last_play(&self) -> Play {
+        self.last_play
+    }
+
+    fn action(&mut self, last_play: Play) -> Play {
+        self.last_play = (self.choose.borrow_mut())(last_play);
+        self.last_play
+    }
+}
+
 ///////////////////////////////
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub enum Play {
     Cheat,
     #[default]
     Cooperate,
 }
+
+impl Display for Play {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(match self {
+            Self::Cheat => "cheat",
+            Self::Cooperate => "cooperate",
+        })
+    }
+}
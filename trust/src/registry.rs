@@ -0,0 +1,87 @@
+use crate::{
+    Agent, CheatingAgent, CooperatingAgent, CopycatAgent, DetectiveAgent, GenerousCopycatAgent,
+    GrudgerAgent, PavlovAgent, RandomAgent, TitForTwoTatsAgent,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub(crate) type AgentFactory = Box<dyn Fn() -> Box<dyn Agent>>;
+
+/// Constructors of agents by strategy name.
+pub struct Registry {
+    factories: Vec<(String, AgentFactory)>,
+}
+
+impl Registry {
+    /// An empty registry, see `Registry::default` for the built-in strategies.
+    pub fn new() -> Self {
+        Self {
+            factories: Vec::new(),
+        }
+    }
+
+    /// Registers `make` under `name`, replacing a previous strategy of the
+    /// same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        make: impl Fn() -> Box<dyn Agent> + 'static,
+    ) -> &mut Self {
+        let name = name.into();
+        let make = Box::new(make);
+        match self.factories.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, factory)) => *factory = make,
+            None => self.factories.push((name, make)),
+        }
+        self
+    }
+
+    pub fn create(&self, name: &str) -> Option<Box<dyn Agent>> {
+        let (_, make) = self.factories.iter().find(|(n, _)| n == name)?;
+        Some(make())
+    }
+
+    /// Registered names in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+impl Default for Registry {
+    /// The built-in strategies: `cheater`, `cooperator`, `copycat`, `grudger`,
+    /// `detective`, `pavlov`, `tit-for-two-tats`, `random`, cooperating half
+    /// of the time, and `generous-copycat`, forgiving one cheat in ten. The
+    /// last two use entropy-seeded RNGs.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register("cheater", || Box::new(CheatingAgent::new()))
+            .register("cooperator", || Box::new(CooperatingAgent::new()))
+            .register("copycat", || Box::new(CopycatAgent::new()))
+            .register("grudger", || Box::new(GrudgerAgent::new()))
+            .register("detective", || Box::new(DetectiveAgent::new()))
+            .register("pavlov", || Box::new(PavlovAgent::new()))
+            .register("tit-for-two-tats", || Box::new(TitForTwoTatsAgent::new()))
+            .register("random", || {
+                Box::new(RandomAgent::new(0.5, StdRng::from_entropy()))
+            })
+            .register("generous-copycat", || {
+                Box::new(GenerousCopycatAgent::new(0.1, StdRng::from_entropy()))
+            });
+        registry
+    }
+}
+
+thread_local! {
+    // Per thread, as factories need not be `Sync`.
+    static BUILT_IN: Registry = Registry::default();
+}
+
+impl dyn Agent {
+    /// Constructs a built-in strategy, see `Registry::default`.
+    pub fn by_name(name: &str) -> Option<Box<dyn Agent>> {
+        BUILT_IN.with(|registry| registry.create(name))
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::{self, Write};
+
+use crate::{registry::AgentFactory, Agent, Game, Payoffs, Termination};
+
+/// Round-robin tournament: every pair of distinct entrants plays one game,
+/// each with freshly constructed agents. Games are numbered in the order
+/// they are played, see `Termination::for_game`.
+pub struct Tournament {
+    termination: Termination,
+    payoffs: Payoffs,
+    discount: f64,
+    entrants: Vec<(String, AgentFactory)>,
+}
+
+impl Tournament {
+    /// Games of `rounds` rounds, see `Tournament::termination` for others.
+    pub fn new(rounds: usize) -> Self {
+        Self {
+            termination: Termination::Rounds(rounds),
+            payoffs: Payoffs::default(),
+            discount: 1.,
+            entrants: Vec::new(),
+        }
+    }
+
+    pub fn termination(&mut self, termination: Termination) -> &mut Self {
+        self.termination = termination;
+        self
+    }
+
+    /// Payoffs used in every game, see `Game::with_payoffs`.
+    pub fn payoffs(&mut self, payoffs: Payoffs) -> &mut Self {
+        self.payoffs = payoffs;
+        self
+    }
+
+    /// Discount used in every game, see `Game::with_discount`.
+    pub fn discount(&mut self, discount: f64) -> &mut Self {
+        self.discount = discount;
+        self
+    }
+
+    pub fn add_agent(
+        &mut self,
+        name: impl Into<String>,
+        make: impl Fn() -> Box<dyn Agent> + 'static,
+    ) -> &mut Self {
+        self.entrants.push((name.into(), Box::new(make)));
+        self
+    }
+
+    /// Enters `agent` itself: every game gets a reset clone of it.
+    pub fn add_agent_instance(
+        &mut self,
+        name: impl Into<String>,
+        agent: Box<dyn Agent>,
+    ) -> &mut Self {
+        self.add_agent(name, move || {
+            let mut agent = agent.clone();
+            agent.reset();
+            agent
+        })
+    }
+
+    pub fn run(&self) -> Scoreboard {
+        let n = self.entrants.len();
+        let mut scores = vec![vec![None; n]; n];
+        let mut rounds = 0;
+        let mut games = 0..;
+        for (i, (_, make_left)) in self.entrants.iter().enumerate() {
+            for (j, (_, make_right)) in self.entrants.iter().enumerate().skip(i + 1) {
+                let termination = self.termination.for_game(games.next().unwrap());
+                let summary = Game::new(make_left(), make_right())
+                    .with_payoffs(self.payoffs)
+                    .with_discount(self.discount)
+                    .play(termination);
+                rounds = rounds.max(summary.rounds);
+                scores[i][j] = Some(summary.left_score);
+                scores[j][i] = Some(summary.right_score);
+            }
+        }
+
+        Scoreboard {
+            rounds,
+            names: self.entrants.iter().map(|(name, _)| name.clone()).collect(),
+            scores,
+        }
+    }
+
+    /// Plays every ordered pair of entrants, self-play included, and averages
+    /// the scores per round over `repetitions` games each.
+    pub fn score_matrix(&self, repetitions: usize) -> ScoreMatrix {
+        let n = self.entrants.len();
+        let mut scores = vec![vec![0.; n]; n];
+        let mut games = 0..;
+        for (i, (_, make_left)) in self.entrants.iter().enumerate() {
+            for (j, (_, make_right)) in self.entrants.iter().enumerate() {
+                let (mut total, mut rounds) = (0., 0);
+                for _ in 0..repetitions {
+                    let termination = self.termination.for_game(games.next().unwrap());
+                    let summary = Game::new(make_left(), make_right())
+                        .with_payoffs(self.payoffs)
+                        .with_discount(self.discount)
+                        .play(termination);
+                    total += summary.left_score;
+                    rounds += summary.rounds;
+                }
+                if rounds > 0 {
+                    scores[i][j] = total / rounds as f64;
+                }
+            }
+        }
+
+        ScoreMatrix {
+            names: self.entrants.iter().map(|(name, _)| name.clone()).collect(),
+            scores,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Average score per round of every entrant against every other one and
+/// itself, see `Tournament::score_matrix`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreMatrix {
+    names: Vec<String>,
+    /// `scores[i][j]` is what entrant `i` scored against `j`.
+    scores: Vec<Vec<f64>>,
+}
+
+impl ScoreMatrix {
+    /// Row and column labels.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Average score of `agent` against `opponent`, `None` for unknown names.
+    pub fn score(&self, agent: &str, opponent: &str) -> Option<f64> {
+        Some(self.scores[self.index(agent)?][self.index(opponent)?])
+    }
+
+    /// The averages as rows by entrant, with columns by opponent in the
+    /// same order as `names`.
+    pub fn values(&self) -> &[Vec<f64>] {
+        &self.scores
+    }
+
+    /// Averages of `agent` against every entrant, in `names` order.
+    pub fn row(&self, agent: &str) -> Option<&[f64]> {
+        Some(&self.scores[self.index(agent)?])
+    }
+
+    /// Averages of every entrant against `opponent`, in `names` order.
+    pub fn column(&self, opponent: &str) -> Option<Vec<f64>> {
+        let j = self.index(opponent)?;
+        Some(self.scores.iter().map(|row| row[j]).collect())
+    }
+
+    /// Smallest and largest average, e.g. for a heatmap color scale. `None`
+    /// if there are no entrants.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        self.scores.iter().flatten().fold(None, |range, &score| {
+            Some(match range {
+                None => (score, score),
+                Some((min, max)) => (score.min(min), score.max(max)),
+            })
+        })
+    }
+
+    /// Average of `agent` over all the opponents, including itself.
+    pub fn mean(&self, agent: &str) -> Option<f64> {
+        let row = &self.scores[self.index(agent)?];
+        Some(row.iter().sum::<f64>() / row.len() as f64)
+    }
+
+    /// Writes a header of opponent names, then one row per entrant with its
+    /// average against each of them.
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        for name in &self.names {
+            write!(w, ",{}", csv_field(name))?;
+        }
+        writeln!(w)?;
+        for (name, row) in self.names.iter().zip(&self.scores) {
+            write!(w, "{}", csv_field(name))?;
+            for score in row {
+                write!(w, ",{score}")?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut buf = Vec::new();
+        self.write_csv(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct Scoreboard {
+    rounds: usize,
+    names: Vec<String>,
+    /// `scores[i][j]` is what entrant `i` scored against `j`.
+    scores: Vec<Vec<Option<f64>>>,
+}
+
+impl Scoreboard {
+    /// Length of the longest game.
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Score of `agent` in its game against `opponent`, `None` for unknown
+    /// names or `agent == opponent`.
+    pub fn score(&self, agent: &str, opponent: &str) -> Option<f64> {
+        self.scores[self.index(agent)?][self.index(opponent)?]
+    }
+
+    pub fn total(&self, agent: &str) -> Option<f64> {
+        Some(self.scores[self.index(agent)?].iter().flatten().sum())
+    }
+
+    /// Entrants with their totals, best first. Ties keep entry order.
+    pub fn standings(&self) -> Vec<(&str, f64)> {
+        let mut standings = self
+            .names
+            .iter()
+            .zip(&self.scores)
+            .map(|(name, row)| (name.as_str(), row.iter().flatten().sum::<f64>()))
+            .collect::<Vec<_>>();
+        standings.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        standings
+    }
+
+    /// Writes the per-matchup matrix: a header of opponent names, then one
+    /// row per entrant with its score against each of them. The diagonal is
+    /// left empty.
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        for name in &self.names {
+            write!(w, ",{}", csv_field(name))?;
+        }
+        writeln!(w)?;
+        for (name, row) in self.names.iter().zip(&self.scores) {
+            write!(w, "{}", csv_field(name))?;
+            for score in row {
+                match score {
+                    Some(score) => write!(w, ",{score}")?,
+                    None => write!(w, ",")?,
+                }
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `{"rounds": ..., "standings": [{"name": ..., "total": ...}, ...],
+    /// "matches": [{"left": ..., "right": ..., "left_score": ...,
+    /// "right_score": ...}, ...]}`.
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "{{\"rounds\": {}, \"standings\": [", self.rounds)?;
+        for (i, (name, total)) in self.standings().into_iter().enumerate() {
+            if i > 0 {
+                write!(w, ", ")?;
+            }
+            write!(w, "{{\"name\": {}, \"total\": {total}}}", json_string(name))?;
+        }
+        write!(w, "], \"matches\": [")?;
+        let mut first = true;
+        for i in 0..self.names.len() {
+            for j in i + 1..self.names.len() {
+                if !first {
+                    write!(w, ", ")?;
+                }
+                first = false;
+                write!(
+                    w,
+                    "{{\"left\": {}, \"right\": {}, \"left_score\": {}, \"right_score\": {}}}",
+                    json_string(&self.names[i]),
+                    json_string(&self.names[j]),
+                    self.scores[i][j].unwrap(),
+                    self.scores[j][i].unwrap(),
+                )?;
+            }
+        }
+        write!(w, "]}}")
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut buf = Vec::new();
+        self.write_csv(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut buf = Vec::new();
+        self.write_json(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
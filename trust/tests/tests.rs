@@ -1,10 +1,13 @@
 use trust::{
-    CheatingAgent, CooperatingAgent, CopycatAgent, DetectiveAgent, Game, GrudgerAgent, RoundOutcome,
+    Agent, CheatingAgent, CooperatingAgent, CopycatAgent, DetectiveAgent, Game, GameStats,
+    GameSummary, GenerousCopycatAgent, GrudgerAgent, HumanAgent, PavlovAgent, Payoffs, Play,
+    RandomAgent, Registry, RoundOutcome, RoundRecord, ScoreMatrix, Termination, TitForTwoTatsAgent,
+    Tournament,
 };
 
 fn test_game<'a>(mut game: Game, expected_outcomes: impl IntoIterator<Item = &'a RoundOutcome>) {
-    let mut left_score = 0;
-    let mut right_score = 0;
+    let mut left_score = 0.;
+    let mut right_score = 0.;
 
     for (i, expected) in expected_outcomes.into_iter().enumerate() {
         let outcome = game.play_round();
@@ -20,16 +23,16 @@ fn test_game<'a>(mut game: Game, expected_outcomes: impl IntoIterator<Item = &'a
 
         match outcome {
             RoundOutcome::BothCooperated => {
-                left_score += 2;
-                right_score += 2;
+                left_score += 2.;
+                right_score += 2.;
             }
             RoundOutcome::LeftCheated => {
-                left_score += 3;
-                right_score -= 1;
+                left_score += 3.;
+                right_score -= 1.;
             }
             RoundOutcome::RightCheated => {
-                left_score -= 1;
-                right_score += 3;
+                left_score -= 1.;
+                right_score += 3.;
             }
             RoundOutcome::BothCheated => (),
         }
@@ -208,3 +211,621 @@ fn test_copycat_detective() {
             .chain([RoundOutcome::BothCooperated; 11].iter()),
     );
 }
+
+#[test]
+fn test_pavlov_copycat() {
+    let game = Game::new(Box::new(PavlovAgent::new()), Box::new(CopycatAgent::new()));
+    test_game(game, &[RoundOutcome::BothCooperated; 13]);
+}
+
+#[test]
+fn test_pavlov_cheater() {
+    let game = Game::new(Box::new(PavlovAgent::new()), Box::new(CheatingAgent::new()));
+    test_game(
+        game,
+        [RoundOutcome::RightCheated, RoundOutcome::BothCheated]
+            .iter()
+            .cycle()
+            .take(10),
+    );
+}
+
+#[test]
+fn test_pavlov_detective() {
+    let game = Game::new(
+        Box::new(PavlovAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    test_game(
+        game,
+        [RoundOutcome::BothCooperated; 1]
+            .iter()
+            .chain([RoundOutcome::RightCheated; 1].iter())
+            .chain([RoundOutcome::LeftCheated; 2].iter())
+            .chain([RoundOutcome::BothCheated; 1].iter())
+            .chain([RoundOutcome::BothCooperated; 9].iter()),
+    );
+}
+
+#[test]
+fn test_tit_for_two_tats_cheater() {
+    let game = Game::new(
+        Box::new(TitForTwoTatsAgent::new()),
+        Box::new(CheatingAgent::new()),
+    );
+    test_game(
+        game,
+        [RoundOutcome::RightCheated; 2]
+            .iter()
+            .chain([RoundOutcome::BothCheated; 10].iter()),
+    );
+}
+
+#[test]
+fn test_tit_for_two_tats_grudger() {
+    let game = Game::new(
+        Box::new(TitForTwoTatsAgent::new()),
+        Box::new(GrudgerAgent::new()),
+    );
+    test_game(game, &[RoundOutcome::BothCooperated; 15]);
+}
+
+#[test]
+fn test_tit_for_two_tats_pavlov() {
+    let game = Game::new(
+        Box::new(TitForTwoTatsAgent::new()),
+        Box::new(PavlovAgent::new()),
+    );
+    test_game(game, &[RoundOutcome::BothCooperated; 12]);
+}
+
+#[test]
+fn test_tit_for_two_tats_detective() {
+    let game = Game::new(
+        Box::new(TitForTwoTatsAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    test_game(
+        game,
+        [RoundOutcome::BothCooperated; 1]
+            .iter()
+            .chain([RoundOutcome::RightCheated; 1].iter())
+            .chain([RoundOutcome::BothCooperated; 2].iter())
+            .chain([RoundOutcome::RightCheated; 2].iter())
+            .chain([RoundOutcome::BothCheated; 8].iter()),
+    );
+}
+
+#[test]
+fn test_tournament_export() {
+    let mut tournament = Tournament::new(5);
+    tournament
+        .add_agent("coop", || Box::new(CooperatingAgent::new()))
+        .add_agent("cheat", || Box::new(CheatingAgent::new()))
+        .add_agent("copy\"cat", || Box::new(CopycatAgent::new()));
+    let scoreboard = tournament.run();
+
+    assert_eq!(scoreboard.score("coop", "cheat"), Some(-5.));
+    assert_eq!(scoreboard.score("cheat", "coop"), Some(15.));
+    assert_eq!(scoreboard.score("cheat", "cheat"), None);
+    assert_eq!(scoreboard.total("copy\"cat"), Some(9.));
+    assert_eq!(scoreboard.total("nobody"), None);
+    assert_eq!(
+        scoreboard.standings(),
+        [("cheat", 18.), ("copy\"cat", 9.), ("coop", 5.)]
+    );
+
+    assert_eq!(
+        scoreboard.to_csv(),
+        ",coop,cheat,\"copy\"\"cat\"\n\
+         coop,,-5,10\n\
+         cheat,15,,3\n\
+         \"copy\"\"cat\",10,-1,\n"
+    );
+    assert_eq!(
+        scoreboard.to_json(),
+        r#"{"rounds": 5, "standings": [{"name": "cheat", "total": 18}, "#.to_string()
+            + r#"{"name": "copy\"cat", "total": 9}, {"name": "coop", "total": 5}], "#
+            + r#""matches": [{"left": "coop", "right": "cheat", "left_score": -5, "right_score": 15}, "#
+            + r#"{"left": "coop", "right": "copy\"cat", "left_score": 10, "right_score": 10}, "#
+            + r#"{"left": "cheat", "right": "copy\"cat", "left_score": 3, "right_score": -1}]}"#
+    );
+}
+
+#[test]
+fn test_random_agent() {
+    let game = Game::new(
+        Box::new(RandomAgent::from_seed(1.0, 0)),
+        Box::new(RandomAgent::from_seed(0.0, 0)),
+    );
+    test_game(game, &[RoundOutcome::RightCheated; 10]);
+
+    let outcomes = |seed| {
+        let mut game = Game::new(
+            Box::new(RandomAgent::from_seed(0.5, seed)),
+            Box::new(CooperatingAgent::new()),
+        );
+        (0..50).map(|_| game.play_round()).collect::<Vec<_>>()
+    };
+    let first = outcomes(42);
+    assert_eq!(first, outcomes(42));
+    assert!(first.contains(&RoundOutcome::BothCooperated));
+    assert!(first.contains(&RoundOutcome::LeftCheated));
+}
+
+#[test]
+fn test_history_replay() {
+    let mut game = Game::new(
+        Box::new(CooperatingAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    for _ in 0..4 {
+        game.play_round();
+    }
+
+    assert_eq!(
+        game.history(),
+        [
+            (
+                Play::Cooperate,
+                Play::Cooperate,
+                RoundOutcome::BothCooperated
+            ),
+            (Play::Cooperate, Play::Cheat, RoundOutcome::RightCheated),
+            (
+                Play::Cooperate,
+                Play::Cooperate,
+                RoundOutcome::BothCooperated
+            ),
+            (
+                Play::Cooperate,
+                Play::Cooperate,
+                RoundOutcome::BothCooperated
+            ),
+        ]
+    );
+
+    let replay = game.replay().collect::<Vec<_>>();
+    assert_eq!(replay.len(), 4);
+    assert_eq!(
+        replay[1],
+        RoundRecord {
+            left: Play::Cooperate,
+            right: Play::Cheat,
+            outcome: RoundOutcome::RightCheated,
+            left_score: 1.,
+            right_score: 5.,
+        }
+    );
+    let last = replay.last().unwrap();
+    assert_eq!(
+        (last.left_score, last.right_score),
+        (game.left_score(), game.right_score())
+    );
+}
+
+#[test]
+fn test_rounds() {
+    let mut game = Game::new(
+        Box::new(CooperatingAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    let rounds = game.rounds(6);
+    assert_eq!(rounds.len(), 6);
+    let cheats = rounds
+        .filter(|outcome| *outcome == RoundOutcome::RightCheated)
+        .count();
+    assert_eq!(cheats, 3);
+    assert_eq!(game.history().len(), 6);
+
+    assert_eq!(
+        game.play_rounds(4),
+        GameSummary {
+            rounds: 4,
+            right_cheated: 4,
+            left_score: -1.,
+            right_score: 27.,
+            ..GameSummary::default()
+        }
+    );
+    assert_eq!(game.rounds(0).next(), None);
+}
+
+#[test]
+fn test_registry() {
+    let registry = Registry::default();
+    assert_eq!(
+        registry.names().collect::<Vec<_>>(),
+        [
+            "cheater",
+            "cooperator",
+            "copycat",
+            "grudger",
+            "detective",
+            "pavlov",
+            "tit-for-two-tats",
+            "random",
+            "generous-copycat"
+        ]
+    );
+    for name in registry.names() {
+        assert!(<dyn Agent>::by_name(name).is_some(), "{name}");
+    }
+    assert!(<dyn Agent>::by_name("nobody").is_none());
+
+    let game = Game::new(
+        <dyn Agent>::by_name("cooperator").unwrap(),
+        registry.create("cheater").unwrap(),
+    );
+    test_game(game, &[RoundOutcome::RightCheated; 5]);
+
+    let mut registry = Registry::new();
+    registry
+        .register("nice", || Box::new(CheatingAgent::new()))
+        .register("nice", || Box::new(CooperatingAgent::new()));
+    assert_eq!(registry.names().collect::<Vec<_>>(), ["nice"]);
+    let game = Game::new(
+        registry.create("nice").unwrap(),
+        registry.create("nice").unwrap(),
+    );
+    test_game(game, &[RoundOutcome::BothCooperated; 5]);
+    assert!(registry.create("copycat").is_none());
+}
+
+#[test]
+fn test_payoffs_and_discount() {
+    let payoffs = Payoffs {
+        reward: 1.5,
+        temptation: 2.5,
+        ..Payoffs::default()
+    };
+    assert_eq!(payoffs.get(RoundOutcome::LeftCheated), (2.5, -1.));
+    assert_eq!(RoundOutcome::BothCooperated.payoffs(), (2., 2.));
+
+    let mut game = Game::new(
+        Box::new(CooperatingAgent::new()),
+        Box::new(CooperatingAgent::new()),
+    )
+    .with_payoffs(payoffs)
+    .with_discount(0.5);
+    let summary = game.play_rounds(3);
+    assert_eq!((summary.left_score, summary.right_score), (2.625, 2.625));
+    let scores = game
+        .replay()
+        .map(|step| step.left_score)
+        .collect::<Vec<_>>();
+    assert_eq!(scores, [1.5, 2.25, 2.625]);
+
+    let mut tournament = Tournament::new(2);
+    tournament
+        .payoffs(payoffs)
+        .discount(0.5)
+        .add_agent("coop", || Box::new(CooperatingAgent::new()))
+        .add_agent("cheat", || Box::new(CheatingAgent::new()));
+    let scoreboard = tournament.run();
+    assert_eq!(scoreboard.standings(), [("cheat", 3.75), ("coop", -1.5)]);
+    assert_eq!(
+        scoreboard.to_csv(),
+        ",coop,cheat\ncoop,,-1.5\ncheat,3.75,\n"
+    );
+}
+
+#[test]
+fn test_termination() {
+    let new_game = || {
+        Game::new(
+            Box::new(CheatingAgent::new()),
+            Box::new(CooperatingAgent::new()),
+        )
+    };
+
+    assert_eq!(new_game().play(Termination::Rounds(3)).rounds, 3);
+
+    let summary = new_game().play(Termination::ScoreThreshold {
+        threshold: 10.,
+        max_rounds: 100,
+    });
+    assert_eq!((summary.rounds, summary.left_score), (4, 12.));
+    let mut game = Game::new(
+        Box::new(CheatingAgent::new()),
+        Box::new(CheatingAgent::new()),
+    );
+    let threshold = Termination::ScoreThreshold {
+        threshold: 1.,
+        max_rounds: 7,
+    };
+    assert_eq!(game.play(threshold).rounds, 7);
+
+    let continuation = |seed| Termination::Continuation {
+        probability: 0.9,
+        seed,
+    };
+    let lengths = (0..20)
+        .map(|seed| new_game().play(continuation(seed)).rounds)
+        .collect::<Vec<_>>();
+    assert!(lengths.iter().all(|&n| n >= 1));
+    assert!(lengths.iter().any(|&n| n != lengths[0]));
+    assert_eq!(new_game().play(continuation(5)).rounds, lengths[5]);
+    let never = Termination::Continuation {
+        probability: 0.,
+        seed: 0,
+    };
+    assert_eq!(new_game().play(never).rounds, 1);
+
+    let mut tournament = Tournament::new(0);
+    tournament
+        .termination(continuation(1))
+        .add_agent("coop", || Box::new(CooperatingAgent::new()))
+        .add_agent("cheat", || Box::new(CheatingAgent::new()));
+    let scoreboard = tournament.run();
+    assert_eq!(scoreboard.rounds(), lengths[1]);
+    assert_eq!(
+        scoreboard.score("cheat", "coop"),
+        Some(3. * lengths[1] as f64)
+    );
+
+    // Games of one tournament don't share their length.
+    assert_eq!(continuation(3).for_game(0), continuation(3));
+    assert_ne!(continuation(3).for_game(1), continuation(3));
+    assert_eq!(threshold.for_game(4), threshold);
+    let mut tournament = Tournament::new(0);
+    tournament.termination(continuation(1));
+    tournament.add_agent("cheat", || Box::new(CheatingAgent::new()));
+    for i in 0..8 {
+        tournament.add_agent(format!("coop {i}"), || Box::new(CooperatingAgent::new()));
+    }
+    let scoreboard = tournament.run();
+    let cheat_scores = (0..8)
+        .map(|i| scoreboard.score("cheat", &format!("coop {i}")).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(cheat_scores[0], 3. * lengths[1] as f64);
+    assert!(cheat_scores.iter().any(|&score| score != cheat_scores[0]));
+}
+
+#[test]
+fn test_game_stats() {
+    let mut game = Game::new(
+        Box::new(CooperatingAgent::new()),
+        Box::new(CooperatingAgent::new()),
+    );
+    assert_eq!(game.stats(), GameStats::default());
+    game.play_rounds(3);
+    assert_eq!(
+        game.stats(),
+        GameStats {
+            rounds: 3,
+            left_cooperation_rate: 1.,
+            right_cooperation_rate: 1.,
+            longest_cooperation_streak: 3,
+            first_defection: None,
+            score_differential: 0.,
+        }
+    );
+
+    // Detective opens with cooperate, cheat, cooperate, cooperate and then
+    // keeps cheating the cooperator.
+    let mut game = Game::new(
+        Box::new(CooperatingAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    game.play_rounds(8);
+    assert_eq!(
+        game.stats(),
+        GameStats {
+            rounds: 8,
+            left_cooperation_rate: 1.,
+            right_cooperation_rate: 0.375,
+            longest_cooperation_streak: 2,
+            first_defection: Some(1),
+            score_differential: -20.,
+        }
+    );
+}
+
+#[test]
+fn test_reset_and_clone() {
+    let fresh_outcomes = |agent: Box<dyn Agent>| {
+        let mut game = Game::new(agent, Box::new(CopycatAgent::new()));
+        game.rounds(6).collect::<Vec<_>>()
+    };
+    let expected = fresh_outcomes(Box::new(DetectiveAgent::new()));
+
+    let mut detective: Box<dyn Agent> = Box::new(DetectiveAgent::new());
+    for _ in 0..6 {
+        detective.action(Play::Cheat);
+    }
+    detective.upd_score(5.);
+    let mut clone = detective.clone();
+    assert_eq!(clone.score(), 5.);
+    clone.reset();
+    assert_eq!(clone.score(), 0.);
+    assert_eq!(fresh_outcomes(clone), expected);
+    assert_eq!(detective.score(), 5.);
+
+    // Clones of a random agent share its RNG state, reset doesn't rewind it.
+    let mut random: Box<dyn Agent> = Box::new(RandomAgent::from_seed(0.5, 7));
+    let plays = |agent: &mut Box<dyn Agent>| {
+        (0..16)
+            .map(|_| agent.action(Play::Cooperate))
+            .collect::<Vec<_>>()
+    };
+    let mut clone = random.clone();
+    let first = plays(&mut random);
+    assert_eq!(plays(&mut clone), first);
+    random.upd_score(1.);
+    random.reset();
+    assert_eq!(random.score(), 0.);
+    assert_ne!(plays(&mut random), first);
+
+    let mut tournament = Tournament::new(5);
+    tournament
+        .add_agent_instance("detective", detective)
+        .add_agent("coop", || Box::new(CooperatingAgent::new()))
+        .add_agent("cheat", || Box::new(CheatingAgent::new()));
+    let scoreboard = tournament.run();
+    let mut fresh = Tournament::new(5);
+    fresh
+        .add_agent("detective", || Box::new(DetectiveAgent::new()))
+        .add_agent("coop", || Box::new(CooperatingAgent::new()))
+        .add_agent("cheat", || Box::new(CheatingAgent::new()));
+    assert_eq!(scoreboard.to_csv(), fresh.run().to_csv());
+}
+
+#[test]
+fn test_human_agent() {
+    // Plays tit-for-tat by hand.
+    let mut first = true;
+    let human = HumanAgent::new(move |opponent| match std::mem::take(&mut first) {
+        true => Play::Cooperate,
+        false => opponent,
+    });
+    let mut game = Game::new(Box::new(human), Box::new(DetectiveAgent::new()));
+    game.play_rounds(6);
+    let history = game.history();
+    assert_eq!(history[0].0, Play::Cooperate);
+    for (prev, next) in history.iter().zip(&history[1..]) {
+        assert_eq!(next.0, prev.1);
+    }
+
+    // Moves may as well come from another thread.
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let human = HumanAgent::new(move |_| receiver.recv().unwrap());
+    let mut game = Game::new(Box::new(human), Box::new(CooperatingAgent::new()));
+    std::thread::spawn(move || {
+        for play in [Play::Cheat, Play::Cooperate] {
+            sender.send(play).unwrap();
+        }
+    });
+    assert_eq!(
+        game.rounds(2).collect::<Vec<_>>(),
+        [RoundOutcome::LeftCheated, RoundOutcome::BothCooperated]
+    );
+}
+
+#[test]
+fn test_observers() {
+    let records = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mut game = Game::new(
+        Box::new(CooperatingAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    game.play_round();
+    let count = std::rc::Rc::new(std::cell::Cell::new(0));
+    game.add_observer({
+        let records = records.clone();
+        move |record| records.borrow_mut().push(*record)
+    })
+    .add_observer({
+        let count = count.clone();
+        move |_| count.set(count.get() + 1)
+    });
+    game.play_rounds(3);
+
+    assert_eq!(count.get(), 3);
+    assert_eq!(
+        records.borrow()[0],
+        RoundRecord {
+            left: Play::Cooperate,
+            right: Play::Cheat,
+            outcome: RoundOutcome::RightCheated,
+            left_score: 1.,
+            right_score: 5.,
+        }
+    );
+    let replay = game.replay().skip(1).collect::<Vec<_>>();
+    assert_eq!(*records.borrow(), replay);
+}
+
+#[test]
+fn test_score_matrix() {
+    let mut tournament = Tournament::new(5);
+    tournament
+        .add_agent("coop", || Box::new(CooperatingAgent::new()))
+        .add_agent("cheat", || Box::new(CheatingAgent::new()))
+        .add_agent("copycat", || Box::new(CopycatAgent::new()));
+    let matrix: ScoreMatrix = tournament.score_matrix(2);
+
+    assert_eq!(matrix.names(), ["coop", "cheat", "copycat"]);
+    assert_eq!(matrix.score("coop", "coop"), Some(2.));
+    assert_eq!(matrix.score("cheat", "copycat"), Some(0.6));
+    assert_eq!(matrix.score("copycat", "cheat"), Some(-0.2));
+    assert_eq!(matrix.score("coop", "nobody"), None);
+    assert_eq!(matrix.mean("coop"), Some(1.));
+    assert_eq!(
+        matrix.values(),
+        [vec![2., -1., 2.], vec![3., 0., 0.6], vec![2., -0.2, 2.]]
+    );
+    assert_eq!(matrix.row("cheat"), Some(&[3., 0., 0.6][..]));
+    assert_eq!(matrix.column("cheat"), Some(vec![-1., 0., -0.2]));
+    assert_eq!(matrix.column("nobody"), None);
+    assert_eq!(matrix.range(), Some((-1., 3.)));
+    assert_eq!(Tournament::new(5).score_matrix(1).range(), None);
+    assert_eq!(
+        matrix.to_csv(),
+        ",coop,cheat,copycat\n\
+         coop,2,-1,2\n\
+         cheat,3,0,0.6\n\
+         copycat,2,-0.2,2\n"
+    );
+
+    assert_eq!(tournament.score_matrix(0).score("cheat", "coop"), Some(0.));
+}
+
+#[test]
+fn test_generous_copycat() {
+    // Without forgiveness it is a plain copycat.
+    let mut game = Game::new(
+        Box::new(GenerousCopycatAgent::from_seed(0., 1)),
+        Box::new(CheatingAgent::new()),
+    );
+    assert_eq!(
+        game.rounds(3).collect::<Vec<_>>(),
+        [
+            RoundOutcome::RightCheated,
+            RoundOutcome::BothCheated,
+            RoundOutcome::BothCheated
+        ]
+    );
+
+    let mut game = Game::new(
+        Box::new(GenerousCopycatAgent::from_seed(1., 1)),
+        Box::new(CheatingAgent::new()),
+    );
+    assert!(game.rounds(3).all(|o| o == RoundOutcome::RightCheated));
+
+    let mut agent: Box<dyn Agent> = Box::new(GenerousCopycatAgent::from_seed(0.5, 3));
+    agent.action(Play::Cooperate);
+    let plays = (0..100)
+        .map(|_| agent.action(Play::Cheat))
+        .collect::<Vec<_>>();
+    let forgiven = plays.iter().filter(|&&p| p == Play::Cooperate).count();
+    assert!((30..70).contains(&forgiven));
+    assert_eq!(agent.action(Play::Cooperate), Play::Cooperate);
+
+    let mut again: Box<dyn Agent> = Box::new(GenerousCopycatAgent::from_seed(0.5, 3));
+    again.action(Play::Cooperate);
+    assert!(plays.iter().all(|&p| again.action(Play::Cheat) == p));
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(Play::Cheat.to_string(), "cheat");
+    assert_eq!(Play::Cooperate.to_string(), "cooperate");
+    assert_eq!(RoundOutcome::BothCooperated.to_string(), "both cooperated");
+    assert_eq!(RoundOutcome::RightCheated.to_string(), "right cheated");
+
+    let mut game = Game::new(
+        Box::new(CopycatAgent::new()),
+        Box::new(DetectiveAgent::new()),
+    );
+    game.play_rounds(3);
+    assert_eq!(
+        game.render_round_log(),
+        "\
+round  left       right      left score  right score
+    1  cooperate  cooperate           2            2
+    2  cooperate  cheat               1            5
+    3  cheat      cooperate           4            4
+"
+    );
+}